@@ -0,0 +1,193 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+use crate::TextEncoding;
+
+/// Everything that can go wrong while reading or writing a tag.
+///
+/// Offsets are in bytes, counted from the start of the data the tag was read from.
+#[derive(Debug)]
+pub enum Error {
+    /// The file could not be opened for reading.
+    Open { path: PathBuf, source: io::Error },
+    /// The file could not be created for writing.
+    Create { path: PathBuf, source: io::Error },
+    /// The rewritten file could not take the place of the original, or could not be given its
+    /// owner.
+    Replace { path: PathBuf, source: io::Error },
+    /// Reading or writing failed for a reason other than the data ending too soon.
+    Io(io::Error),
+    /// The data does not start with "ID3".
+    NotAnId3Tag,
+    /// The data ended before the 10 bytes of the tag header could be read.
+    TruncatedHeader,
+    /// The data ended while reading the extended header.
+    TruncatedExtendedHeader { offset: u64 },
+    /// The data ended while reading a frame. `frame_id` is `None` if the data ended before
+    /// the frame's ID could be read.
+    TruncatedFrame {
+        offset: u64,
+        frame_id: Option<String>,
+    },
+    /// The frame ID is not made of four ASCII characters.
+    InvalidFrameId { offset: u64, bytes: [u8; 4] },
+    /// The tag can't be converted to this version.
+    UnsupportedVersion { version: u8 },
+    /// Tags of this version can't hold text in this encoding.
+    UnsupportedEncoding { encoding: TextEncoding, version: u8 },
+    /// The timestamps are in MPEG frames, which can't be converted to time without decoding
+    /// the audio.
+    UnsupportedTimestampFormat,
+    /// There is no frame with ID `frame_id` identified by `key`, such as a comment's language
+    /// and description.
+    KeyedFrameNotFound { frame_id: String, key: String },
+    /// Line `line` (counted from 1) of a chapter list could not be parsed.
+    InvalidChapterList { line: usize, reason: String },
+    /// `value` is not a timestamp in the `yyyy-MM-ddTHH:mm:ss` format, or one of its shorter
+    /// forms, so it can't be the value of the time frame `frame_id`.
+    InvalidTimestamp { frame_id: String, value: String },
+    /// The frame's ID can't be written in a tag of this version, which needs IDs of 4 bytes.
+    UnwritableFrameId { frame_id: String, version: u8 },
+    /// The frame's data is malformed, so the frame can only be handled as raw bytes.
+    MalformedFrame { frame_id: String },
+    /// There is no frame with ID `frame_id` at the (zero indexed) position `index`. `found`
+    /// is how many frames with that ID the tag has.
+    FrameNotFound {
+        frame_id: String,
+        index: u32,
+        found: u32,
+    },
+}
+
+/// A specialized [`Result`](std::result::Result) for tag operations.
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// Turns a failed read into `on_eof` if the data ended too soon, or into [`Error::Io`]
+    /// otherwise.
+    pub(crate) fn from_read(error: io::Error, on_eof: impl FnOnce() -> Error) -> Error {
+        match error.kind() {
+            io::ErrorKind::UnexpectedEof => on_eof(),
+            _ => Error::Io(error),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Open { path, source } => {
+                write!(f, "Failed to open file {}: {}", path.display(), source)
+            }
+            Error::Create { path, source } => write!(
+                f,
+                "Failed to open file {} for writing: {}",
+                path.display(),
+                source
+            ),
+            Error::Replace { path, source } => write!(
+                f,
+                "Failed to replace file {} with the rewritten one: {}",
+                path.display(),
+                source
+            ),
+            Error::Io(source) => write!(f, "I/O error: {}", source),
+            Error::NotAnId3Tag => write!(
+                f,
+                "Invalid header. It does not start with \"ID3\" as it's first 3 bytes"
+            ),
+            Error::TruncatedHeader => {
+                write!(f, "Could not read the ID3v2 Header. File ended too soon.")
+            }
+            Error::TruncatedExtendedHeader { offset } => write!(
+                f,
+                "Could not read the extended header at byte {}. File ended too soon.",
+                offset
+            ),
+            Error::TruncatedFrame {
+                offset,
+                frame_id: Some(frame_id),
+            } => write!(
+                f,
+                "Failed to read data of frame \"{}\" at byte {}. File ended too soon.",
+                frame_id, offset
+            ),
+            Error::TruncatedFrame {
+                offset,
+                frame_id: None,
+            } => write!(
+                f,
+                "Failed to read frame header at byte {}. File ended too soon.",
+                offset
+            ),
+            Error::InvalidFrameId { offset, bytes } => {
+                write!(f, "Invalid frame id {:02X?} at byte {}", bytes, offset)
+            }
+            Error::UnsupportedVersion { version } => write!(
+                f,
+                "ID3v2.{} is not supported. Only ID3v2.3 and ID3v2.4 are",
+                version
+            ),
+            Error::UnsupportedEncoding { encoding, version } => {
+                write!(f, "ID3v2.{} tags can't hold {} text", version, encoding)
+            }
+            Error::UnsupportedTimestampFormat => write!(
+                f,
+                "Timestamps in MPEG frames are not supported. Only milliseconds are"
+            ),
+            Error::KeyedFrameNotFound { frame_id, key } => {
+                write!(f, "No frame \"{}\" found with {}", frame_id, key)
+            }
+            Error::InvalidChapterList { line, reason } => {
+                write!(
+                    f,
+                    "Line {} of the chapter list is invalid: {}",
+                    line, reason
+                )
+            }
+            Error::InvalidTimestamp { frame_id, value } => write!(
+                f,
+                "\"{}\" is not a valid timestamp for frame \"{}\". Timestamps look like yyyy-MM-ddTHH:mm:ss and may stop after any part",
+                value, frame_id
+            ),
+            Error::UnwritableFrameId { frame_id, version } => write!(
+                f,
+                "Frame id \"{}\" can't be written in an ID3v2.{} tag. Frame ids are 4 characters long",
+                frame_id, version
+            ),
+            Error::MalformedFrame { frame_id } => {
+                write!(f, "The data of frame \"{}\" is malformed", frame_id)
+            }
+            Error::FrameNotFound {
+                frame_id,
+                index,
+                found,
+            } => write!(
+                f,
+                "Frame \"{}\" number {} not found. There are only {} frames with that id",
+                frame_id,
+                index + 1,
+                found
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Open { source, .. }
+            | Error::Create { source, .. }
+            | Error::Replace { source, .. }
+            | Error::Io(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Error {
+        Error::Io(error)
+    }
+}
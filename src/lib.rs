@@ -0,0 +1,44 @@
+//! Reads, writes, removes and edits ID3v2 tags.
+//!
+//! The entry point is [`Mp3File`], which reads the [`Tag`] at the start of an MP3 file and
+//! writes it back along with the file's audio:
+//!
+//! ```no_run
+//! use id3v2::Mp3File;
+//! use std::path::Path;
+//!
+//! let path = Path::new("song.mp3");
+//! let mut file = Mp3File::from_path(path).unwrap();
+//! file.add_frame("TIT2".to_string(), "Song title".to_string());
+//! file.write_to_file(path).unwrap();
+//! ```
+//!
+//! Tags can also be read from and written to anything implementing [`std::io::Read`] +
+//! [`std::io::Seek`] and [`std::io::Write`], or from and to byte slices:
+//!
+//! ```no_run
+//! # fn tag_bytes() -> Vec<u8> { Vec::new() }
+//! let tag = id3v2::Tag::from_bytes(&tag_bytes()).unwrap();
+//! let bytes = tag.to_bytes().unwrap();
+//! ```
+//!
+//! Problems that don't stop a tag from being read, such as unknown frame IDs or a CRC that
+//! doesn't match, are reported as warnings through the [`log`] crate.
+
+pub mod error;
+mod mp3_file;
+pub mod utils;
+
+pub use error::{Error, Result};
+pub use mp3_file::{
+    format_chapter_list, is_comment_frame_id, is_string_known_id3v2_id, is_string_valid_id3v2_id,
+    is_text_frame_id, is_timestamp_frame_id, is_url_frame_id, is_user_defined_frame_id,
+    parse_chapter_list, rating_to_stars, sniff_mime_type, stars_to_rating, ChannelAdjustment,
+    ChannelType, Chapter, Comment, ContentType, EncapsulatedObject, ExtendedHeader, FrameBody,
+    FrameConversion, GainScope, Garbage, Genre, ID3v2Frame as Frame, Mp3File, OwnedData, Picture,
+    PictureType, PlayCounter, Popularimeter, RelativeVolume, ReplayGain, SetPosition,
+    SynchronisedLyrics, SynchronisedText, TableOfContents, Tag, TagRestrictions, TextEncoding,
+    TextFrame, Timestamp, TimestampFormat, UserText, UserUrl, WriteOptions, DEFAULT_LANGUAGE,
+    ID3V1_GENRES, KNOWN_ID3V2_IDS, MAX_UNIQUE_FILE_IDENTIFIER_LENGTH, PICTURE_TYPES,
+    TIMESTAMP_FRAME_IDS,
+};
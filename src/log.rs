@@ -0,0 +1,60 @@
+//! Renders the tool's own messages, and the warnings the `id3v2` library reports through the
+//! `log` crate, on stderr.
+
+use colored::Colorize;
+use log::{Level, LevelFilter, Metadata, Record};
+use std::env;
+
+const DEFAULT_LOG_LEVEL: LevelFilter = LevelFilter::Info;
+
+struct Logger;
+
+static LOGGER: Logger = Logger;
+
+impl log::Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        match record.level() {
+            Level::Error => eprintln!("{}  {}", "❌".red(), record.args()),
+            Level::Warn => eprintln!("{}  {}", "⚠".yellow(), record.args()),
+            _ => eprintln!("{}", record.args()),
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+fn get_level_from_env() -> LevelFilter {
+    let env_string_level = env::var("RUST_LOG")
+        .unwrap_or_else(|_| "INFO".to_string())
+        .to_lowercase();
+
+    match &env_string_level[..] {
+        "info" => LevelFilter::Info,
+        "trace" => LevelFilter::Trace,
+        "debug" => LevelFilter::Debug,
+        "warn" | "warning" => LevelFilter::Warn,
+        "err" | "error" => LevelFilter::Error,
+        _ => DEFAULT_LOG_LEVEL,
+    }
+}
+
+pub fn init(initial_level: Option<LevelFilter>) {
+    let level = initial_level.unwrap_or_else(get_level_from_env);
+    // Only fails if a logger was already set, which would then keep receiving the messages
+    let _ = log::set_logger(&LOGGER);
+    log::set_max_level(level);
+}
+
+pub fn warn(message: String) {
+    log::warn!("{}", message)
+}
+pub fn error(message: String) {
+    log::error!("{}", message)
+}
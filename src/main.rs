@@ -1,11 +1,20 @@
 use clap::{Parser, Subcommand};
-use std::path::PathBuf;
-use utils::log;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 
-use mp3_file::{is_string_known_id3v2_id, is_string_valid_id3v2_id, Mp3File, KNOWN_ID3V2_IDS};
+use id3v2::utils::BinaryFormat;
+use id3v2::{
+    format_chapter_list, is_comment_frame_id, is_string_known_id3v2_id, is_string_valid_id3v2_id,
+    is_text_frame_id, is_timestamp_frame_id, is_user_defined_frame_id, parse_chapter_list,
+    sniff_mime_type, stars_to_rating, Comment, EncapsulatedObject, Error, ExtendedHeader, Frame,
+    FrameBody, GainScope, Mp3File, OwnedData, Picture, PictureType, Popularimeter, ReplayGain,
+    SetPosition, SynchronisedLyrics, TagRestrictions, TextEncoding, TextFrame, Timestamp, UserText,
+    UserUrl, WriteOptions, DEFAULT_LANGUAGE, KNOWN_ID3V2_IDS, MAX_UNIQUE_FILE_IDENTIFIER_LENGTH,
+    PICTURE_TYPES,
+};
 
-mod mp3_file;
-mod utils;
+mod log;
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
@@ -17,6 +26,25 @@ struct Args {
         short
     )]
     human_readable: bool,
+    #[clap(
+        value_parser,
+        long,
+        help = "Keeps the modification time of rewritten files, as if they hadn't been touched"
+    )]
+    preserve_mtime: bool,
+    #[clap(
+        value_parser,
+        long,
+        help = "Doesn't give rewritten files the owner and group of the original"
+    )]
+    no_preserve_owner: bool,
+    #[clap(
+        value_parser,
+        long,
+        default_value_t = 1024,
+        help = "The bytes of padding to leave after a tag that outgrew its space, so later edits don't rewrite the whole file"
+    )]
+    padding: u32,
     #[clap(subcommand)]
     command: Command,
 }
@@ -28,14 +56,45 @@ enum Command {
         file: PathBuf,
         #[clap(value_parser, long, help = "Also prints the frame's flags")]
         frame_flags: bool,
+        #[clap(
+            value_parser,
+            long,
+            help = "Shows the data of UFID and PRIV frames as base64 instead of hex"
+        )]
+        base64: bool,
     },
     Write {
         #[clap(value_parser, help = "The MP3 file to be used.")]
         file: PathBuf,
-        #[clap(value_parser, help = "The ID of the frame")]
+        #[clap(
+            value_parser,
+            help = "The ID of the frame. TXXX and WXXX frames can be followed by their description, as in TXXX:CATALOG_ID"
+        )]
         frame_id: String,
-        #[clap(value_parser, help = "The data of the frame")]
-        data: String,
+        #[clap(
+            value_parser,
+            required = true,
+            help = "The data of the frame. Text frames can have more than one value"
+        )]
+        data: Vec<String>,
+        #[clap(
+            value_parser = parse_language,
+            long,
+            help = "The language of COMM and USLT frames, as a three letter ISO-639-2 code. Defaults to eng"
+        )]
+        lang: Option<String>,
+        #[clap(
+            value_parser,
+            long,
+            help = "The description of COMM, USLT, TXXX and WXXX frames. TXXX:DESCRIPTION can be used instead"
+        )]
+        desc: Option<String>,
+        #[clap(
+            value_parser = parse_encoding,
+            long,
+            help = "The encoding of text frames: latin1, utf16, utf16be or utf8"
+        )]
+        encoding: Option<TextEncoding>,
     },
     Delete {
         #[clap(value_parser, help = "The MP3 file to be used.")]
@@ -44,80 +103,762 @@ enum Command {
         frame_id: String,
         #[clap(value_parser, short, long, help = "The ID of the frame to delete")]
         frame_index: Option<u32>,
+        #[clap(
+            value_parser = parse_language,
+            long,
+            help = "The language of COMM and USLT frames, as a three letter ISO-639-2 code. Defaults to eng"
+        )]
+        lang: Option<String>,
+        #[clap(
+            value_parser,
+            long,
+            help = "The description of COMM, USLT, TXXX and WXXX frames. TXXX:DESCRIPTION can be used instead"
+        )]
+        desc: Option<String>,
     },
     Edit {
         #[clap(value_parser, help = "The MP3 file to be used.")]
         file: PathBuf,
         #[clap(value_parser, help = "The ID of the frame to delete")]
         frame_id: String,
-        #[clap(value_parser, help = "The data of the frame that'll be replaced")]
-        data: String,
+        #[clap(
+            value_parser,
+            required = true,
+            help = "The data of the frame that'll be replaced. Text frames can have more than one value"
+        )]
+        data: Vec<String>,
         #[clap(value_parser, short, long, help = "The ID of the frame to delete")]
         frame_index: Option<u32>,
+        #[clap(
+            value_parser = parse_language,
+            long,
+            help = "The language of COMM and USLT frames, as a three letter ISO-639-2 code. Defaults to eng"
+        )]
+        lang: Option<String>,
+        #[clap(
+            value_parser,
+            long,
+            help = "The description of COMM, USLT, TXXX and WXXX frames. TXXX:DESCRIPTION can be used instead"
+        )]
+        desc: Option<String>,
+        #[clap(
+            value_parser = parse_encoding,
+            long,
+            help = "The encoding of text frames: latin1, utf16, utf16be or utf8"
+        )]
+        encoding: Option<TextEncoding>,
+    },
+    Upgrade {
+        #[clap(
+            value_parser,
+            help = "The MP3 file with an ID3v2.2 tag to rewrite as ID3v2.3"
+        )]
+        file: PathBuf,
+    },
+    Convert {
+        #[clap(value_parser, help = "The MP3 file to be used.")]
+        file: PathBuf,
+        #[clap(
+            value_parser = parse_version,
+            long,
+            help = "The version to convert the tag to. Either 2.3 or 2.4"
+        )]
+        to: u8,
+    },
+    /// Lists, extracts, embeds, replaces or removes attached pictures (APIC frames).
+    Picture {
+        #[clap(subcommand)]
+        command: PictureCommand,
+    },
+    /// Imports LRC files into synchronised lyrics (SYLT frames) and exports them back.
+    SyncedLyrics {
+        #[clap(subcommand)]
+        command: SyncedLyricsCommand,
+    },
+    /// Lists chapters (CHAP and CTOC frames), and imports or exports them as a chapter list.
+    Chapters {
+        #[clap(subcommand)]
+        command: ChaptersCommand,
+    },
+    /// Reads and sets star ratings (POPM frames) and play counts (PCNT frames).
+    Rating {
+        #[clap(subcommand)]
+        command: RatingCommand,
+    },
+    /// Lists, sets or removes unique file identifiers (UFID frames) by owner.
+    Ufid {
+        #[clap(subcommand)]
+        command: OwnedDataCommand,
+    },
+    /// Lists, sets or removes private frames (PRIV frames) by owner.
+    Private {
+        #[clap(subcommand)]
+        command: OwnedDataCommand,
+    },
+    /// Lists, embeds, extracts or removes files stored in the tag (GEOB frames).
+    Object {
+        #[clap(subcommand)]
+        command: ObjectCommand,
+    },
+    /// Reads and writes ReplayGain values, kept in RVA2 and TXXX:REPLAYGAIN_* frames.
+    Gain {
+        #[clap(subcommand)]
+        command: GainCommand,
+    },
+    /// Shows, sets or removes the extended header of ID3v2.3 and ID3v2.4 tags.
+    ExtendedHeader {
+        #[clap(subcommand)]
+        command: ExtendedHeaderCommand,
+    },
+    /// Writes consecutive track numbers (TRCK frames) to a list of files, in the given order.
+    NumberTracks {
+        #[clap(value_parser, required = true, help = "The MP3 files to number")]
+        files: Vec<PathBuf>,
+        #[clap(
+            value_parser,
+            long,
+            help = "Numbers the files in the order of their names instead of the given order"
+        )]
+        sort: bool,
+        #[clap(
+            value_parser,
+            long,
+            default_value_t = 1,
+            help = "The number of the first track"
+        )]
+        start: u32,
+        #[clap(
+            value_parser,
+            long,
+            help = "The number of tracks. Defaults to the number of the last track"
+        )]
+        total: Option<u32>,
+        #[clap(
+            value_parser,
+            long,
+            default_value_t = 0,
+            help = "Pads the numbers with zeros to this many digits"
+        )]
+        pad: usize,
+        #[clap(
+            value_parser = parse_set_position,
+            long,
+            help = "Also writes this disc number, as in 1 or 1/2, to the TPOS frame of every file"
+        )]
+        disc: Option<SetPosition>,
     },
     ShowKnownFrameIds {},
 }
 
-fn main() -> Result<(), ()> {
+/// Size limits are levels from 0, the loosest, to 3, the strictest.
+#[derive(Subcommand, Debug)]
+enum ExtendedHeaderCommand {
+    /// Shows whether the tag has an extended header, and what it holds.
+    Show {
+        #[clap(value_parser, help = "The MP3 file to be used.")]
+        file: PathBuf,
+    },
+    /// Adds an extended header, or replaces the existing one. Only ID3v2.4 tags can be marked as
+    /// updates or declare restrictions.
+    Set {
+        #[clap(value_parser, help = "The MP3 file to be used.")]
+        file: PathBuf,
+        #[clap(value_parser, long, help = "Stores a CRC-32 of the tag")]
+        crc: bool,
+        #[clap(
+            value_parser,
+            long,
+            help = "Marks the tag as an update of an earlier one"
+        )]
+        update: bool,
+        #[clap(
+            value_parser = clap::value_parser!(u8).range(0..=3),
+            long,
+            help = "Restricts the number of frames and the size of the tag"
+        )]
+        tag_size_limit: Option<u8>,
+        #[clap(
+            value_parser,
+            long,
+            help = "Restricts text to the ISO-8859-1 and UTF-8 encodings"
+        )]
+        text_encoding_limit: bool,
+        #[clap(
+            value_parser = clap::value_parser!(u8).range(0..=3),
+            long,
+            help = "Restricts the length of text fields"
+        )]
+        text_size_limit: Option<u8>,
+        #[clap(value_parser, long, help = "Restricts images to PNG and JPEG")]
+        image_encoding_limit: bool,
+        #[clap(
+            value_parser = clap::value_parser!(u8).range(0..=3),
+            long,
+            help = "Restricts the dimensions of images"
+        )]
+        image_size_limit: Option<u8>,
+    },
+    /// Removes the extended header.
+    Remove {
+        #[clap(value_parser, help = "The MP3 file to be used.")]
+        file: PathBuf,
+    },
+}
+
+/// Gains are in dB, and peaks are fractions of full scale, such as 0.98.
+#[derive(Subcommand, Debug)]
+enum GainCommand {
+    /// Shows the track and album gains, and every RVA2 frame.
+    Show {
+        #[clap(value_parser, help = "The MP3 file to be used.")]
+        file: PathBuf,
+    },
+    /// Sets the track or album gain, in both the TXXX:REPLAYGAIN_* and the RVA2 frames. ID3v2.3
+    /// tags have no RVA2 frame, so only the TXXX frames are written.
+    Set {
+        #[clap(value_parser, help = "The MP3 file to be used.")]
+        file: PathBuf,
+        #[clap(value_parser, long, allow_hyphen_values = true)]
+        track_gain: Option<f64>,
+        #[clap(value_parser, long, requires = "track-gain")]
+        track_peak: Option<f64>,
+        #[clap(value_parser, long, allow_hyphen_values = true)]
+        album_gain: Option<f64>,
+        #[clap(value_parser, long, requires = "album-gain")]
+        album_peak: Option<f64>,
+    },
+    /// Removes the track and album gains.
+    Remove {
+        #[clap(value_parser, help = "The MP3 file to be used.")]
+        file: PathBuf,
+    },
+}
+
+/// Objects are identified by their description, which may be empty.
+#[derive(Subcommand, Debug)]
+enum ObjectCommand {
+    /// Lists every object with its MIME type, filename, size and description.
+    List {
+        #[clap(value_parser, help = "The MP3 file to be used.")]
+        file: PathBuf,
+    },
+    /// Stores a file in the tag, replacing the object with the same description.
+    Embed {
+        #[clap(value_parser, help = "The MP3 file to be used.")]
+        file: PathBuf,
+        #[clap(value_parser, help = "The file to embed")]
+        object: PathBuf,
+        #[clap(value_parser, long, default_value = "")]
+        description: String,
+        #[clap(
+            value_parser,
+            long,
+            help = "The MIME type of the file. Detected for images, application/octet-stream otherwise"
+        )]
+        mime_type: Option<String>,
+        #[clap(
+            value_parser = parse_encoding,
+            long,
+            help = "The encoding of the filename and description: latin1, utf16, utf16be or utf8"
+        )]
+        encoding: Option<TextEncoding>,
+    },
+    /// Writes an object back to a file.
+    Extract {
+        #[clap(value_parser, help = "The MP3 file to be used.")]
+        file: PathBuf,
+        #[clap(value_parser, long, default_value = "")]
+        description: String,
+        #[clap(
+            value_parser,
+            short,
+            long,
+            help = "Where to write the object. Defaults to its stored filename"
+        )]
+        output: Option<PathBuf>,
+    },
+    Remove {
+        #[clap(value_parser, help = "The MP3 file to be used.")]
+        file: PathBuf,
+        #[clap(value_parser, long, default_value = "")]
+        description: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum OwnedDataCommand {
+    /// Lists every frame with its owner and data.
+    List {
+        #[clap(value_parser, help = "The MP3 file to be used.")]
+        file: PathBuf,
+        #[clap(value_parser, long, help = "Shows the data as base64 instead of hex")]
+        base64: bool,
+    },
+    /// Sets the data of the frame of an owner, replacing the one there was.
+    Set {
+        #[clap(value_parser, help = "The MP3 file to be used.")]
+        file: PathBuf,
+        #[clap(
+            value_parser,
+            help = "Who the data belongs to, usually a URL or an email"
+        )]
+        owner: String,
+        #[clap(
+            value_parser,
+            help = "The data, as text unless --hex or --base64 is used"
+        )]
+        data: String,
+        #[clap(value_parser, long, conflicts_with = "base64")]
+        hex: bool,
+        #[clap(value_parser, long)]
+        base64: bool,
+    },
+    /// Removes the frame of an owner.
+    Remove {
+        #[clap(value_parser, help = "The MP3 file to be used.")]
+        file: PathBuf,
+        #[clap(value_parser)]
+        owner: String,
+    },
+}
+
+/// Star ratings are stored as a byte from 1 to 255. 1 to 5 stars are written as 1, 64, 128, 196
+/// and 255, like most players do.
+#[derive(Subcommand, Debug)]
+enum RatingCommand {
+    /// Shows every rating and the play count.
+    Show {
+        #[clap(value_parser, help = "The MP3 file to be used.")]
+        file: PathBuf,
+    },
+    /// Sets the rating of the user identified by --email, keeping their play count.
+    Set {
+        #[clap(value_parser, help = "The MP3 file to be used.")]
+        file: PathBuf,
+        #[clap(
+            value_parser,
+            help = "From 1 to 5 stars, or 0 to mark the file as not rated"
+        )]
+        rating: u8,
+        #[clap(value_parser, long, default_value = "")]
+        email: String,
+        #[clap(
+            value_parser,
+            long,
+            help = "Write the rating as a byte from 0 to 255 instead of stars"
+        )]
+        raw: bool,
+    },
+    /// Removes the rating of the user identified by --email.
+    Remove {
+        #[clap(value_parser, help = "The MP3 file to be used.")]
+        file: PathBuf,
+        #[clap(value_parser, long, default_value = "")]
+        email: String,
+    },
+    /// Counts plays in the PCNT frame, and in the rating of the user identified by --email if
+    /// there's one.
+    Played {
+        #[clap(value_parser, help = "The MP3 file to be used.")]
+        file: PathBuf,
+        #[clap(value_parser, long, default_value = "")]
+        email: String,
+        #[clap(value_parser, long, default_value = "1")]
+        count: u64,
+    },
+}
+
+/// Chapter lists have one `start[-end] title[ | url]` line per chapter, with times written as
+/// `[hh:]mm:ss[.mmm]`, such as `01:30.5-03:00 Second movement`.
+#[derive(Subcommand, Debug)]
+enum ChaptersCommand {
+    /// Lists the tables of contents and every chapter with its times, title and sub-frames.
+    List {
+        #[clap(value_parser, help = "The MP3 file to be used.")]
+        file: PathBuf,
+    },
+    /// Replaces every chapter and table of contents with the ones in a chapter list. A chapter
+    /// without an end time ends where the next one starts, and the last one at the length in
+    /// the TLEN frame.
+    Import {
+        #[clap(value_parser, help = "The MP3 file to be used.")]
+        file: PathBuf,
+        #[clap(value_parser, help = "The chapter list to import")]
+        chapters: PathBuf,
+    },
+    Export {
+        #[clap(value_parser, help = "The MP3 file to be used.")]
+        file: PathBuf,
+        #[clap(
+            value_parser,
+            short,
+            long,
+            help = "The chapter list to write. The chapters are printed if omitted"
+        )]
+        output: Option<PathBuf>,
+    },
+    /// Removes every chapter and table of contents.
+    Remove {
+        #[clap(value_parser, help = "The MP3 file to be used.")]
+        file: PathBuf,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum SyncedLyricsCommand {
+    /// Adds the lyrics of an LRC file, replacing the ones with the same language and description.
+    Import {
+        #[clap(value_parser, help = "The MP3 file to be used.")]
+        file: PathBuf,
+        #[clap(value_parser, help = "The LRC file to import")]
+        lrc: PathBuf,
+        #[clap(
+            value_parser = parse_language,
+            long,
+            default_value = "eng",
+            help = "The language of the lyrics, as a three letter ISO-639-2 code"
+        )]
+        lang: String,
+        #[clap(value_parser, long, default_value = "")]
+        desc: String,
+        #[clap(
+            value_parser = parse_encoding,
+            long,
+            help = "The encoding of the lyrics: latin1, utf16, utf16be or utf8"
+        )]
+        encoding: Option<TextEncoding>,
+    },
+    Export {
+        #[clap(value_parser, help = "The MP3 file to be used.")]
+        file: PathBuf,
+        #[clap(
+            value_parser = parse_language,
+            long,
+            default_value = "eng",
+            help = "The language of the lyrics, as a three letter ISO-639-2 code"
+        )]
+        lang: String,
+        #[clap(value_parser, long, default_value = "")]
+        desc: String,
+        #[clap(
+            value_parser,
+            short,
+            long,
+            help = "The LRC file to write. The lyrics are printed if omitted"
+        )]
+        output: Option<PathBuf>,
+    },
+    Remove {
+        #[clap(value_parser, help = "The MP3 file to be used.")]
+        file: PathBuf,
+        #[clap(
+            value_parser = parse_language,
+            long,
+            default_value = "eng",
+            help = "The language of the lyrics, as a three letter ISO-639-2 code"
+        )]
+        lang: String,
+        #[clap(value_parser, long, default_value = "")]
+        desc: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum PictureCommand {
+    /// Lists every picture with its index, type, MIME type, dimensions, size and description.
+    List {
+        #[clap(value_parser, help = "The MP3 file to be used.")]
+        file: PathBuf,
+    },
+    Extract {
+        #[clap(value_parser, help = "The MP3 file to be used.")]
+        file: PathBuf,
+        #[clap(
+            value_parser,
+            short,
+            long,
+            help = "The index of the picture to extract. Every picture is extracted if omitted"
+        )]
+        index: Option<u32>,
+        #[clap(
+            value_parser,
+            short,
+            long,
+            help = "The file to write the picture to, or the directory to write every picture to if no index is given"
+        )]
+        output: Option<PathBuf>,
+    },
+    Embed {
+        #[clap(value_parser, help = "The MP3 file to be used.")]
+        file: PathBuf,
+        #[clap(value_parser, help = "The image file to embed")]
+        image: PathBuf,
+        #[clap(
+            value_parser = parse_picture_type,
+            long = "type",
+            default_value = "front-cover",
+            help = "What the picture depicts. Use show-picture-types to see every type"
+        )]
+        picture_type: PictureType,
+        #[clap(value_parser, long, default_value = "")]
+        description: String,
+        #[clap(
+            value_parser,
+            long,
+            help = "The MIME type of the image. Detected from the image if omitted"
+        )]
+        mime_type: Option<String>,
+    },
+    Replace {
+        #[clap(value_parser, help = "The MP3 file to be used.")]
+        file: PathBuf,
+        #[clap(value_parser, help = "The new image file")]
+        image: PathBuf,
+        #[clap(
+            value_parser,
+            short,
+            long,
+            help = "The index of the picture to replace"
+        )]
+        index: Option<u32>,
+        #[clap(
+            value_parser = parse_picture_type,
+            long = "type",
+            help = "The new picture type. Kept as is if omitted"
+        )]
+        picture_type: Option<PictureType>,
+        #[clap(
+            value_parser,
+            long,
+            help = "The new description. Kept as is if omitted"
+        )]
+        description: Option<String>,
+        #[clap(
+            value_parser,
+            long,
+            help = "The MIME type of the image. Detected from the image if omitted"
+        )]
+        mime_type: Option<String>,
+    },
+    Remove {
+        #[clap(value_parser, help = "The MP3 file to be used.")]
+        file: PathBuf,
+        #[clap(value_parser, short, long, help = "The index of the picture to remove")]
+        index: Option<u32>,
+    },
+    ShowPictureTypes {},
+}
+
+/// Everything that makes the program exit with a non-zero code.
+#[derive(Debug)]
+enum CliError {
+    /// An argument given by the user is invalid. The reason has already been reported.
+    InvalidArgument,
+    Id3v2(Error),
+}
+
+impl From<Error> for CliError {
+    fn from(error: Error) -> CliError {
+        CliError::Id3v2(error)
+    }
+}
+
+fn main() -> ExitCode {
     log::init(None);
     let args = Args::parse();
+    match run(args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(CliError::InvalidArgument) => ExitCode::from(2),
+        Err(CliError::Id3v2(error)) => {
+            report_error(&error);
+            ExitCode::from(exit_code(&error))
+        }
+    }
+}
+
+fn run(args: Args) -> Result<(), CliError> {
+    let write_options = &WriteOptions {
+        preserve_modification_time: args.preserve_mtime,
+        preserve_ownership: !args.no_preserve_owner,
+        padding: args.padding,
+        ..WriteOptions::default()
+    };
     match args.command {
         Command::Read {
             file: file_path,
             frame_flags,
+            base64,
         } => {
             let mp3_file = Mp3File::from_path(&file_path)?;
             println!(
                 "{}",
-                mp3_file.format_frames(frame_flags, args.human_readable)
+                mp3_file.format_frames(frame_flags, args.human_readable, binary_format(base64))
             );
         }
         Command::Write {
             file: file_path,
             data,
             frame_id,
+            lang,
+            desc,
+            encoding,
         } => {
-            if !validate_frame_id(&frame_id) {
-                return Err(());
+            let (frame_id, desc) = split_frame_id(frame_id, desc)?;
+            if !validate_frame_id(&frame_id) || !validate_frame_key(&frame_id, &lang, &desc) {
+                return Err(CliError::InvalidArgument);
             }
             let mut mp3_file = Mp3File::from_path(&file_path)?;
-            mp3_file.add_frame(frame_id, data);
-            mp3_file.write_to_file(&file_path)?;
+            if is_user_defined_frame_id(&frame_id) {
+                let description = desc.unwrap_or_default();
+                set_user_defined_frame(&mut mp3_file, &frame_id, description, data, encoding)?;
+            } else if is_comment_frame_id(&frame_id) {
+                let comment = comment_from_args(
+                    lang.unwrap_or_else(|| DEFAULT_LANGUAGE.to_string()),
+                    desc.unwrap_or_default(),
+                    single_value(data)?,
+                    encoding,
+                    mp3_file.major_version(),
+                );
+                mp3_file.set_comment(&frame_id, comment)?;
+            } else if is_timestamp_frame_id(&frame_id) {
+                set_timestamp(&mut mp3_file, &frame_id, single_value(data)?)?;
+            } else if is_text_frame_id(&frame_id) {
+                let text_frame = text_frame_from_args(data, encoding, mp3_file.major_version());
+                mp3_file.add_text_frame(frame_id, &text_frame)?;
+            } else {
+                mp3_file.add_frame(frame_id, single_value(data)?);
+            }
+            mp3_file.write_to_file_with_options(&file_path, write_options)?;
         }
         Command::Edit {
             file: file_path,
             frame_id,
             data,
             frame_index,
+            lang,
+            desc,
+            encoding,
         } => {
+            let (frame_id, desc) = split_frame_id(frame_id, desc)?;
             let unwraped_frame_index = frame_index.unwrap_or(1);
-            if !validate_frame_id(&frame_id) || !validate_frame_index(unwraped_frame_index) {
-                return Err(());
+            if !validate_frame_id(&frame_id)
+                || !validate_frame_index(unwraped_frame_index)
+                || !validate_frame_key(&frame_id, &lang, &desc)
+            {
+                return Err(CliError::InvalidArgument);
             }
             let zero_indexed_frame = unwraped_frame_index - 1;
 
             let mut mp3_file = Mp3File::from_path(&file_path)?;
 
-            mp3_file
-                .edit_frame(&frame_id, data, zero_indexed_frame)
-                .or_else(|largest_found_index| {
-                    error_no_frame_with_id_found(
-                        &frame_id,
-                        unwraped_frame_index,
-                        largest_found_index,
-                    );
-                    Err(())
-                })?;
-
-            mp3_file.write_to_file(&file_path)?;
+            if is_user_defined_frame_id(&frame_id) {
+                // User defined frames are addressed by description when it's given, and by
+                // position otherwise
+                let description = match desc {
+                    Some(description) => {
+                        let exists = match frame_id.as_str() {
+                            "TXXX" => mp3_file.user_text(&description).is_some(),
+                            _ => mp3_file.user_url(&description).is_some(),
+                        };
+                        if !exists {
+                            return Err(Error::KeyedFrameNotFound {
+                                frame_id,
+                                key: format!("description \"{}\"", description),
+                            }
+                            .into());
+                        }
+                        description
+                    }
+                    None => mp3_file
+                        .frame_at(&frame_id, zero_indexed_frame)?
+                        .body()
+                        .description()
+                        .ok_or_else(|| Error::MalformedFrame {
+                            frame_id: frame_id.clone(),
+                        })?
+                        .to_string(),
+                };
+                set_user_defined_frame(&mut mp3_file, &frame_id, description, data, encoding)?;
+            } else if is_comment_frame_id(&frame_id) {
+                // Comments are addressed by language and description when either is given,
+                // and by position otherwise
+                let (language, description) = if lang.is_some() || desc.is_some() {
+                    let language = lang.unwrap_or_else(|| DEFAULT_LANGUAGE.to_string());
+                    let description = desc.unwrap_or_default();
+                    if mp3_file
+                        .comment(&frame_id, &language, &description)
+                        .is_none()
+                    {
+                        return Err(not_found_by_language(frame_id, &language, &description).into());
+                    }
+                    (language, description)
+                } else {
+                    let comment = mp3_file.comment_at(&frame_id, zero_indexed_frame)?;
+                    (comment.language.clone(), comment.description.clone())
+                };
+                let comment = comment_from_args(
+                    language,
+                    description,
+                    single_value(data)?,
+                    encoding,
+                    mp3_file.major_version(),
+                );
+                mp3_file.set_comment(&frame_id, comment)?;
+            } else if is_timestamp_frame_id(&frame_id) {
+                // A tag has a single frame of each kind. ID3v2.3 tags may keep it in other
+                // frames, such as TYER
+                if mp3_file.timestamp(&frame_id).is_none() {
+                    return Err(Error::FrameNotFound {
+                        frame_id,
+                        index: zero_indexed_frame,
+                        found: 0,
+                    }
+                    .into());
+                }
+                set_timestamp(&mut mp3_file, &frame_id, single_value(data)?)?;
+            } else if is_text_frame_id(&frame_id) {
+                let text_frame = text_frame_from_args(data, encoding, mp3_file.major_version());
+                mp3_file.edit_text_frame(&frame_id, &text_frame, zero_indexed_frame)?;
+            } else {
+                mp3_file.edit_frame(&frame_id, single_value(data)?, zero_indexed_frame)?;
+            }
+            mp3_file.write_to_file_with_options(&file_path, write_options)?;
         }
         Command::Delete {
             file: file_path,
             frame_index,
             frame_id,
+            lang,
+            desc,
         } => {
+            let (frame_id, desc) = split_frame_id(frame_id, desc)?;
             let unwraped_frame_index = frame_index.unwrap_or(1);
-            if !validate_frame_id(&frame_id) || !validate_frame_index(unwraped_frame_index) {
+            if !validate_frame_id(&frame_id)
+                || !validate_frame_index(unwraped_frame_index)
+                || !validate_frame_key(&frame_id, &lang, &desc)
+            {
+                return Err(CliError::InvalidArgument);
+            }
+            if let (true, Some(description)) = (is_user_defined_frame_id(&frame_id), &desc) {
+                println!(
+                    "Removing the \"{}\" frame with description \"{}\"",
+                    frame_id, description
+                );
+                let mut mp3_file = Mp3File::from_path(&file_path)?;
+                mp3_file.remove_user_defined_frame(&frame_id, description)?;
+                mp3_file.write_to_file_with_options(&file_path, write_options)?;
+                return Ok(());
+            }
+            if lang.is_some() || desc.is_some() {
+                let language = lang.unwrap_or_else(|| DEFAULT_LANGUAGE.to_string());
+                let description = desc.unwrap_or_default();
+                println!(
+                    "Removing the \"{}\" frame with language \"{}\" and description \"{}\"",
+                    frame_id, language, description
+                );
+                let mut mp3_file = Mp3File::from_path(&file_path)?;
+                mp3_file.remove_comment(&frame_id, &language, &description)?;
+                mp3_file.write_to_file_with_options(&file_path, write_options)?;
                 return Ok(());
             }
             let zero_indexed_frame = unwraped_frame_index - 1;
@@ -128,18 +869,86 @@ fn main() -> Result<(), ()> {
                 frame_id
             );
             let mut mp3_file = Mp3File::from_path(&file_path)?;
-            match mp3_file.remove_frame(&frame_id, zero_indexed_frame) {
-                Ok(_) => (),
-                Err(largest_found_index) => {
-                    error_no_frame_with_id_found(
-                        &frame_id,
-                        zero_indexed_frame,
-                        largest_found_index,
-                    );
-                    return Ok(());
+            mp3_file.remove_frame(&frame_id, zero_indexed_frame)?;
+            mp3_file.write_to_file_with_options(&file_path, write_options)?;
+        }
+        Command::Upgrade { file: file_path } => {
+            let mut mp3_file = Mp3File::from_path(&file_path)?;
+            if mp3_file.major_version() != 2 {
+                log::warn(format!(
+                    "The tag is already ID3v2.{}. Nothing to upgrade.",
+                    mp3_file.major_version()
+                ));
+                return Ok(());
+            }
+            for frame_id in mp3_file.upgrade_v22() {
+                log::warn(format!(
+                    "Frame \"{}\" has no ID3v2.3 equivalent and was dropped",
+                    frame_id
+                ));
+            }
+            mp3_file.write_to_file_with_options(&file_path, write_options)?;
+        }
+        Command::Convert {
+            file: file_path,
+            to,
+        } => {
+            let mut mp3_file = Mp3File::from_path(&file_path)?;
+            for conversion in mp3_file.convert(to)? {
+                if conversion.is_lossy() {
+                    log::warn(conversion.to_string());
+                } else {
+                    println!("{}", conversion);
+                }
+            }
+            mp3_file.write_to_file_with_options(&file_path, write_options)?;
+        }
+        Command::Picture { command } => run_picture_command(command, write_options)?,
+        Command::SyncedLyrics { command } => run_synced_lyrics_command(command, write_options)?,
+        Command::Chapters { command } => run_chapters_command(command, write_options)?,
+        Command::Rating { command } => run_rating_command(command, write_options)?,
+        Command::Ufid { command } => run_owned_data_command("UFID", command, write_options)?,
+        Command::Private { command } => run_owned_data_command("PRIV", command, write_options)?,
+        Command::Object { command } => run_object_command(command, write_options)?,
+        Command::Gain { command } => run_gain_command(command, write_options)?,
+        Command::ExtendedHeader { command } => run_extended_header_command(command, write_options)?,
+        Command::NumberTracks {
+            mut files,
+            sort,
+            start,
+            total,
+            pad,
+            disc,
+        } => {
+            if sort {
+                files.sort_by(|a, b| a.file_name().cmp(&b.file_name()).then_with(|| a.cmp(b)));
+            }
+            if start == 0 {
+                log::error("Tracks are numbered from 1, not zero".to_string());
+                return Err(CliError::InvalidArgument);
+            }
+            let last = start.saturating_add(files.len() as u32 - 1);
+            let total = total.unwrap_or(last);
+            if total < last {
+                log::error(format!(
+                    "The total can't be {}, since the last track is number {}",
+                    total, last
+                ));
+                return Err(CliError::InvalidArgument);
+            }
+            for (number, file_path) in (start..).zip(files.iter()) {
+                let track = SetPosition {
+                    number,
+                    total: Some(total),
+                };
+                let mut mp3_file = Mp3File::from_path(file_path)?;
+                mp3_file.set_track(&track, pad);
+                if let Some(disc) = &disc {
+                    mp3_file.set_disc(disc, pad);
                 }
+                mp3_file.write_to_file_with_options(file_path, write_options)?;
+                println!("{} {}", track.to_padded_string(pad), file_path.display());
             }
-            mp3_file.write_to_file(&file_path)?;
         }
         Command::ShowKnownFrameIds {} => {
             for (id, description) in KNOWN_ID3V2_IDS.iter() {
@@ -150,6 +959,871 @@ fn main() -> Result<(), ()> {
     Ok(())
 }
 
+fn run_picture_command(
+    command: PictureCommand,
+    write_options: &WriteOptions,
+) -> Result<(), CliError> {
+    match command {
+        PictureCommand::List { file: file_path } => {
+            let mp3_file = Mp3File::from_path(&file_path)?;
+            let mut pictures = mp3_file.frames_with_id("APIC").peekable();
+            if pictures.peek().is_none() {
+                println!("No pictures in file.");
+            }
+            for (index, frame) in pictures.enumerate() {
+                println!("{} {}", index + 1, frame.format_data());
+            }
+        }
+        PictureCommand::Extract {
+            file: file_path,
+            index,
+            output,
+        } => {
+            let mp3_file = Mp3File::from_path(&file_path)?;
+            match index {
+                Some(index) => {
+                    if !validate_frame_index(index) {
+                        return Err(CliError::InvalidArgument);
+                    }
+                    let picture = mp3_file.picture(index - 1)?;
+                    let path = output.unwrap_or_else(|| {
+                        PathBuf::from(format!("{}.{}", picture.picture_type, picture.extension()))
+                    });
+                    write_picture(picture, &path)?;
+                }
+                None => {
+                    let directory = output.unwrap_or_default();
+                    for index in 0..mp3_file.frames_with_id("APIC").count() as u32 {
+                        let picture = match mp3_file.picture(index) {
+                            Ok(picture) => picture,
+                            Err(error) => {
+                                log::warn(format!("Skipping picture {}: {}", index + 1, error));
+                                continue;
+                            }
+                        };
+                        let name = format!(
+                            "{}-{}.{}",
+                            index + 1,
+                            picture.picture_type,
+                            picture.extension()
+                        );
+                        write_picture(picture, &directory.join(name))?;
+                    }
+                }
+            }
+        }
+        PictureCommand::Embed {
+            file: file_path,
+            image,
+            picture_type,
+            description,
+            mime_type,
+        } => {
+            let mut mp3_file = Mp3File::from_path(&file_path)?;
+            let data = read_file(&image)?;
+            let mime_type = image_mime_type(&data, mime_type, &image)?;
+            let encoding = TextEncoding::default_for(&[&description], mp3_file.major_version());
+            mp3_file.add_picture(Picture {
+                encoding,
+                mime_type,
+                picture_type,
+                description,
+                data,
+            })?;
+            mp3_file.write_to_file_with_options(&file_path, write_options)?;
+        }
+        PictureCommand::Replace {
+            file: file_path,
+            image,
+            index,
+            picture_type,
+            description,
+            mime_type,
+        } => {
+            let unwraped_index = index.unwrap_or(1);
+            if !validate_frame_index(unwraped_index) {
+                return Err(CliError::InvalidArgument);
+            }
+            let mut mp3_file = Mp3File::from_path(&file_path)?;
+            let data = read_file(&image)?;
+            let mime_type = image_mime_type(&data, mime_type, &image)?;
+            let major_version = mp3_file.major_version();
+            let picture = mp3_file.picture_mut(unwraped_index - 1)?;
+            picture.mime_type = mime_type;
+            picture.data = data;
+            if let Some(picture_type) = picture_type {
+                picture.picture_type = picture_type;
+            }
+            if let Some(description) = description {
+                picture.encoding = TextEncoding::default_for(&[&description], major_version);
+                picture.description = description;
+            }
+            mp3_file.write_to_file_with_options(&file_path, write_options)?;
+        }
+        PictureCommand::Remove {
+            file: file_path,
+            index,
+        } => {
+            let unwraped_index = index.unwrap_or(1);
+            if !validate_frame_index(unwraped_index) {
+                return Err(CliError::InvalidArgument);
+            }
+            let mut mp3_file = Mp3File::from_path(&file_path)?;
+            mp3_file.remove_frame("APIC", unwraped_index - 1)?;
+            mp3_file.write_to_file_with_options(&file_path, write_options)?;
+        }
+        PictureCommand::ShowPictureTypes {} => {
+            for (picture_type, name) in PICTURE_TYPES.iter() {
+                println!("{} - {}", *picture_type as u8, name);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn run_synced_lyrics_command(
+    command: SyncedLyricsCommand,
+    write_options: &WriteOptions,
+) -> Result<(), CliError> {
+    match command {
+        SyncedLyricsCommand::Import {
+            file: file_path,
+            lrc,
+            lang,
+            desc,
+            encoding,
+        } => {
+            let mut mp3_file = Mp3File::from_path(&file_path)?;
+            let contents = String::from_utf8_lossy(&read_file(&lrc)?).to_string();
+            let mut lyrics =
+                SynchronisedLyrics::from_lrc(&contents, lang, desc, mp3_file.major_version());
+            if lyrics.lines.is_empty() {
+                log::warn(format!("No timed lines found in {}", lrc.display()));
+            }
+            if let Some(encoding) = encoding {
+                lyrics.encoding = encoding;
+            }
+            mp3_file.set_synchronised_lyrics(lyrics)?;
+            mp3_file.write_to_file_with_options(&file_path, write_options)?;
+        }
+        SyncedLyricsCommand::Export {
+            file: file_path,
+            lang,
+            desc,
+            output,
+        } => {
+            let mp3_file = Mp3File::from_path(&file_path)?;
+            let lyrics = mp3_file
+                .synchronised_lyrics(&lang, &desc)
+                .ok_or_else(|| not_found_by_language("SYLT".to_string(), &lang, &desc))?;
+            let lrc = lyrics.to_lrc()?;
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, lrc).map_err(|source| Error::Create { path, source })?
+                }
+                None => print!("{}", lrc),
+            }
+        }
+        SyncedLyricsCommand::Remove {
+            file: file_path,
+            lang,
+            desc,
+        } => {
+            let mut mp3_file = Mp3File::from_path(&file_path)?;
+            mp3_file.remove_synchronised_lyrics(&lang, &desc)?;
+            mp3_file.write_to_file_with_options(&file_path, write_options)?;
+        }
+    }
+    Ok(())
+}
+
+fn run_chapters_command(
+    command: ChaptersCommand,
+    write_options: &WriteOptions,
+) -> Result<(), CliError> {
+    match command {
+        ChaptersCommand::List { file: file_path } => {
+            let mp3_file = Mp3File::from_path(&file_path)?;
+            for frame in mp3_file.frames_with_id("CTOC") {
+                println!("{} {}", frame.format_id(), frame.format_data());
+            }
+            let mut chapters: Vec<&Frame> = mp3_file.frames_with_id("CHAP").collect();
+            if chapters.is_empty() {
+                println!("No chapters in file.");
+            }
+            chapters.sort_by_key(|frame| match frame.body() {
+                FrameBody::Chapter(chapter) => chapter.start_time,
+                _ => u32::MAX,
+            });
+            for (index, frame) in chapters.into_iter().enumerate() {
+                println!("{} {}", index + 1, frame.format_data());
+            }
+        }
+        ChaptersCommand::Import {
+            file: file_path,
+            chapters,
+        } => {
+            let mut mp3_file = Mp3File::from_path(&file_path)?;
+            let contents = String::from_utf8_lossy(&read_file(&chapters)?).to_string();
+            let audio_length = mp3_file
+                .frames_with_id("TLEN")
+                .find_map(|frame| frame.text()?.text().trim().parse::<u32>().ok());
+            let chapters = parse_chapter_list(&contents, audio_length, mp3_file.major_version())?;
+            if chapters.is_empty() {
+                log::warn("The chapter list has no chapters".to_string());
+            }
+            mp3_file.set_chapters(chapters);
+            mp3_file.write_to_file_with_options(&file_path, write_options)?;
+        }
+        ChaptersCommand::Export {
+            file: file_path,
+            output,
+        } => {
+            let mp3_file = Mp3File::from_path(&file_path)?;
+            let chapter_list = format_chapter_list(mp3_file.chapters());
+            match output {
+                Some(path) => std::fs::write(&path, chapter_list)
+                    .map_err(|source| Error::Create { path, source })?,
+                None => print!("{}", chapter_list),
+            }
+        }
+        ChaptersCommand::Remove { file: file_path } => {
+            let mut mp3_file = Mp3File::from_path(&file_path)?;
+            if mp3_file.remove_chapters() == 0 {
+                log::warn("No chapters in file".to_string());
+            }
+            mp3_file.write_to_file_with_options(&file_path, write_options)?;
+        }
+    }
+    Ok(())
+}
+
+fn run_rating_command(
+    command: RatingCommand,
+    write_options: &WriteOptions,
+) -> Result<(), CliError> {
+    match command {
+        RatingCommand::Show { file: file_path } => {
+            let mp3_file = Mp3File::from_path(&file_path)?;
+            let mut frames = mp3_file
+                .frames()
+                .iter()
+                .filter(|frame| frame.id() == "POPM" || frame.id() == "PCNT")
+                .peekable();
+            if frames.peek().is_none() {
+                println!("No ratings or play counts in file.");
+            }
+            for frame in frames {
+                println!("{} {}", frame.format_id(), frame.format_data());
+            }
+        }
+        RatingCommand::Set {
+            file: file_path,
+            rating,
+            email,
+            raw,
+        } => {
+            if !raw && rating > 5 {
+                log::error(
+                    "The rating must be from 0 to 5 stars. Use --raw to set a byte from 0 to 255"
+                        .to_string(),
+                );
+                return Err(CliError::InvalidArgument);
+            }
+            let rating = if raw { rating } else { stars_to_rating(rating) };
+            let mut mp3_file = Mp3File::from_path(&file_path)?;
+            let counter = mp3_file
+                .popularimeter(&email)
+                .and_then(|popularimeter| popularimeter.counter.clone());
+            mp3_file.set_popularimeter(Popularimeter {
+                email,
+                rating,
+                counter,
+            });
+            mp3_file.write_to_file_with_options(&file_path, write_options)?;
+        }
+        RatingCommand::Remove {
+            file: file_path,
+            email,
+        } => {
+            let mut mp3_file = Mp3File::from_path(&file_path)?;
+            mp3_file.remove_popularimeter(&email)?;
+            mp3_file.write_to_file_with_options(&file_path, write_options)?;
+        }
+        RatingCommand::Played {
+            file: file_path,
+            email,
+            count,
+        } => {
+            let mut mp3_file = Mp3File::from_path(&file_path)?;
+            mp3_file.increment_play_count(&email, count);
+            mp3_file.write_to_file_with_options(&file_path, write_options)?;
+        }
+    }
+    Ok(())
+}
+
+fn run_owned_data_command(
+    frame_id: &str,
+    command: OwnedDataCommand,
+    write_options: &WriteOptions,
+) -> Result<(), CliError> {
+    match command {
+        OwnedDataCommand::List {
+            file: file_path,
+            base64,
+        } => {
+            let mp3_file = Mp3File::from_path(&file_path)?;
+            let mut frames = mp3_file.frames_with_id(frame_id).peekable();
+            if frames.peek().is_none() {
+                println!("No {} frames in file.", frame_id);
+            }
+            for frame in frames {
+                println!("{}", frame.body().format_with(binary_format(base64)));
+            }
+        }
+        OwnedDataCommand::Set {
+            file: file_path,
+            owner,
+            data,
+            hex,
+            base64,
+        } => {
+            let data = if hex || base64 {
+                let (format, name) = if hex {
+                    (BinaryFormat::Hex, "hex")
+                } else {
+                    (BinaryFormat::Base64, "base64")
+                };
+                format.decode(&data).ok_or_else(|| {
+                    log::error(format!("The data is not valid {}", name));
+                    CliError::InvalidArgument
+                })?
+            } else {
+                data.into_bytes()
+            };
+            if frame_id == "UFID" {
+                if owner.is_empty() {
+                    log::error("The owner of a UFID frame can't be empty".to_string());
+                    return Err(CliError::InvalidArgument);
+                }
+                if data.len() > MAX_UNIQUE_FILE_IDENTIFIER_LENGTH {
+                    log::error(format!(
+                        "Unique file identifiers can't be longer than {} bytes",
+                        MAX_UNIQUE_FILE_IDENTIFIER_LENGTH
+                    ));
+                    return Err(CliError::InvalidArgument);
+                }
+            }
+            let mut mp3_file = Mp3File::from_path(&file_path)?;
+            mp3_file.set_owned_data(frame_id, OwnedData { owner, data });
+            mp3_file.write_to_file_with_options(&file_path, write_options)?;
+        }
+        OwnedDataCommand::Remove {
+            file: file_path,
+            owner,
+        } => {
+            let mut mp3_file = Mp3File::from_path(&file_path)?;
+            mp3_file.remove_owned_data(frame_id, &owner)?;
+            mp3_file.write_to_file_with_options(&file_path, write_options)?;
+        }
+    }
+    Ok(())
+}
+
+fn run_object_command(
+    command: ObjectCommand,
+    write_options: &WriteOptions,
+) -> Result<(), CliError> {
+    match command {
+        ObjectCommand::List { file: file_path } => {
+            let mp3_file = Mp3File::from_path(&file_path)?;
+            let mut objects = mp3_file.frames_with_id("GEOB").peekable();
+            if objects.peek().is_none() {
+                println!("No objects in file.");
+            }
+            for frame in objects {
+                println!("{}", frame.format_data());
+            }
+        }
+        ObjectCommand::Embed {
+            file: file_path,
+            object,
+            description,
+            mime_type,
+            encoding,
+        } => {
+            let mut mp3_file = Mp3File::from_path(&file_path)?;
+            let data = read_file(&object)?;
+            let mime_type = mime_type
+                .or_else(|| sniff_mime_type(&data).map(str::to_string))
+                .unwrap_or_else(|| "application/octet-stream".to_string());
+            let filename = object
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let mut object = EncapsulatedObject::new(
+                mime_type,
+                filename,
+                description,
+                data,
+                mp3_file.major_version(),
+            );
+            if let Some(encoding) = encoding {
+                object.encoding = encoding;
+            }
+            mp3_file.set_encapsulated_object(object)?;
+            mp3_file.write_to_file_with_options(&file_path, write_options)?;
+        }
+        ObjectCommand::Extract {
+            file: file_path,
+            description,
+            output,
+        } => {
+            let mp3_file = Mp3File::from_path(&file_path)?;
+            let object = mp3_file.encapsulated_object(&description).ok_or_else(|| {
+                Error::KeyedFrameNotFound {
+                    frame_id: "GEOB".to_string(),
+                    key: format!("description \"{}\"", description),
+                }
+            })?;
+            // Only the name is kept, so a stored path can't write outside the current directory
+            let path = output.unwrap_or_else(|| {
+                Path::new(&object.filename)
+                    .file_name()
+                    .map(PathBuf::from)
+                    .unwrap_or_else(|| PathBuf::from("object.bin"))
+            });
+            std::fs::write(&path, &object.data).map_err(|source| Error::Create {
+                path: path.clone(),
+                source,
+            })?;
+            println!(
+                "Extracted {} bytes to {}",
+                object.data.len(),
+                path.display()
+            );
+        }
+        ObjectCommand::Remove {
+            file: file_path,
+            description,
+        } => {
+            let mut mp3_file = Mp3File::from_path(&file_path)?;
+            mp3_file.remove_encapsulated_object(&description)?;
+            mp3_file.write_to_file_with_options(&file_path, write_options)?;
+        }
+    }
+    Ok(())
+}
+
+fn run_extended_header_command(
+    command: ExtendedHeaderCommand,
+    write_options: &WriteOptions,
+) -> Result<(), CliError> {
+    match command {
+        ExtendedHeaderCommand::Show { file: file_path } => {
+            let mp3_file = Mp3File::from_path(&file_path)?;
+            let Some(extended_header) = mp3_file.extended_header() else {
+                println!("No extended header");
+                return Ok(());
+            };
+            let yes_or_no = |flag: bool| if flag { "yes" } else { "no" };
+            println!("Update: {}", yes_or_no(extended_header.is_update));
+            println!("CRC-32: {}", yes_or_no(extended_header.has_crc));
+            match &extended_header.restrictions {
+                Some(restrictions) => {
+                    println!("Restrictions:");
+                    for restriction in restrictions.describe() {
+                        println!("  {}", restriction);
+                    }
+                }
+                None => println!("Restrictions: none"),
+            }
+        }
+        ExtendedHeaderCommand::Set {
+            file: file_path,
+            crc,
+            update,
+            tag_size_limit,
+            text_encoding_limit,
+            text_size_limit,
+            image_encoding_limit,
+            image_size_limit,
+        } => {
+            let has_restrictions = tag_size_limit.is_some()
+                || text_encoding_limit
+                || text_size_limit.is_some()
+                || image_encoding_limit
+                || image_size_limit.is_some();
+            let extended_header = ExtendedHeader {
+                is_update: update,
+                has_crc: crc,
+                restrictions: has_restrictions.then(|| TagRestrictions {
+                    tag_size: tag_size_limit.unwrap_or(0),
+                    text_encoding: text_encoding_limit,
+                    text_field_size: text_size_limit.unwrap_or(0),
+                    image_encoding: image_encoding_limit,
+                    image_size: image_size_limit.unwrap_or(0),
+                }),
+            };
+            let mut mp3_file = Mp3File::from_path(&file_path)?;
+            if mp3_file.major_version() == 3 && (update || has_restrictions) {
+                log::error(
+                    "The extended header of ID3v2.3 tags can only hold a CRC-32".to_string(),
+                );
+                return Err(CliError::InvalidArgument);
+            }
+            mp3_file.set_extended_header(Some(extended_header))?;
+            mp3_file.write_to_file_with_options(&file_path, write_options)?;
+        }
+        ExtendedHeaderCommand::Remove { file: file_path } => {
+            let mut mp3_file = Mp3File::from_path(&file_path)?;
+            if mp3_file.extended_header().is_none() {
+                log::warn("No extended header in file".to_string());
+            }
+            mp3_file.set_extended_header(None)?;
+            mp3_file.write_to_file_with_options(&file_path, write_options)?;
+        }
+    }
+    Ok(())
+}
+
+fn run_gain_command(command: GainCommand, write_options: &WriteOptions) -> Result<(), CliError> {
+    let scopes = [GainScope::Track, GainScope::Album];
+    match command {
+        GainCommand::Show { file: file_path } => {
+            let mp3_file = Mp3File::from_path(&file_path)?;
+            for scope in scopes {
+                match mp3_file.replay_gain(scope) {
+                    Some(ReplayGain { gain, peak }) => {
+                        print!("{}: {:.2} dB", scope.name(), gain);
+                        match peak {
+                            Some(peak) => println!(", peak {:.6}", peak),
+                            None => println!(),
+                        }
+                    }
+                    None => println!("{}: not set", scope.name()),
+                }
+            }
+            for frame in mp3_file.frames_with_id("RVA2") {
+                println!("{} {}", frame.format_id(), frame.format_data());
+            }
+        }
+        GainCommand::Set {
+            file: file_path,
+            track_gain,
+            track_peak,
+            album_gain,
+            album_peak,
+        } => {
+            let gains = [(track_gain, track_peak), (album_gain, album_peak)];
+            if gains.iter().all(|(gain, _)| gain.is_none()) {
+                log::error("Nothing to set. Use --track-gain or --album-gain.".to_string());
+                return Err(CliError::InvalidArgument);
+            }
+            if gains
+                .iter()
+                .flat_map(|(gain, peak)| [gain, peak])
+                .any(|value| value.is_some_and(|value| !value.is_finite()))
+            {
+                log::error("Gains and peaks must be finite numbers".to_string());
+                return Err(CliError::InvalidArgument);
+            }
+            let mut mp3_file = Mp3File::from_path(&file_path)?;
+            for (scope, (gain, peak)) in scopes.into_iter().zip(gains) {
+                if let Some(gain) = gain {
+                    mp3_file.set_replay_gain(scope, ReplayGain { gain, peak })?;
+                }
+            }
+            if mp3_file.major_version() < 4 {
+                log::warn(
+                    "ID3v2.3 tags have no RVA2 frame. Only the TXXX frames were written."
+                        .to_string(),
+                );
+            }
+            mp3_file.write_to_file_with_options(&file_path, write_options)?;
+        }
+        GainCommand::Remove { file: file_path } => {
+            let mut mp3_file = Mp3File::from_path(&file_path)?;
+            let mut removed = false;
+            for scope in scopes {
+                removed |= mp3_file.remove_replay_gain(scope);
+            }
+            if !removed {
+                log::warn("No gains in file".to_string());
+            }
+            mp3_file.write_to_file_with_options(&file_path, write_options)?;
+        }
+    }
+    Ok(())
+}
+
+fn binary_format(base64: bool) -> BinaryFormat {
+    if base64 {
+        BinaryFormat::Base64
+    } else {
+        BinaryFormat::Hex
+    }
+}
+
+fn read_file(path: &Path) -> Result<Vec<u8>, Error> {
+    std::fs::read(path).map_err(|source| Error::Open {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+fn write_picture(picture: &Picture, path: &Path) -> Result<(), Error> {
+    std::fs::write(path, &picture.data).map_err(|source| Error::Create {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    println!("Extracted {} to {}", picture.picture_type, path.display());
+    Ok(())
+}
+
+/// Uses the MIME type chosen by the user, or detects it from the image's magic bytes.
+fn image_mime_type(
+    data: &[u8],
+    mime_type: Option<String>,
+    path: &Path,
+) -> Result<String, CliError> {
+    if let Some(mime_type) = mime_type {
+        return Ok(mime_type);
+    }
+    match sniff_mime_type(data) {
+        Some(mime_type) => Ok(mime_type.to_string()),
+        None => {
+            log::error(format!(
+                "Could not detect the image format of {}. Use --mime-type to set it.",
+                path.display()
+            ));
+            Err(CliError::InvalidArgument)
+        }
+    }
+}
+
+/// Prints `error` in a way that makes sense to someone using the command line.
+fn report_error(error: &Error) {
+    match error {
+        Error::Open { path, source } => {
+            let path = path.to_string_lossy();
+            match source.kind() {
+                ErrorKind::NotFound => log::error(format!("File {} does not exist.", path)),
+                ErrorKind::PermissionDenied => {
+                    log::error(format!("Failed to open file {}. Permission denied", path))
+                }
+                _ => log::error(format!(
+                    "Failed to open file {}. Unknown error: {}",
+                    path,
+                    source.kind()
+                )),
+            }
+        }
+        Error::Create { path, source } if source.kind() == ErrorKind::PermissionDenied => {
+            log::error(format!(
+                "Failed to open file {} for writing. Permission denied",
+                path.to_string_lossy(),
+            ))
+        }
+        Error::FrameNotFound {
+            frame_id,
+            index,
+            found,
+        } => error_no_frame_with_id_found(frame_id, index + 1, *found),
+        _ => log::error(error.to_string()),
+    }
+}
+
+/// The process exit code for each kind of error. 2 is reserved for invalid arguments.
+fn exit_code(error: &Error) -> u8 {
+    match error {
+        Error::Open { source, .. } => match source.kind() {
+            ErrorKind::NotFound => 3,
+            ErrorKind::PermissionDenied => 4,
+            _ => 5,
+        },
+        Error::Create { .. } => 6,
+        Error::Io(_) => 7,
+        Error::NotAnId3Tag => 8,
+        Error::TruncatedHeader => 9,
+        Error::TruncatedExtendedHeader { .. } => 10,
+        Error::TruncatedFrame { .. } => 11,
+        Error::InvalidFrameId { .. } => 12,
+        Error::FrameNotFound { .. } | Error::KeyedFrameNotFound { .. } => 14,
+        Error::UnwritableFrameId { .. } => 15,
+        Error::UnsupportedVersion { .. } => 16,
+        Error::UnsupportedEncoding { .. } => 17,
+        Error::MalformedFrame { .. } => 18,
+        Error::UnsupportedTimestampFormat => 19,
+        Error::InvalidChapterList { .. } => 20,
+        Error::InvalidTimestamp { .. } => 21,
+        Error::Replace { .. } => 22,
+    }
+}
+
+fn parse_encoding(encoding: &str) -> Result<TextEncoding, String> {
+    match &encoding.to_lowercase()[..] {
+        "latin1" | "iso-8859-1" => Ok(TextEncoding::Latin1),
+        "utf16" | "utf-16" => Ok(TextEncoding::Utf16),
+        "utf16be" | "utf-16be" => Ok(TextEncoding::Utf16BE),
+        "utf8" | "utf-8" => Ok(TextEncoding::Utf8),
+        _ => Err("The encoding must be one of latin1, utf16, utf16be or utf8".to_string()),
+    }
+}
+
+/// Uses the encoding chosen by the user, or the best one for the tag's version.
+fn text_frame_from_args(
+    values: Vec<String>,
+    encoding: Option<TextEncoding>,
+    major_version: u8,
+) -> TextFrame {
+    match encoding {
+        Some(encoding) => TextFrame::new(encoding, values),
+        None => TextFrame::with_default_encoding(values, major_version),
+    }
+}
+
+/// Uses the encoding chosen by the user, or the best one for the tag's version.
+fn comment_from_args(
+    language: String,
+    description: String,
+    text: String,
+    encoding: Option<TextEncoding>,
+    major_version: u8,
+) -> Comment {
+    let mut comment = Comment::new(language, description, text, major_version);
+    if let Some(encoding) = encoding {
+        comment.encoding = encoding;
+    }
+    comment
+}
+
+fn not_found_by_language(frame_id: String, language: &str, description: &str) -> Error {
+    Error::KeyedFrameNotFound {
+        frame_id,
+        key: format!(
+            "language \"{}\" and description \"{}\"",
+            language, description
+        ),
+    }
+}
+
+/// Replaces the value of a time frame, or adds one. Warns when the tag's version can't hold
+/// the whole timestamp.
+fn set_timestamp(mp3_file: &mut Mp3File, frame_id: &str, value: String) -> Result<(), CliError> {
+    let timestamp = Timestamp::parse(&value).ok_or_else(|| Error::InvalidTimestamp {
+        frame_id: frame_id.to_string(),
+        value,
+    })?;
+    mp3_file.set_timestamp(frame_id, &timestamp);
+    match mp3_file.timestamp(frame_id) {
+        Some(stored) if stored != timestamp => log::warn(format!(
+            "ID3v2.{} tags can't hold \"{}\", so only \"{}\" was written",
+            mp3_file.major_version(),
+            timestamp,
+            stored
+        )),
+        _ => (),
+    }
+    Ok(())
+}
+
+/// Adds or replaces the `TXXX` or `WXXX` frame with the given description.
+fn set_user_defined_frame(
+    mp3_file: &mut Mp3File,
+    frame_id: &str,
+    description: String,
+    data: Vec<String>,
+    encoding: Option<TextEncoding>,
+) -> Result<(), CliError> {
+    let major_version = mp3_file.major_version();
+    if frame_id == "TXXX" {
+        let mut user_text = UserText::new(description, data, major_version);
+        if let Some(encoding) = encoding {
+            user_text.encoding = encoding;
+        }
+        mp3_file.set_user_text(user_text)?;
+    } else {
+        let mut user_url = UserUrl::new(description, single_value(data)?, major_version);
+        if let Some(encoding) = encoding {
+            user_url.encoding = encoding;
+        }
+        mp3_file.set_user_url(user_url)?;
+    }
+    Ok(())
+}
+
+/// Splits frame IDs like `TXXX:CATALOG_ID` into the ID and the description, which can also be
+/// given with `--desc`.
+fn split_frame_id(
+    frame_id: String,
+    desc: Option<String>,
+) -> Result<(String, Option<String>), CliError> {
+    match frame_id.split_once(':') {
+        Some(_) if desc.is_some() => {
+            log::error(
+                "The description can't be given both after the frame ID and with --desc"
+                    .to_string(),
+            );
+            Err(CliError::InvalidArgument)
+        }
+        Some((id, description)) => Ok((id.to_string(), Some(description.to_string()))),
+        None => Ok((frame_id, desc)),
+    }
+}
+
+/// `--lang` only makes sense for frames that have a language, and `--desc` for frames that
+/// have a description.
+fn validate_frame_key(frame_id: &str, lang: &Option<String>, desc: &Option<String>) -> bool {
+    if lang.is_some() && !is_comment_frame_id(frame_id) {
+        log::error("--lang can only be used with COMM and USLT frames".to_string());
+        return false;
+    }
+    if desc.is_some() && !is_comment_frame_id(frame_id) && !is_user_defined_frame_id(frame_id) {
+        log::error(
+            "A description can only be given to COMM, USLT, TXXX and WXXX frames".to_string(),
+        );
+        return false;
+    }
+    true
+}
+
+/// Only text frames can hold more than one value.
+fn single_value(mut values: Vec<String>) -> Result<String, CliError> {
+    if values.len() != 1 {
+        log::error("Only text frames can have more than one value".to_string());
+        return Err(CliError::InvalidArgument);
+    }
+    Ok(values.remove(0))
+}
+
+fn parse_picture_type(name: &str) -> Result<PictureType, String> {
+    PictureType::from_name(name)
+        .ok_or_else(|| "Unknown picture type. Use show-picture-types to see every type".to_string())
+}
+
+fn parse_language(language: &str) -> Result<String, String> {
+    if language.len() != 3 || !language.chars().all(|char| char.is_ascii_alphabetic()) {
+        return Err("The language must be a three letter ISO-639-2 code, such as eng".to_string());
+    }
+    Ok(language.to_lowercase())
+}
+
+fn parse_set_position(position: &str) -> Result<SetPosition, String> {
+    SetPosition::parse(position).ok_or_else(|| {
+        "The position must be a number from 1, optionally followed by /total".to_string()
+    })
+}
+
+fn parse_version(version: &str) -> Result<u8, String> {
+    match version {
+        "2.3" | "3" => Ok(3),
+        "2.4" | "4" => Ok(4),
+        _ => Err("The version must be either 2.3 or 2.4".to_string()),
+    }
+}
+
 fn ordinal_numeral(number: u32) -> &'static str {
     match number {
         1 => "st",
@@ -181,7 +1855,7 @@ fn error_no_frame_with_id_found(frame_id: &str, index: u32, largest_found_index:
     } else {
         log::error(format!(
             "There are only {} frames with id \"{}\". You tried to remove the {}{}",
-            largest_found_index.to_string(),
+            largest_found_index,
             frame_id,
             index,
             ordinal_numeral(index)
@@ -190,14 +1864,14 @@ fn error_no_frame_with_id_found(frame_id: &str, index: u32, largest_found_index:
 }
 
 fn validate_frame_id(frame_id: &str) -> bool {
-    if !is_string_valid_id3v2_id(&frame_id) {
+    if !is_string_valid_id3v2_id(frame_id) {
         log::error(format!(
             "Provided frame id \"{}\" is not valid. It must be a four-character word composed exclusively of numbers or uppercase letters",
             frame_id,
         ));
         return false;
     }
-    if !is_string_known_id3v2_id(&frame_id) {
+    if !is_string_known_id3v2_id(frame_id) {
         log::warn(format!(
             "Provided frame id \"{}\" is not a known id. The operation will still be executed.",
             frame_id,
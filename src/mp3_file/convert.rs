@@ -0,0 +1,846 @@
+//! Translation of frames between ID3v2.3 and ID3v2.4.
+
+use std::fmt;
+
+use super::frame_body::{FrameBody, TextFrame, Timestamp};
+use super::id3v2_frame::ID3v2Frame;
+use super::text_encoding::TextEncoding;
+
+/// Something that happened to one or more frames while converting a tag to another version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FrameConversion {
+    /// The frames were replaced by equivalent frames of the target version.
+    Translated { from: Vec<String>, to: Vec<String> },
+    /// The frames were replaced by frames of the target version, but some of their information
+    /// could not be kept.
+    Lossy {
+        from: Vec<String>,
+        to: Vec<String>,
+        reason: String,
+    },
+    /// The frame's text was re-encoded because the target version doesn't support its encoding.
+    Reencoded {
+        frame_id: String,
+        from: TextEncoding,
+        to: TextEncoding,
+    },
+    /// The frame has no equivalent in the target version and was removed.
+    Dropped { frame_id: String, reason: String },
+}
+
+impl FrameConversion {
+    /// Whether information was lost in this conversion.
+    pub fn is_lossy(&self) -> bool {
+        matches!(
+            self,
+            FrameConversion::Lossy { .. } | FrameConversion::Dropped { .. }
+        )
+    }
+}
+
+impl fmt::Display for FrameConversion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrameConversion::Translated { from, to } => {
+                write!(f, "{} -> {}", from.join(", "), to.join(", "))
+            }
+            FrameConversion::Lossy { from, to, reason } => write!(
+                f,
+                "{} -> {} (lossy: {})",
+                from.join(", "),
+                to.join(", "),
+                reason
+            ),
+            FrameConversion::Reencoded { frame_id, from, to } => {
+                write!(f, "{} re-encoded from {} to {}", frame_id, from, to)
+            }
+            FrameConversion::Dropped { frame_id, reason } => {
+                write!(f, "{} dropped: {}", frame_id, reason)
+            }
+        }
+    }
+}
+
+/// Frames that only exist in ID3v2.4, and have no ID3v2.3 equivalent.
+const V24_ONLY_IDS: [&str; 12] = [
+    "ASPI", "SEEK", "SIGN", "TDEN", "TDRL", "TDTG", "TMOO", "TPRO", "TSOA", "TSOP", "TSOT", "TSST",
+];
+
+/// Frames that were renamed without any change to their contents, as (ID3v2.3, ID3v2.4).
+const RENAMED_IDS: [(&str, &str); 2] = [("TORY", "TDOR"), ("IPLS", "TIPL")];
+
+/// Translates ID3v2.3 frames to ID3v2.4.
+pub fn upgrade_frames(frames: &mut Vec<ID3v2Frame>) -> Vec<FrameConversion> {
+    let mut report = Vec::new();
+    drop_opaque_frames(frames, &mut report);
+    convert_dates_to_v24(frames, &mut report);
+
+    for frame in frames.iter_mut() {
+        if let Some((v23_id, v24_id)) = RENAMED_IDS.iter().find(|(v23, _)| *v23 == frame.id) {
+            rename(frame, v24_id);
+            report.push(FrameConversion::Translated {
+                from: vec![v23_id.to_string()],
+                to: vec![v24_id.to_string()],
+            });
+        }
+    }
+
+    convert_binary_frames(frames, "RVAD", "RVA2", rvad_to_rva2, &mut report);
+    convert_binary_frames(frames, "EQUA", "EQU2", equa_to_equ2, &mut report);
+
+    frames.retain(|frame| {
+        let keep = frame.id != "TSIZ";
+        if !keep {
+            report.push(FrameConversion::Dropped {
+                frame_id: frame.id.clone(),
+                reason: "TSIZ is deprecated in ID3v2.4".to_string(),
+            });
+        }
+        keep
+    });
+
+    report
+}
+
+/// Translates ID3v2.4 frames to ID3v2.3.
+pub fn downgrade_frames(frames: &mut Vec<ID3v2Frame>) -> Vec<FrameConversion> {
+    let mut report = Vec::new();
+    drop_opaque_frames(frames, &mut report);
+    convert_dates_to_v23(frames, &mut report);
+
+    if let Some(tdor_index) = index_of(frames, "TDOR") {
+        if let Some(timestamp) = parse_timestamp(frames, tdor_index, &mut report) {
+            let tdor = &mut frames[tdor_index];
+            tdor.id = "TORY".to_string();
+            tdor.set_body(text_frame_body(
+                TextEncoding::Latin1,
+                &Timestamp::from_year(timestamp.year).to_string(),
+            ));
+            report.push(if timestamp.month.is_some() {
+                FrameConversion::Lossy {
+                    from: vec!["TDOR".to_string()],
+                    to: vec!["TORY".to_string()],
+                    reason: format!("only the year of \"{}\" was kept", timestamp),
+                }
+            } else {
+                FrameConversion::Translated {
+                    from: vec!["TDOR".to_string()],
+                    to: vec!["TORY".to_string()],
+                }
+            });
+        }
+    }
+
+    convert_involved_people_to_v23(frames, &mut report);
+    convert_binary_frames(frames, "RVA2", "RVAD", rva2_to_rvad, &mut report);
+    convert_binary_frames(frames, "EQU2", "EQUA", equ2_to_equa, &mut report);
+
+    frames.retain(|frame| {
+        let keep = !V24_ONLY_IDS.contains(&frame.id.as_str());
+        if !keep {
+            report.push(FrameConversion::Dropped {
+                frame_id: frame.id.clone(),
+                reason: "the frame does not exist in ID3v2.3".to_string(),
+            });
+        }
+        keep
+    });
+
+    for frame in frames.iter_mut() {
+        if let Some(conversion) = reencode_for_v23(frame) {
+            report.push(conversion);
+        }
+        // The titles and links of chapters are frames too
+        let parent_id = frame.id.clone();
+        for sub_frame in frame.body.sub_frames_mut().into_iter().flatten() {
+            if let Some(FrameConversion::Reencoded { frame_id, from, to }) =
+                reencode_for_v23(sub_frame)
+            {
+                report.push(FrameConversion::Reencoded {
+                    frame_id: format!("{}/{}", parent_id, frame_id),
+                    from,
+                    to,
+                });
+            }
+        }
+    }
+
+    report
+}
+
+/// Frames whose data is compressed, encrypted or unsynchronised can't be interpreted, and the
+/// extra data in their header is laid out differently in each version.
+fn drop_opaque_frames(frames: &mut Vec<ID3v2Frame>, report: &mut Vec<FrameConversion>) {
+    frames.retain(|frame| {
+        let keep = !frame.has_opaque_data();
+        if !keep {
+            report.push(FrameConversion::Dropped {
+                frame_id: frame.id.clone(),
+                reason: "the frame is compressed, encrypted or unsynchronised".to_string(),
+            });
+        }
+        keep
+    });
+}
+
+/// Builds the body of a text frame with a single value.
+fn text_frame_body(encoding: TextEncoding, text: &str) -> FrameBody {
+    FrameBody::Text(TextFrame::new(encoding, vec![text.to_string()]))
+}
+
+/// Changes the ID of a frame whose contents are laid out the same way under both IDs, but may
+/// be interpreted differently, like IPLS and TIPL.
+fn rename(frame: &mut ID3v2Frame, id: &str) {
+    frame.id = id.to_string();
+    // Only bodies that embed frames can fail to encode, and those are never renamed
+    if let Ok(data) = frame.body.to_bytes(4) {
+        frame.body = FrameBody::from_bytes(id, &data, 4);
+    }
+}
+
+/// The first value of a text frame.
+fn frame_text(frame: &ID3v2Frame) -> Option<String> {
+    frame.text().map(|text_frame| text_frame.text().to_string())
+}
+
+fn index_of(frames: &[ID3v2Frame], id: &str) -> Option<usize> {
+    frames.iter().position(|frame| frame.id == id)
+}
+
+/// Merges TYER (yyyy), TDAT (DDMM) and TIME (HHMM) into a single TDRC timestamp.
+fn convert_dates_to_v24(frames: &mut Vec<ID3v2Frame>, report: &mut Vec<FrameConversion>) {
+    let mut from = Vec::new();
+    if let Some(year_index) = index_of(frames, "TYER") {
+        let year = frame_text(&frames[year_index]).unwrap_or_default();
+        let date = index_of(frames, "TDAT").and_then(|index| frame_text(&frames[index]));
+        let time = index_of(frames, "TIME").and_then(|index| frame_text(&frames[index]));
+
+        match Timestamp::from_v23(&year, date.as_deref(), time.as_deref()) {
+            Some(timestamp) => {
+                from.push("TYER".to_string());
+                let mut lost = Vec::new();
+                match &date {
+                    Some(_) if timestamp.day.is_some() => {
+                        from.push("TDAT".to_string());
+                        match &time {
+                            Some(_) if timestamp.hour.is_some() => from.push("TIME".to_string()),
+                            Some(time) => {
+                                lost.push(format!("time \"{}\" is not a valid HHMM time", time))
+                            }
+                            None => (),
+                        }
+                    }
+                    Some(date) => lost.push(format!("date \"{}\" is not a valid DDMM date", date)),
+                    None => (),
+                }
+
+                frames[year_index].id = "TDRC".to_string();
+                frames[year_index].set_body(text_frame_body(
+                    TextEncoding::Latin1,
+                    &timestamp.to_string(),
+                ));
+
+                let to = vec!["TDRC".to_string()];
+                report.push(if lost.is_empty() {
+                    FrameConversion::Translated {
+                        from: from.clone(),
+                        to,
+                    }
+                } else {
+                    FrameConversion::Lossy {
+                        from: from.clone(),
+                        to,
+                        reason: lost.join(", "),
+                    }
+                });
+            }
+            None => {
+                frames.remove(year_index);
+                report.push(FrameConversion::Dropped {
+                    frame_id: "TYER".to_string(),
+                    reason: format!("year \"{}\" is not in the yyyy format", year),
+                });
+            }
+        }
+    }
+
+    frames.retain(|frame| {
+        let is_v23_date = ["TDAT", "TIME", "TRDA"].contains(&frame.id.as_str());
+        if is_v23_date && !from.contains(&frame.id) {
+            report.push(FrameConversion::Dropped {
+                frame_id: frame.id.clone(),
+                reason: "the date could not be merged into TDRC".to_string(),
+            });
+        }
+        !is_v23_date
+    });
+}
+
+/// Splits a TDRC timestamp into TYER, TDAT and TIME, keeping as much of it as those can hold.
+fn convert_dates_to_v23(frames: &mut Vec<ID3v2Frame>, report: &mut Vec<FrameConversion>) {
+    let Some(tdrc_index) = index_of(frames, "TDRC") else {
+        return;
+    };
+    let Some(timestamp) = parse_timestamp(frames, tdrc_index, report) else {
+        return;
+    };
+    let (year, date, time) = timestamp.to_v23();
+    let new_frames: Vec<ID3v2Frame> = [("TYER", Some(year)), ("TDAT", date), ("TIME", time)]
+        .into_iter()
+        .filter_map(|(id, text)| {
+            Some(ID3v2Frame::new(
+                id.to_string(),
+                text_frame_body(TextEncoding::Latin1, &text?),
+            ))
+        })
+        .collect();
+
+    let from = vec!["TDRC".to_string()];
+    let to = new_frames.iter().map(|frame| frame.id.clone()).collect();
+    let kept = timestamp.truncated_for_v23();
+    report.push(if kept != timestamp {
+        FrameConversion::Lossy {
+            from,
+            to,
+            reason: format!("\"{}\" was truncated to \"{}\"", timestamp, kept),
+        }
+    } else {
+        FrameConversion::Translated { from, to }
+    });
+    frames.splice(tdrc_index..tdrc_index + 1, new_frames);
+}
+
+/// The timestamp of the time frame at `index`. If it's not a valid timestamp, the frame is
+/// dropped, since there's no way to tell which part of it ID3v2.3 could hold.
+fn parse_timestamp(
+    frames: &mut Vec<ID3v2Frame>,
+    index: usize,
+    report: &mut Vec<FrameConversion>,
+) -> Option<Timestamp> {
+    let text = frame_text(&frames[index]).unwrap_or_default();
+    let timestamp = Timestamp::parse(&text);
+    if timestamp.is_none() {
+        let frame = frames.remove(index);
+        report.push(FrameConversion::Dropped {
+            frame_id: frame.id,
+            reason: format!("\"{}\" is not a valid timestamp", text),
+        });
+    }
+    timestamp
+}
+
+/// Merges TIPL and TMCL into IPLS. All three are lists of null separated pairs.
+fn convert_involved_people_to_v23(frames: &mut Vec<ID3v2Frame>, report: &mut Vec<FrameConversion>) {
+    let Some(first_index) = frames
+        .iter()
+        .position(|frame| frame.id == "TIPL" || frame.id == "TMCL")
+    else {
+        return;
+    };
+
+    let mut from = Vec::new();
+    let mut people = Vec::new();
+    for frame in frames.iter() {
+        if frame.id != "TIPL" && frame.id != "TMCL" {
+            continue;
+        }
+        from.push(frame.id.clone());
+        if let Some(text_frame) = frame.text() {
+            people.extend(text_frame.values.iter().cloned());
+        }
+    }
+
+    let encoding = TextEncoding::default_for(&people, 3);
+    let mut data = vec![encoding as u8];
+    for person in people.iter() {
+        data.extend(encoding.encode(person));
+        data.extend(encoding.terminator());
+    }
+    let ipls = ID3v2Frame::new("IPLS".to_string(), FrameBody::Unknown(data));
+
+    frames[first_index] = ipls;
+    let mut index = 0;
+    frames.retain(|frame| {
+        index += 1;
+        index - 1 == first_index || (frame.id != "TIPL" && frame.id != "TMCL")
+    });
+
+    let to = vec!["IPLS".to_string()];
+    report.push(if from.iter().any(|id| id == "TMCL") {
+        FrameConversion::Lossy {
+            from,
+            to,
+            reason: "musician credits were merged into the involved people list".to_string(),
+        }
+    } else {
+        FrameConversion::Translated { from, to }
+    });
+}
+
+/// The converted data of a binary frame, and the reason it's lossy, if it is.
+type BinaryConversion = Option<(Vec<u8>, Option<String>)>;
+
+/// Replaces the data of every `from_id` frame using `convert`, renaming it to `to_id`. Frames
+/// that can't be converted are dropped.
+fn convert_binary_frames(
+    frames: &mut Vec<ID3v2Frame>,
+    from_id: &str,
+    to_id: &str,
+    convert: fn(&[u8]) -> BinaryConversion,
+    report: &mut Vec<FrameConversion>,
+) {
+    frames.retain_mut(|frame| {
+        if frame.id != from_id {
+            return true;
+        }
+        let from = vec![from_id.to_string()];
+        let to = vec![to_id.to_string()];
+        match frame.body.to_bytes(4).ok().and_then(|data| convert(&data)) {
+            Some((data, lost)) => {
+                frame.id = to_id.to_string();
+                frame.set_body(FrameBody::from_bytes(to_id, &data, 4));
+                report.push(match lost {
+                    Some(reason) => FrameConversion::Lossy { from, to, reason },
+                    None => FrameConversion::Translated { from, to },
+                });
+                true
+            }
+            None => {
+                report.push(FrameConversion::Dropped {
+                    frame_id: from_id.to_string(),
+                    reason: "the frame's data is malformed".to_string(),
+                });
+                false
+            }
+        }
+    });
+}
+
+/// The ID3v2.3 spec does not define the unit of RVAD and EQUA adjustments. They are interpreted
+/// here as a fraction of full scale: the volume is multiplied by 1 ± value / 2^bits.
+const V23_ADJUSTMENT_NOTE: &str = "ID3v2.3 volume adjustments have no defined unit";
+
+fn v23_adjustment_to_db(value: u64, bits: u8, increment: bool) -> f64 {
+    let fraction = value as f64 / 2f64.powi(bits as i32);
+    let factor = if increment {
+        1.0 + fraction
+    } else {
+        1.0 - fraction
+    };
+    20.0 * factor.max(1e-5).log10()
+}
+
+/// Returns the value and whether it is an increment.
+fn db_to_v23_adjustment(db: f64, bits: u8) -> (u64, bool) {
+    let factor = 10f64.powf(db / 20.0);
+    let max = (1u64 << bits) - 1;
+    let value = ((factor - 1.0).abs() * 2f64.powi(bits as i32)).round() as u64;
+    (value.min(max), factor >= 1.0)
+}
+
+/// RVA2 adjustments are signed 16 bit integers in units of 1/512 dB.
+fn db_to_rva2_adjustment(db: f64) -> i16 {
+    (db * 512.0).round().clamp(i16::MIN as f64, i16::MAX as f64) as i16
+}
+
+fn read_unsigned(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .fold(0u64, |acc, byte| acc.wrapping_shl(8) | *byte as u64)
+}
+
+fn write_unsigned(value: u64, length: usize) -> Vec<u8> {
+    (0..length)
+        .rev()
+        .map(|index| (value >> (index * 8)) as u8)
+        .collect()
+}
+
+/// The RVAD channels in the order they appear in the frame, with their RVA2 channel type and
+/// their bit in the increment/decrement byte.
+const RVAD_CHANNELS: [(u8, u8); 6] = [(2, 0), (3, 1), (4, 2), (5, 3), (6, 4), (8, 5)];
+
+fn rvad_to_rva2(data: &[u8]) -> BinaryConversion {
+    let (&increments, rest) = data.split_first()?;
+    let (&bits, rest) = rest.split_first()?;
+    if bits == 0 || bits > 64 {
+        return None;
+    }
+    let length = (bits as usize).div_ceil(8);
+
+    // Right and left, then right back and left back, then center, then bass. Each group has
+    // the adjustments of its channels followed by their peaks.
+    let groups: [&[usize]; 4] = [&[0, 1], &[2, 3], &[4], &[5]];
+    let mut rva2 = vec![0];
+    let mut position = 0;
+    for group in groups {
+        let group_size = group.len() * length * 2;
+        if rest.len() < position + group_size {
+            break;
+        }
+        for (index_in_group, channel) in group.iter().enumerate() {
+            let (channel_type, increment_bit) = RVAD_CHANNELS[*channel];
+            let adjustment_start = position + index_in_group * length;
+            let peak_start = position + (group.len() + index_in_group) * length;
+            let adjustment = read_unsigned(&rest[adjustment_start..adjustment_start + length]);
+            let increment = (increments >> increment_bit) & 1 == 1;
+            let db = v23_adjustment_to_db(adjustment, bits, increment);
+
+            rva2.push(channel_type);
+            rva2.extend(db_to_rva2_adjustment(db).to_be_bytes());
+            rva2.push(bits);
+            rva2.extend(&rest[peak_start..peak_start + length]);
+        }
+        position += group_size;
+    }
+    if position == 0 {
+        return None;
+    }
+    Some((rva2, Some(V23_ADJUSTMENT_NOTE.to_string())))
+}
+
+fn rva2_to_rvad(data: &[u8]) -> BinaryConversion {
+    const BITS: u8 = 16;
+    let (_identification, mut rest) = TextEncoding::Latin1.split_terminated(data);
+    // Adjustment and peak of each RVAD channel, indexed like RVAD_CHANNELS
+    let mut channels: [Option<(f64, u64)>; 6] = [None; 6];
+    let mut master = None;
+    let mut lost = vec![V23_ADJUSTMENT_NOTE.to_string()];
+
+    while rest.len() >= 4 {
+        let channel_type = rest[0];
+        let db = i16::from_be_bytes([rest[1], rest[2]]) as f64 / 512.0;
+        let peak_bits = rest[3];
+        // Like RelativeVolume::from_bytes, peaks that don't fit in 64 bits are malformed
+        if peak_bits > 64 {
+            return None;
+        }
+        let peak_length = (peak_bits as usize).div_ceil(8);
+        let peak_bytes = rest.get(4..4 + peak_length)?;
+        let peak = read_unsigned(peak_bytes);
+        // Scale the peak to 16 bits
+        let peak = if peak_bits > BITS {
+            peak >> (peak_bits - BITS)
+        } else {
+            peak << (BITS - peak_bits)
+        };
+        rest = &rest[4 + peak_length..];
+
+        match RVAD_CHANNELS
+            .iter()
+            .position(|(rva2_type, _)| *rva2_type == channel_type)
+        {
+            Some(index) => channels[index] = Some((db, peak)),
+            None if channel_type == 1 => master = Some((db, peak)),
+            None => lost.push(format!(
+                "channel type {} has no ID3v2.3 equivalent",
+                channel_type
+            )),
+        }
+    }
+    // RVAD has no master volume, so it's applied to the front channels
+    if let Some(master) = master {
+        channels[0] = channels[0].or(Some(master));
+        channels[1] = channels[1].or(Some(master));
+    }
+    if channels.iter().all(Option::is_none) {
+        return None;
+    }
+
+    // Channels are positional, so every group before the last one used must be written
+    let last_channel = channels.iter().rposition(Option::is_some).unwrap_or(0);
+    let channel_count = match last_channel {
+        0 | 1 => 2,
+        2 | 3 => 4,
+        4 => 5,
+        _ => 6,
+    };
+    let mut increments = 0u8;
+    let mut adjustments = Vec::new();
+    let mut peaks = Vec::new();
+    let length = BITS as usize / 8;
+    let mut rvad = Vec::new();
+    for (index, channel) in channels.iter().take(channel_count).enumerate() {
+        let (db, peak) = channel.unwrap_or((0.0, 0));
+        let (value, increment) = db_to_v23_adjustment(db, BITS);
+        increments |= (increment as u8) << RVAD_CHANNELS[index].1;
+        adjustments.push(write_unsigned(value, length));
+        peaks.push(write_unsigned(peak, length));
+    }
+    rvad.push(increments);
+    rvad.push(BITS);
+    for (start, end) in [(0, 2), (2, 4), (4, 5), (5, 6)] {
+        if end > channel_count {
+            break;
+        }
+        rvad.extend(adjustments[start..end].concat());
+        rvad.extend(peaks[start..end].concat());
+    }
+    Some((rvad, Some(lost.join(", "))))
+}
+
+fn equa_to_equ2(data: &[u8]) -> BinaryConversion {
+    let (&bits, mut rest) = data.split_first()?;
+    if bits == 0 || bits > 64 {
+        return None;
+    }
+    let length = (bits as usize).div_ceil(8);
+    // Linear interpolation, empty identification
+    let mut equ2 = vec![1, 0];
+    while rest.len() >= 2 + length {
+        let increment = rest[0] >> 7 == 1;
+        let frequency = u16::from_be_bytes([rest[0] & 0x7F, rest[1]]);
+        let adjustment = read_unsigned(&rest[2..2 + length]);
+        let db = v23_adjustment_to_db(adjustment, bits, increment);
+        // EQU2 frequencies are in units of 1/2 Hz
+        equ2.extend((frequency * 2).to_be_bytes());
+        equ2.extend(db_to_rva2_adjustment(db).to_be_bytes());
+        rest = &rest[2 + length..];
+    }
+    Some((equ2, Some(V23_ADJUSTMENT_NOTE.to_string())))
+}
+
+fn equ2_to_equa(data: &[u8]) -> BinaryConversion {
+    const BITS: u8 = 16;
+    let (&interpolation, rest) = data.split_first()?;
+    let (_identification, mut rest) = TextEncoding::Latin1.split_terminated(rest);
+    let mut lost = vec![V23_ADJUSTMENT_NOTE.to_string()];
+    if interpolation != 0 {
+        lost.push("interpolation method is not supported by ID3v2.3".to_string());
+    }
+    let mut equa = vec![BITS];
+    while rest.len() >= 4 {
+        let frequency = (u16::from_be_bytes([rest[0], rest[1]]) / 2).min(0x7FFF);
+        let db = i16::from_be_bytes([rest[2], rest[3]]) as f64 / 512.0;
+        let (value, increment) = db_to_v23_adjustment(db, BITS);
+        equa.extend((frequency | (increment as u16) << 15).to_be_bytes());
+        equa.extend(write_unsigned(value, BITS as usize / 8));
+        rest = &rest[4..];
+    }
+    Some((equa, Some(lost.join(", "))))
+}
+
+/// A piece of a frame's data, used to find the encoded strings that need to be re-encoded.
+#[derive(Clone, Copy)]
+enum Field {
+    /// A fixed number of bytes
+    Bytes(usize),
+    /// Every remaining byte is a list of strings in the frame's encoding
+    EncodedStrings,
+}
+
+/// The layout of the data after the encoding byte for frames that have one.
+fn encoded_frame_layout(frame_id: &str) -> Option<&'static [Field]> {
+    use Field::*;
+    match frame_id {
+        "USER" => Some(&[Bytes(3), EncodedStrings]),
+        _ => None,
+    }
+}
+
+/// ID3v2.3 only supports ISO-8859-1 and UTF-16 with a byte order mark. Text in other encodings
+/// is re-encoded as UTF-16, and lists of values are joined with "/".
+fn reencode_for_v23(frame: &mut ID3v2Frame) -> Option<FrameConversion> {
+    let to = TextEncoding::Utf16;
+    if let Some(from) = frame.body.text_encoding() {
+        if from.is_supported_by(3) {
+            return None;
+        }
+        frame.body.set_text_encoding(to);
+        return Some(FrameConversion::Reencoded {
+            frame_id: frame.id.clone(),
+            from,
+            to,
+        });
+    }
+
+    let FrameBody::Unknown(frame_data) = &frame.body else {
+        return None;
+    };
+    let layout = encoded_frame_layout(&frame.id)?;
+    let (&encoding_byte, mut rest) = frame_data.split_first()?;
+    let from = TextEncoding::from_byte(encoding_byte)?;
+    if from.is_supported_by(3) {
+        return None;
+    }
+
+    let mut data = vec![to as u8];
+    for field in layout {
+        match field {
+            Field::Bytes(length) => {
+                let length = (*length).min(rest.len());
+                data.extend(&rest[..length]);
+                rest = &rest[length..];
+            }
+            Field::EncodedStrings => {
+                let mut values = Vec::new();
+                while !rest.is_empty() {
+                    let (value, remaining) = from.split_terminated(rest);
+                    values.push(from.decode(value));
+                    rest = remaining;
+                }
+                data.extend(to.encode(&values.join("/")));
+            }
+        }
+    }
+    frame.set_body(FrameBody::Unknown(data));
+
+    Some(FrameConversion::Reencoded {
+        frame_id: frame.id.clone(),
+        from,
+        to,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text_frame(frame_id: &str, text: &str) -> ID3v2Frame {
+        ID3v2Frame::new(
+            frame_id.to_string(),
+            text_frame_body(TextEncoding::Latin1, text),
+        )
+    }
+
+    fn texts(frames: &[ID3v2Frame]) -> Vec<(&str, String)> {
+        frames
+            .iter()
+            .map(|frame| (frame.id(), frame_text(frame).unwrap_or_default()))
+            .collect()
+    }
+
+    #[test]
+    fn id3v23_dates_are_merged_into_tdrc() {
+        let mut frames = vec![
+            text_frame("TIME", "1830"),
+            text_frame("TYER", "2004"),
+            text_frame("TDAT", "1207"),
+        ];
+        let report = upgrade_frames(&mut frames);
+        assert_eq!(texts(&frames), [("TDRC", "2004-07-12T18:30".to_string())]);
+        assert_eq!(
+            report,
+            [FrameConversion::Translated {
+                from: vec!["TYER".to_string(), "TDAT".to_string(), "TIME".to_string()],
+                to: vec!["TDRC".to_string()],
+            }]
+        );
+    }
+
+    #[test]
+    fn id3v23_dates_that_can_not_be_merged_are_dropped() {
+        let mut frames = vec![text_frame("TYER", "2004"), text_frame("TDAT", "3102")];
+        let report = upgrade_frames(&mut frames);
+        assert_eq!(texts(&frames), [("TDRC", "2004".to_string())]);
+        assert!(matches!(&report[0], FrameConversion::Lossy { reason, .. }
+            if reason == "date \"3102\" is not a valid DDMM date"));
+        assert!(
+            matches!(&report[1], FrameConversion::Dropped { frame_id, .. } if frame_id == "TDAT")
+        );
+
+        // Without a year, there's nothing to merge into
+        let mut frames = vec![text_frame("TDAT", "1207"), text_frame("TIME", "1830")];
+        let report = upgrade_frames(&mut frames);
+        assert!(frames.is_empty());
+        assert_eq!(report.len(), 2);
+        let mut frames = vec![text_frame("TYER", "04")];
+        let report = upgrade_frames(&mut frames);
+        assert!(frames.is_empty());
+        assert!(
+            matches!(&report[0], FrameConversion::Dropped { frame_id, .. } if frame_id == "TYER")
+        );
+    }
+
+    #[test]
+    fn tdrc_is_split_into_id3v23_dates() {
+        let mut frames = vec![
+            text_frame("TIT2", "Title"),
+            text_frame("TDRC", "2004-07-12T18:30:05"),
+        ];
+        let report = downgrade_frames(&mut frames);
+        assert_eq!(
+            texts(&frames),
+            [
+                ("TIT2", "Title".to_string()),
+                ("TYER", "2004".to_string()),
+                ("TDAT", "1207".to_string()),
+                ("TIME", "1830".to_string())
+            ]
+        );
+        assert!(report[0].is_lossy());
+
+        let mut frames = vec![text_frame("TDRC", "2004")];
+        let report = downgrade_frames(&mut frames);
+        assert_eq!(texts(&frames), [("TYER", "2004".to_string())]);
+        assert!(!report[0].is_lossy());
+
+        let mut frames = vec![
+            text_frame("TDRC", "July 2004"),
+            text_frame("TDOR", "2001-05"),
+        ];
+        let report = downgrade_frames(&mut frames);
+        assert_eq!(texts(&frames), [("TORY", "2001".to_string())]);
+        assert!(
+            matches!(&report[0], FrameConversion::Dropped { frame_id, .. } if frame_id == "TDRC")
+        );
+        assert!(matches!(&report[1], FrameConversion::Lossy { .. }));
+    }
+
+    #[test]
+    fn tsiz_is_dropped_from_id3v24_tags() {
+        let mut frames = vec![text_frame("TSIZ", "123456"), text_frame("TORY", "2001")];
+        let report = upgrade_frames(&mut frames);
+        assert_eq!(texts(&frames), [("TDOR", "2001".to_string())]);
+        assert_eq!(
+            report,
+            [
+                FrameConversion::Translated {
+                    from: vec!["TORY".to_string()],
+                    to: vec!["TDOR".to_string()],
+                },
+                FrameConversion::Dropped {
+                    frame_id: "TSIZ".to_string(),
+                    reason: "TSIZ is deprecated in ID3v2.4".to_string(),
+                }
+            ]
+        );
+        // ID3v2.4 only frames are dropped the other way around
+        let mut frames = vec![text_frame("TSIZ", "123456"), text_frame("TMOO", "Calm")];
+        let report = downgrade_frames(&mut frames);
+        assert_eq!(texts(&frames), [("TSIZ", "123456".to_string())]);
+        assert!(
+            matches!(&report[0], FrameConversion::Dropped { frame_id, .. } if frame_id == "TMOO")
+        );
+    }
+
+    fn rva2(channel_data: &[u8]) -> ID3v2Frame {
+        let data = [b"track\0".as_slice(), channel_data].concat();
+        ID3v2Frame::new("RVA2".to_string(), FrameBody::Unknown(data))
+    }
+
+    #[test]
+    fn rva2_peaks_are_scaled_to_16_bits() {
+        // Master volume at +1 dB, with an 8 bit peak of 0x80
+        let mut frames = vec![rva2(&[1, 0x02, 0x00, 8, 0x80])];
+        downgrade_frames(&mut frames);
+        assert_eq!(frames[0].id(), "RVAD");
+        let data = frames[0].body().to_bytes(3).unwrap();
+        // Both front channels get the master volume, and a peak of 0x8000
+        assert_eq!(data[..2], [0b11, 16]);
+        assert_eq!(data[6..], [0x80, 0x00, 0x80, 0x00]);
+    }
+
+    #[test]
+    fn rva2_peaks_with_more_than_64_bits_are_malformed() {
+        let mut channel = vec![1, 0x02, 0x00, 200];
+        channel.extend([0xFF; 25]);
+        let mut frames = vec![rva2(&channel)];
+        let report = downgrade_frames(&mut frames);
+        assert!(frames.is_empty());
+        assert!(report.iter().any(|conversion| matches!(
+            conversion,
+            FrameConversion::Dropped { frame_id, .. } if frame_id == "RVA2"
+        )));
+    }
+}
@@ -0,0 +1,291 @@
+use crate::error::{Error, Result};
+use crate::utils::{check_bit, crc32, read_syncsafe_integer, write_syncsafe_integer};
+use std::io::{Read, Seek, Write};
+
+/// Limits an ID3v2.4 tag says it stays within. Each size limit is a level from 0, which means
+/// the loosest limit or none, to 3. Nothing checks that the tag actually follows them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TagRestrictions {
+    /// 0: 128 frames and 1 MB, 1: 64 frames and 128 KB, 2: 32 frames and 40 KB, 3: 32 frames
+    /// and 4 KB.
+    pub tag_size: u8,
+    /// Text is only encoded as ISO-8859-1 or UTF-8.
+    pub text_encoding: bool,
+    /// 0: no limit, 1: 1024 characters, 2: 128 characters, 3: 30 characters per text field.
+    pub text_field_size: u8,
+    /// Images are only PNG or JPEG.
+    pub image_encoding: bool,
+    /// 0: no limit, 1: 256x256 pixels or less, 2: 64x64 pixels or less, 3: exactly 64x64
+    /// pixels, unless required otherwise.
+    pub image_size: u8,
+}
+
+impl TagRestrictions {
+    /// Decodes the `%ppqrrstt` restrictions byte.
+    pub fn from_byte(byte: u8) -> TagRestrictions {
+        TagRestrictions {
+            tag_size: byte >> 6,
+            text_encoding: check_bit(byte, 5),
+            text_field_size: (byte >> 3) & 0b11,
+            image_encoding: check_bit(byte, 2),
+            image_size: byte & 0b11,
+        }
+    }
+
+    /// Encodes the restrictions as the `%ppqrrstt` byte. Levels above 3 are capped.
+    pub fn to_byte(&self) -> u8 {
+        (self.tag_size.min(3) << 6)
+            | ((self.text_encoding as u8) << 5)
+            | (self.text_field_size.min(3) << 3)
+            | ((self.image_encoding as u8) << 2)
+            | self.image_size.min(3)
+    }
+
+    /// A line for each restriction that applies, such as `text fields of 30 characters or
+    /// less`.
+    pub fn describe(&self) -> Vec<String> {
+        let tag_sizes = [
+            "128 frames and 1 MB",
+            "64 frames and 128 KB",
+            "32 frames and 40 KB",
+            "32 frames and 4 KB",
+        ];
+        let mut lines = vec![format!(
+            "at most {}",
+            tag_sizes[self.tag_size.min(3) as usize]
+        )];
+        if self.text_encoding {
+            lines.push("text encoded as ISO-8859-1 or UTF-8 only".to_string());
+        }
+        match self.text_field_size {
+            0 => (),
+            1 => lines.push("text fields of 1024 characters or less".to_string()),
+            2 => lines.push("text fields of 128 characters or less".to_string()),
+            _ => lines.push("text fields of 30 characters or less".to_string()),
+        }
+        if self.image_encoding {
+            lines.push("PNG or JPEG images only".to_string());
+        }
+        match self.image_size {
+            0 => (),
+            1 => lines.push("images of 256x256 pixels or less".to_string()),
+            2 => lines.push("images of 64x64 pixels or less".to_string()),
+            _ => lines.push("images of exactly 64x64 pixels".to_string()),
+        }
+        lines
+    }
+}
+
+/// The extended header of ID3v2.3 and ID3v2.4 tags, which comes right after the tag header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ExtendedHeader {
+    /// The tag updates an earlier one in the same file or stream, so its frames replace the
+    /// ones of the earlier tag. ID3v2.4 only.
+    pub is_update: bool,
+    /// Whether the extended header holds a CRC-32 of the tag. The CRC itself is calculated
+    /// every time the tag is written.
+    pub has_crc: bool,
+    /// ID3v2.4 only.
+    pub restrictions: Option<TagRestrictions>,
+}
+
+impl ExtendedHeader {
+    /// Reads the extended header of a tag of the given version, which starts right after the
+    /// tag header. `tag_size` is the size of the tag as declared by that header, which the
+    /// extended header can't go past. Returns the header and the CRC it holds, if any.
+    pub(crate) fn from_reader<R: Read + Seek>(
+        reader: &mut R,
+        major_version: u8,
+        tag_size: u32,
+    ) -> Result<(ExtendedHeader, Option<u32>)> {
+        let offset = reader.stream_position()?;
+        let reader = &mut reader.take(tag_size as u64);
+        match major_version {
+            3 => Self::from_reader_v3(reader, offset),
+            _ => Self::from_reader_v4(reader, offset),
+        }
+    }
+
+    /// The ID3v2.3 extended header is a plain 32 bit size, which does not include itself and is
+    /// either 6 or 10, followed by 2 bytes of flags, 4 bytes of padding size and the optional
+    /// CRC-32.
+    fn from_reader_v3<R: Read>(
+        reader: &mut R,
+        offset: u64,
+    ) -> Result<(ExtendedHeader, Option<u32>)> {
+        let mut buffer = [0u8; 10];
+        read_exact(reader, &mut buffer, offset)?;
+        let size = u32::from_be_bytes(buffer[0..4].try_into().unwrap());
+        let has_crc = check_bit(buffer[4], 7);
+
+        let mut crc = None;
+        if has_crc {
+            let mut crc_bytes = [0u8; 4];
+            read_exact(reader, &mut crc_bytes, offset)?;
+            crc = Some(u32::from_be_bytes(crc_bytes));
+        }
+        let expected_size = if has_crc { 10 } else { 6 };
+        if size != expected_size {
+            log::warn!(
+                "Extended header size is {}, but its flags say it should be {}",
+                size,
+                expected_size
+            );
+        }
+
+        let extended_header = ExtendedHeader {
+            has_crc,
+            ..ExtendedHeader::default()
+        };
+        Ok((extended_header, crc))
+    }
+
+    /// The ID3v2.4 extended header is a syncsafe size, which includes itself, followed by the
+    /// number of flag bytes, which is always 1, the flags byte and then the data of each flag
+    /// that is set, in order, each preceded by its length.
+    fn from_reader_v4<R: Read>(
+        reader: &mut R,
+        offset: u64,
+    ) -> Result<(ExtendedHeader, Option<u32>)> {
+        let mut size_bytes = [0u8; 4];
+        read_exact(reader, &mut size_bytes, offset)?;
+        let size = read_syncsafe_integer(&size_bytes);
+        if size < 6 {
+            log::warn!(
+                "Extended header size is {}, but it can't be less than 6",
+                size
+            );
+        }
+        // The size can't be trusted, so nothing is allocated before the bytes are read
+        let rest_size = size.max(6) as u64 - 4;
+        let mut buffer = Vec::new();
+        reader.take(rest_size).read_to_end(&mut buffer)?;
+        if (buffer.len() as u64) < rest_size {
+            return Err(Error::TruncatedExtendedHeader { offset });
+        }
+        if buffer[0] != 1 {
+            log::warn!(
+                "Extended header has {} flag bytes, but ID3v2.4 defines 1",
+                buffer[0]
+            );
+        }
+        let flags = buffer[1];
+        let mut data = &buffer[2..];
+
+        // Each flag's data is preceded by its length
+        let mut flag_data = |name: &str, expected_length: usize| -> Option<Vec<u8>> {
+            let Some((&length, rest)) = data.split_first() else {
+                log::warn!("Extended header ends before the {} data", name);
+                return None;
+            };
+            let length = length as usize;
+            if length != expected_length || rest.len() < length {
+                log::warn!(
+                    "Extended header {} data is {} bytes long, but it should be {}",
+                    name,
+                    length,
+                    expected_length
+                );
+            }
+            let (flag_data, rest) = rest.split_at(length.min(rest.len()));
+            data = rest;
+            (flag_data.len() == expected_length).then(|| flag_data.to_vec())
+        };
+
+        let mut extended_header = ExtendedHeader::default();
+        if check_bit(flags, 6) {
+            extended_header.is_update = true;
+            flag_data("update", 0);
+        }
+        let mut crc = None;
+        if check_bit(flags, 5) {
+            extended_header.has_crc = true;
+            // A 35 bit syncsafe integer holding a 32 bit CRC
+            crc = flag_data("CRC", 5).map(|bytes| {
+                bytes
+                    .iter()
+                    .fold(0u64, |crc, &byte| crc << 7 | (byte & 0x7F) as u64) as u32
+            });
+        }
+        if check_bit(flags, 4) {
+            extended_header.restrictions =
+                flag_data("restrictions", 1).map(|bytes| TagRestrictions::from_byte(bytes[0]));
+        }
+        Ok((extended_header, crc))
+    }
+
+    /// The size of the extended header on a tag of the given version, including its own size
+    /// bytes.
+    pub(crate) fn size(&self, major_version: u8) -> u32 {
+        match major_version {
+            3 if self.has_crc => 14,
+            3 => 10,
+            _ => {
+                6 + self.is_update as u32
+                    + if self.has_crc { 6 } else { 0 }
+                    + if self.restrictions.is_some() { 2 } else { 0 }
+            }
+        }
+    }
+
+    /// Writes the extended header for a tag of the given version. `frames` is the already
+    /// serialized frames of the tag and `padding` how many bytes of padding follow them, which
+    /// are needed to calculate the CRC.
+    pub(crate) fn write_to<W: Write>(
+        &self,
+        writer: &mut W,
+        major_version: u8,
+        frames: &[u8],
+        padding: u32,
+    ) -> Result<()> {
+        if major_version == 3 {
+            writer.write_all(&(self.size(3) - 4).to_be_bytes())?;
+            writer.write_all(&[(self.has_crc as u8) << 7, 0])?;
+            writer.write_all(&padding.to_be_bytes())?;
+            if self.has_crc {
+                // ID3v2.3 CRCs only cover the frames
+                writer.write_all(&crc32(frames).to_be_bytes())?;
+            }
+            return Ok(());
+        }
+
+        writer.write_all(&write_syncsafe_integer(self.size(major_version)))?;
+        let flags = (self.is_update as u8) << 6
+            | (self.has_crc as u8) << 5
+            | (self.restrictions.is_some() as u8) << 4;
+        writer.write_all(&[1, flags])?;
+        if self.is_update {
+            writer.write_all(&[0])?;
+        }
+        if self.has_crc {
+            // ID3v2.4 CRCs cover the frames and the padding
+            let mut data = frames.to_vec();
+            data.resize(frames.len() + padding as usize, 0);
+            let crc = crc32(&data) as u64;
+            writer.write_all(&[5])?;
+            writer.write_all(&[4, 3, 2, 1, 0].map(|group| (crc >> (7 * group)) as u8 & 0x7F))?;
+        }
+        if let Some(restrictions) = &self.restrictions {
+            writer.write_all(&[1, restrictions.to_byte()])?;
+        }
+        Ok(())
+    }
+
+    /// Keeps only what a tag of the given version can hold.
+    pub(crate) fn for_version(&self, major_version: u8) -> ExtendedHeader {
+        match major_version {
+            4 => *self,
+            _ => ExtendedHeader {
+                has_crc: self.has_crc,
+                ..ExtendedHeader::default()
+            },
+        }
+    }
+}
+
+/// Fills `buffer` from the extended header that starts at `offset`.
+fn read_exact<R: Read>(reader: &mut R, buffer: &mut [u8], offset: u64) -> Result<()> {
+    reader
+        .read_exact(buffer)
+        .map_err(|err| Error::from_read(err, || Error::TruncatedExtendedHeader { offset }))
+}
@@ -0,0 +1,411 @@
+use super::{FrameBody, TextFrame, UserUrl};
+use crate::error::{Error, Result};
+use crate::mp3_file::id3v2_frame::ID3v2Frame;
+use crate::mp3_file::text_encoding::TextEncoding;
+
+/// Byte offsets set to this value are not used, and times should be used instead.
+const UNUSED_OFFSET: u32 = 0xFFFF_FFFF;
+
+/// The body of chapter frames (`CHAP`): a section of the audio, described by the frames
+/// embedded in it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chapter {
+    /// Identifies the chapter in tables of contents. Unique among the chapters and tables of
+    /// contents of a tag.
+    pub element_id: String,
+    /// In milliseconds from the start of the audio.
+    pub start_time: u32,
+    /// In milliseconds from the start of the audio.
+    pub end_time: u32,
+    /// The position of the chapter's first byte in the audio, if set.
+    pub start_offset: Option<u32>,
+    /// The position of the byte right after the chapter in the audio, if set.
+    pub end_offset: Option<u32>,
+    /// Frames describing the chapter, usually a `TIT2` title, a `WXXX` link or an `APIC`
+    /// image.
+    pub sub_frames: Vec<ID3v2Frame>,
+}
+
+impl Chapter {
+    /// A chapter without offsets or sub-frames.
+    pub fn new(element_id: String, start_time: u32, end_time: u32) -> Chapter {
+        Chapter {
+            element_id,
+            start_time,
+            end_time,
+            start_offset: None,
+            end_offset: None,
+            sub_frames: Vec::new(),
+        }
+    }
+
+    /// Decodes the data of a `CHAP` frame of a tag with the given version, which tells how the
+    /// sub-frames are laid out. Returns `None` if the data ends too soon.
+    pub fn from_bytes(data: &[u8], major_version: u8) -> Option<Chapter> {
+        let (element_id, rest) = TextEncoding::Latin1.split_terminated(data);
+        let read_u32 = |index: usize| -> Option<u32> {
+            Some(u32::from_be_bytes(
+                rest.get(index * 4..index * 4 + 4)?.try_into().ok()?,
+            ))
+        };
+        let offset = |value: u32| (value != UNUSED_OFFSET).then_some(value);
+        Some(Chapter {
+            element_id: TextEncoding::Latin1.decode(element_id),
+            start_time: read_u32(0)?,
+            end_time: read_u32(1)?,
+            start_offset: offset(read_u32(2)?),
+            end_offset: offset(read_u32(3)?),
+            sub_frames: ID3v2Frame::read_embedded(&rest[16..], major_version)?,
+        })
+    }
+
+    /// Encodes the frame, and its sub-frames, for a tag of the given version. Fails if a
+    /// sub-frame can't be written.
+    pub fn to_bytes(&self, major_version: u8) -> Result<Vec<u8>> {
+        let mut data = TextEncoding::Latin1.encode(&self.element_id);
+        data.push(0);
+        data.extend(self.start_time.to_be_bytes());
+        data.extend(self.end_time.to_be_bytes());
+        data.extend(self.start_offset.unwrap_or(UNUSED_OFFSET).to_be_bytes());
+        data.extend(self.end_offset.unwrap_or(UNUSED_OFFSET).to_be_bytes());
+        data.extend(ID3v2Frame::write_embedded(&self.sub_frames, major_version)?);
+        Ok(data)
+    }
+
+    /// The text of the chapter's `TIT2` sub-frame.
+    pub fn title(&self) -> Option<&str> {
+        sub_frame_title(&self.sub_frames)
+    }
+
+    /// Replaces the chapter's `TIT2` sub-frame, or adds one.
+    pub fn set_title(&mut self, title: String, major_version: u8) {
+        let body = FrameBody::Text(TextFrame::with_default_encoding(vec![title], major_version));
+        set_sub_frame(&mut self.sub_frames, "TIT2", body);
+    }
+
+    /// The URL of the chapter's first `WXXX` sub-frame.
+    pub fn url(&self) -> Option<&str> {
+        self.sub_frames.iter().find_map(|frame| match frame.body() {
+            FrameBody::UserUrl(user_url) => Some(user_url.url.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Replaces the chapter's `WXXX` sub-frame, or adds one with an empty description.
+    pub fn set_url(&mut self, url: String, major_version: u8) {
+        let body = FrameBody::UserUrl(UserUrl::new(String::new(), url, major_version));
+        set_sub_frame(&mut self.sub_frames, "WXXX", body);
+    }
+}
+
+/// The body of table of contents frames (`CTOC`): an ordered or unordered list of chapters or
+/// other tables of contents, described by the frames embedded in it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableOfContents {
+    /// Identifies the table of contents. Unique among the chapters and tables of contents of
+    /// a tag.
+    pub element_id: String,
+    /// Whether this is the root of the tree of tables of contents. Only one table of contents
+    /// in a tag should be top level.
+    pub top_level: bool,
+    /// Whether the entries should be played in the order they are listed.
+    pub ordered: bool,
+    /// The element IDs of the chapters and tables of contents in this one.
+    pub child_element_ids: Vec<String>,
+    /// Frames describing the table of contents, usually a `TIT2` title.
+    pub sub_frames: Vec<ID3v2Frame>,
+}
+
+impl TableOfContents {
+    /// Decodes the data of a `CTOC` frame of a tag with the given version, which tells how the
+    /// sub-frames are laid out. Returns `None` if the data ends too soon.
+    pub fn from_bytes(data: &[u8], major_version: u8) -> Option<TableOfContents> {
+        let (element_id, rest) = TextEncoding::Latin1.split_terminated(data);
+        let (&flags, rest) = rest.split_first()?;
+        let (&entry_count, mut rest) = rest.split_first()?;
+        let mut child_element_ids = Vec::new();
+        for _ in 0..entry_count {
+            if rest.is_empty() {
+                return None;
+            }
+            let (child_element_id, remaining) = TextEncoding::Latin1.split_terminated(rest);
+            child_element_ids.push(TextEncoding::Latin1.decode(child_element_id));
+            rest = remaining;
+        }
+        Some(TableOfContents {
+            element_id: TextEncoding::Latin1.decode(element_id),
+            top_level: flags & 0b10 != 0,
+            ordered: flags & 0b01 != 0,
+            child_element_ids,
+            sub_frames: ID3v2Frame::read_embedded(rest, major_version)?,
+        })
+    }
+
+    /// Encodes the frame, and its sub-frames, for a tag of the given version. Only the first
+    /// 255 entries can be written. Fails if a sub-frame can't be written.
+    pub fn to_bytes(&self, major_version: u8) -> Result<Vec<u8>> {
+        let mut data = TextEncoding::Latin1.encode(&self.element_id);
+        data.push(0);
+        data.push((self.top_level as u8) << 1 | self.ordered as u8);
+        let entry_count = self.child_element_ids.len().min(u8::MAX as usize);
+        data.push(entry_count as u8);
+        for child_element_id in self.child_element_ids.iter().take(entry_count) {
+            data.extend(TextEncoding::Latin1.encode(child_element_id));
+            data.push(0);
+        }
+        data.extend(ID3v2Frame::write_embedded(&self.sub_frames, major_version)?);
+        Ok(data)
+    }
+
+    /// The text of the table of contents' `TIT2` sub-frame.
+    pub fn title(&self) -> Option<&str> {
+        sub_frame_title(&self.sub_frames)
+    }
+}
+
+fn sub_frame_title(sub_frames: &[ID3v2Frame]) -> Option<&str> {
+    sub_frames
+        .iter()
+        .filter(|frame| frame.id() == "TIT2")
+        .find_map(|frame| frame.text().map(TextFrame::text))
+}
+
+fn set_sub_frame(sub_frames: &mut Vec<ID3v2Frame>, frame_id: &str, body: FrameBody) {
+    match sub_frames.iter_mut().find(|frame| frame.id() == frame_id) {
+        Some(frame) => frame.set_body(body),
+        None => sub_frames.push(ID3v2Frame::new(frame_id.to_string(), body)),
+    }
+}
+
+/// Parses a chapter list, with one `start[-end] title[ | url]` line per chapter. Times are
+/// written as `[hh:]mm:ss[.mmm]`. Empty lines and lines starting with `#` are ignored.
+///
+/// A chapter without an end time ends where the next one starts. The last one ends at
+/// `audio_length` milliseconds, or where it starts if that's not known. Chapters are given the
+/// element IDs `chp0`, `chp1`, and so on, in chronological order.
+pub fn parse_chapter_list(
+    text: &str,
+    audio_length: Option<u32>,
+    major_version: u8,
+) -> Result<Vec<Chapter>> {
+    let mut entries = Vec::new();
+    for (line_number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let invalid = |reason: &str| Error::InvalidChapterList {
+            line: line_number + 1,
+            reason: reason.to_string(),
+        };
+        let (times, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let (start, end) = match times.split_once('-') {
+            Some((start, end)) => (start, Some(end)),
+            None => (times, None),
+        };
+        let start = parse_time(start).ok_or_else(|| invalid("invalid start time"))?;
+        let end = match end {
+            Some(end) => Some(parse_time(end).ok_or_else(|| invalid("invalid end time"))?),
+            None => None,
+        };
+        if end.is_some_and(|end| end < start) {
+            return Err(invalid("the chapter ends before it starts"));
+        }
+        let (title, url) = match rest.rsplit_once(" | ") {
+            Some((title, url)) => (title.trim(), Some(url.trim())),
+            None => (rest.trim(), None),
+        };
+        entries.push((start, end, title.to_string(), url.map(str::to_string)));
+    }
+    entries.sort_by_key(|(start, ..)| *start);
+
+    let next_starts: Vec<Option<u32>> = entries
+        .iter()
+        .skip(1)
+        .map(|(start, ..)| Some(*start))
+        .chain([None])
+        .collect();
+    let chapters = entries
+        .into_iter()
+        .zip(next_starts)
+        .enumerate()
+        .map(|(index, ((start, end, title, url), next_start))| {
+            let end = end
+                .or(next_start)
+                .or(audio_length.filter(|length| *length >= start))
+                .unwrap_or(start);
+            let mut chapter = Chapter::new(format!("chp{}", index), start, end);
+            if !title.is_empty() {
+                chapter.set_title(title, major_version);
+            }
+            if let Some(url) = url {
+                chapter.set_url(url, major_version);
+            }
+            chapter
+        })
+        .collect();
+    Ok(chapters)
+}
+
+/// Formats chapters as a list that [`parse_chapter_list`] can read back.
+pub fn format_chapter_list<'a>(chapters: impl IntoIterator<Item = &'a Chapter>) -> String {
+    let mut text = String::new();
+    for chapter in chapters {
+        text.push_str(&format!(
+            "{}-{}",
+            format_time(chapter.start_time),
+            format_time(chapter.end_time)
+        ));
+        if let Some(title) = chapter.title() {
+            text.push(' ');
+            text.push_str(title);
+        }
+        if let Some(url) = chapter.url() {
+            text.push_str(" | ");
+            text.push_str(url);
+        }
+        text.push('\n');
+    }
+    text
+}
+
+/// Formats milliseconds as `hh:mm:ss.mmm`.
+pub(super) fn format_time(milliseconds: u32) -> String {
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        milliseconds / 3_600_000,
+        milliseconds / 60_000 % 60,
+        milliseconds / 1000 % 60,
+        milliseconds % 1000
+    )
+}
+
+/// Parses `[hh:]mm:ss[.mmm]` into milliseconds.
+fn parse_time(time: &str) -> Option<u32> {
+    let (time, fraction) = time.split_once('.').unwrap_or((time, ""));
+    let all_digits =
+        |text: &str| !text.is_empty() && text.chars().all(|char| char.is_ascii_digit());
+    let parts: Vec<&str> = time.split(':').collect();
+    if !(2..=3).contains(&parts.len()) || !parts.iter().all(|part| all_digits(part)) {
+        return None;
+    }
+    if fraction.len() > 3 || !fraction.chars().all(|char| char.is_ascii_digit()) {
+        return None;
+    }
+    let mut seconds = 0u64;
+    for part in parts.iter() {
+        seconds = seconds * 60 + part.parse::<u64>().ok()?;
+    }
+    let fraction_milliseconds = match fraction.len() {
+        0 => 0,
+        length => fraction.parse::<u64>().ok()? * 10u64.pow(3 - length as u32),
+    };
+    (seconds * 1000 + fraction_milliseconds).try_into().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chapters_without_an_end_time_end_where_the_next_one_starts() {
+        let text = "# Chapters\n\
+                    03:00 Outro\n\
+                    00:00 Intro\n\
+                    \n\
+                    01:30.5-02:00 Middle | https://example.com\n";
+        let chapters = parse_chapter_list(text, Some(200_000), 4).unwrap();
+        let times: Vec<(&str, u32, u32)> = chapters
+            .iter()
+            .map(|chapter| {
+                (
+                    chapter.element_id.as_str(),
+                    chapter.start_time,
+                    chapter.end_time,
+                )
+            })
+            .collect();
+        assert_eq!(
+            times,
+            [
+                ("chp0", 0, 90_500),
+                ("chp1", 90_500, 120_000),
+                ("chp2", 180_000, 200_000)
+            ]
+        );
+        assert_eq!(chapters[1].title(), Some("Middle"));
+        assert_eq!(chapters[1].url(), Some("https://example.com"));
+
+        // Without the audio length, the last chapter ends where it starts
+        let chapters = parse_chapter_list("1:00:00 Last", None, 4).unwrap();
+        assert_eq!(
+            (chapters[0].start_time, chapters[0].end_time),
+            (3_600_000, 3_600_000)
+        );
+        // And so it does if the audio ends before it starts
+        let chapters = parse_chapter_list("01:00 Last", Some(1000), 4).unwrap();
+        assert_eq!(chapters[0].end_time, 60_000);
+    }
+
+    #[test]
+    fn invalid_chapter_lists_report_the_line() {
+        let error = parse_chapter_list("00:00 Intro\n02:00-01:00 Back", None, 4).unwrap_err();
+        assert!(matches!(error, Error::InvalidChapterList { line: 2, .. }));
+        let error = parse_chapter_list("1:2:3:4 Too long", None, 4).unwrap_err();
+        assert!(matches!(error, Error::InvalidChapterList { line: 1, .. }));
+        let error = parse_chapter_list("00:00.1234 Too precise", None, 4).unwrap_err();
+        assert!(matches!(error, Error::InvalidChapterList { line: 1, .. }));
+    }
+
+    #[test]
+    fn chapter_lists_are_read_back_as_formatted() {
+        let text = "00:00:00.000-00:01:30.500 Intro | https://example.com\n\
+                    00:01:30.500-01:00:00.000 Rest\n";
+        let chapters = parse_chapter_list(text, None, 4).unwrap();
+        assert_eq!(format_chapter_list(&chapters), text);
+    }
+
+    #[test]
+    fn chapters_round_trip() {
+        for major_version in [3, 4] {
+            let mut chapter = Chapter::new("chp0".to_string(), 1000, 2000);
+            chapter.start_offset = Some(4096);
+            chapter.set_title("Intro".to_string(), major_version);
+            chapter.set_url("https://example.com".to_string(), major_version);
+            let data = chapter.to_bytes(major_version).unwrap();
+            assert_eq!(data[..5], *b"chp0\0");
+            // The end offset is not used
+            assert_eq!(data[17..21], [0xFF; 4]);
+            assert_eq!(Chapter::from_bytes(&data, major_version), Some(chapter));
+        }
+    }
+
+    #[test]
+    fn tables_of_contents_round_trip() {
+        for major_version in [3, 4] {
+            let table = TableOfContents {
+                element_id: "toc".to_string(),
+                top_level: true,
+                ordered: false,
+                child_element_ids: vec!["chp0".to_string(), "chp1".to_string()],
+                sub_frames: vec![ID3v2Frame::new(
+                    "TIT2".to_string(),
+                    FrameBody::Text(TextFrame::with_default_encoding(
+                        vec!["Contents".to_string()],
+                        major_version,
+                    )),
+                )],
+            };
+            let data = table.to_bytes(major_version).unwrap();
+            assert_eq!(data[..16], *b"toc\0\x02\x02chp0\0chp1\0");
+            let read = TableOfContents::from_bytes(&data, major_version).unwrap();
+            assert_eq!(read.title(), Some("Contents"));
+            assert_eq!(read, table);
+        }
+    }
+
+    #[test]
+    fn tables_of_contents_with_missing_entries_are_truncated() {
+        assert_eq!(TableOfContents::from_bytes(b"toc\0\x03\x02chp0\0", 4), None);
+    }
+}
@@ -0,0 +1,122 @@
+use crate::mp3_file::text_encoding::TextEncoding;
+
+/// The language used when the user doesn't choose one.
+pub const DEFAULT_LANGUAGE: &str = "eng";
+
+/// The body of comment (`COMM`) and unsynchronised lyrics (`USLT`) frames, which share the
+/// same layout. A tag may have several of them, as long as no two share both language and
+/// description.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comment {
+    /// The encoding of the description and the text.
+    pub encoding: TextEncoding,
+    /// A three letter ISO-639-2 language code, such as `eng`.
+    pub language: String,
+    /// A short description of what the text is about. Often empty.
+    pub description: String,
+    pub text: String,
+}
+
+impl Comment {
+    /// A comment in the best encoding for its description and text on a tag of the given
+    /// version.
+    pub fn new(language: String, description: String, text: String, major_version: u8) -> Comment {
+        Comment {
+            encoding: TextEncoding::default_for(&[&description, &text], major_version),
+            language,
+            description,
+            text,
+        }
+    }
+
+    /// Decodes the data of a `COMM` or `USLT` frame. Returns `None` if the encoding byte is
+    /// invalid or the language is missing.
+    pub fn from_bytes(data: &[u8]) -> Option<Comment> {
+        let (&encoding_byte, rest) = data.split_first()?;
+        let encoding = TextEncoding::from_byte(encoding_byte)?;
+        let language = rest.get(0..3)?;
+        let (description, text) = encoding.split_terminated(&rest[3..]);
+        // A terminator after the text is not required, but some taggers write it
+        let (text, _) = encoding.split_terminated(text);
+        Some(Comment {
+            encoding,
+            language: TextEncoding::Latin1.decode(language),
+            description: encoding.decode(description),
+            text: encoding.decode(text),
+        })
+    }
+
+    /// Encodes the frame for a tag of the given version. Encodings the version doesn't support
+    /// are replaced by UTF-16.
+    pub fn to_bytes(&self, major_version: u8) -> Vec<u8> {
+        let encoding = self.encoding.or_supported_by(major_version);
+        let mut data = vec![encoding as u8];
+        // The language is always exactly 3 bytes
+        let mut language = TextEncoding::Latin1.encode(&self.language);
+        language.resize(3, b' ');
+        data.extend(language);
+        data.extend(encoding.encode(&self.description));
+        data.extend(encoding.terminator());
+        data.extend(encoding.encode(&self.text));
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn utf16_descriptions_and_texts_have_their_own_byte_order_mark() {
+        let data = b"\x01eng\xFF\xFEd\0\0\0\xFE\xFF\0t\0x";
+        let comment = Comment::from_bytes(data).unwrap();
+        assert_eq!(comment.encoding, TextEncoding::Utf16);
+        assert_eq!(comment.language, "eng");
+        assert_eq!(comment.description, "d");
+        assert_eq!(comment.text, "tx");
+    }
+
+    #[test]
+    fn utf16_terminators_are_aligned_to_code_units() {
+        // "ĀA" ends with the bytes 0x41 0x00, whose 0x00 and the terminator's first byte
+        // would look like a terminator one byte too soon
+        let data = b"\x01eng\xFF\xFE\x00\x01A\0\0\0\xFF\xFEx\0\0\0";
+        let comment = Comment::from_bytes(data).unwrap();
+        assert_eq!(comment.description, "ĀA");
+        // The terminator after the text is dropped
+        assert_eq!(comment.text, "x");
+        assert_eq!(comment.to_bytes(3), data[..data.len() - 2]);
+    }
+
+    #[test]
+    fn comments_round_trip() {
+        let comment = Comment::new(String::new(), "desc".to_string(), "text".to_string(), 3);
+        assert_eq!(comment.encoding, TextEncoding::Latin1);
+        assert_eq!(comment.to_bytes(3), b"\0   desc\0text");
+        let comment = Comment::new("en".to_string(), String::new(), "ça".to_string(), 4);
+        assert_eq!(comment.encoding, TextEncoding::Utf8);
+        let data = comment.to_bytes(4);
+        assert_eq!(data, b"\x03en \0\xC3\xA7a");
+        assert_eq!(
+            Comment::from_bytes(&data).unwrap(),
+            Comment {
+                language: "en ".to_string(),
+                ..comment.clone()
+            }
+        );
+        // ID3v2.3 has no UTF-8, so it's written as UTF-16
+        assert_eq!(comment.to_bytes(3), b"\x01en \xFF\xFE\0\0\xFF\xFE\xE7\0a\0");
+    }
+
+    #[test]
+    fn comments_without_a_language_are_invalid() {
+        assert_eq!(Comment::from_bytes(b"\0en"), None);
+        assert_eq!(Comment::from_bytes(b"\x04eng\0text"), None);
+        assert_eq!(Comment::from_bytes(b""), None);
+        let comment = Comment::from_bytes(b"\0eng").unwrap();
+        assert_eq!(
+            (comment.description.as_str(), comment.text.as_str()),
+            ("", "")
+        );
+    }
+}
@@ -0,0 +1,115 @@
+use crate::mp3_file::text_encoding::TextEncoding;
+
+/// The body of general encapsulated object frames (`GEOB`): a file of any kind stored in the
+/// tag. A tag may have several of them, as long as no two share the same description.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncapsulatedObject {
+    /// The encoding of the filename and the description.
+    pub encoding: TextEncoding,
+    /// Such as `application/pdf`. May be empty.
+    pub mime_type: String,
+    /// The name of the file the object was read from. May be empty.
+    pub filename: String,
+    pub description: String,
+    /// The contents of the file.
+    pub data: Vec<u8>,
+}
+
+impl EncapsulatedObject {
+    /// An object in the best encoding for its filename and description on a tag of the given
+    /// version.
+    pub fn new(
+        mime_type: String,
+        filename: String,
+        description: String,
+        data: Vec<u8>,
+        major_version: u8,
+    ) -> EncapsulatedObject {
+        EncapsulatedObject {
+            encoding: TextEncoding::default_for(&[&filename, &description], major_version),
+            mime_type,
+            filename,
+            description,
+            data,
+        }
+    }
+
+    /// Decodes the data of a `GEOB` frame. Returns `None` if the encoding byte is invalid.
+    pub fn from_bytes(data: &[u8]) -> Option<EncapsulatedObject> {
+        let (&encoding_byte, rest) = data.split_first()?;
+        let encoding = TextEncoding::from_byte(encoding_byte)?;
+        let (mime_type, rest) = TextEncoding::Latin1.split_terminated(rest);
+        let (filename, rest) = encoding.split_terminated(rest);
+        let (description, object) = encoding.split_terminated(rest);
+        Some(EncapsulatedObject {
+            encoding,
+            mime_type: TextEncoding::Latin1.decode(mime_type),
+            filename: encoding.decode(filename),
+            description: encoding.decode(description),
+            data: object.to_vec(),
+        })
+    }
+
+    /// Encodes the frame for a tag of the given version. Encodings the version doesn't support
+    /// are replaced by UTF-16.
+    pub fn to_bytes(&self, major_version: u8) -> Vec<u8> {
+        let encoding = self.encoding.or_supported_by(major_version);
+        let mut data = vec![encoding as u8];
+        data.extend(TextEncoding::Latin1.encode(&self.mime_type));
+        data.push(0);
+        data.extend(encoding.encode(&self.filename));
+        data.extend(encoding.terminator());
+        data.extend(encoding.encode(&self.description));
+        data.extend(encoding.terminator());
+        data.extend(&self.data);
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encapsulated_objects_round_trip() {
+        let data = b"\0application/pdf\0notes.pdf\0Notes\0%PDF\0\x01";
+        let object = EncapsulatedObject::from_bytes(data).unwrap();
+        assert_eq!(object.encoding, TextEncoding::Latin1);
+        assert_eq!(object.mime_type, "application/pdf");
+        assert_eq!(object.filename, "notes.pdf");
+        assert_eq!(object.description, "Notes");
+        assert_eq!(object.data, b"%PDF\0\x01");
+        assert_eq!(object.to_bytes(3), data);
+
+        let data = b"\x01\0\xFF\xFE\0\0\xFF\xFEd\0\0\0\0\0";
+        let object = EncapsulatedObject::from_bytes(data).unwrap();
+        assert_eq!(
+            (object.mime_type.as_str(), object.filename.as_str()),
+            ("", "")
+        );
+        assert_eq!(object.description, "d");
+        assert_eq!(object.data, [0, 0]);
+        assert_eq!(object.to_bytes(3), data);
+    }
+
+    #[test]
+    fn encapsulated_objects_are_encoded_for_the_version() {
+        let object = EncapsulatedObject::new(
+            "text/plain".to_string(),
+            "é.txt".to_string(),
+            String::new(),
+            b"data".to_vec(),
+            4,
+        );
+        assert_eq!(object.encoding, TextEncoding::Utf8);
+        let data = object.to_bytes(4);
+        assert_eq!(data, b"\x03text/plain\0\xC3\xA9.txt\0\0data");
+        assert_eq!(EncapsulatedObject::from_bytes(&data), Some(object.clone()));
+        // ID3v2.3 has no UTF-8, so it's written as UTF-16
+        assert_eq!(
+            object.to_bytes(3),
+            b"\x01text/plain\0\xFF\xFE\xE9\0.\0t\0x\0t\0\0\0\xFF\xFE\0\0data"
+        );
+        assert_eq!(EncapsulatedObject::from_bytes(b"\x04text/plain\0"), None);
+    }
+}
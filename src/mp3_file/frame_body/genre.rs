@@ -0,0 +1,321 @@
+use std::fmt;
+
+/// The genres defined by ID3v1, followed by the ones Winamp added. `TCON` frames refer to them
+/// by index.
+pub const ID3V1_GENRES: [&str; 192] = [
+    "Blues",
+    "Classic Rock",
+    "Country",
+    "Dance",
+    "Disco",
+    "Funk",
+    "Grunge",
+    "Hip-Hop",
+    "Jazz",
+    "Metal",
+    "New Age",
+    "Oldies",
+    "Other",
+    "Pop",
+    "R&B",
+    "Rap",
+    "Reggae",
+    "Rock",
+    "Techno",
+    "Industrial",
+    "Alternative",
+    "Ska",
+    "Death Metal",
+    "Pranks",
+    "Soundtrack",
+    "Euro-Techno",
+    "Ambient",
+    "Trip-Hop",
+    "Vocal",
+    "Jazz+Funk",
+    "Fusion",
+    "Trance",
+    "Classical",
+    "Instrumental",
+    "Acid",
+    "House",
+    "Game",
+    "Sound Clip",
+    "Gospel",
+    "Noise",
+    "Alternative Rock",
+    "Bass",
+    "Soul",
+    "Punk",
+    "Space",
+    "Meditative",
+    "Instrumental Pop",
+    "Instrumental Rock",
+    "Ethnic",
+    "Gothic",
+    "Darkwave",
+    "Techno-Industrial",
+    "Electronic",
+    "Pop-Folk",
+    "Eurodance",
+    "Dream",
+    "Southern Rock",
+    "Comedy",
+    "Cult",
+    "Gangsta",
+    "Top 40",
+    "Christian Rap",
+    "Pop/Funk",
+    "Jungle",
+    "Native American",
+    "Cabaret",
+    "New Wave",
+    "Psychedelic",
+    "Rave",
+    "Showtunes",
+    "Trailer",
+    "Lo-Fi",
+    "Tribal",
+    "Acid Punk",
+    "Acid Jazz",
+    "Polka",
+    "Retro",
+    "Musical",
+    "Rock & Roll",
+    "Hard Rock",
+    "Folk",
+    "Folk-Rock",
+    "National Folk",
+    "Swing",
+    "Fast Fusion",
+    "Bebop",
+    "Latin",
+    "Revival",
+    "Celtic",
+    "Bluegrass",
+    "Avantgarde",
+    "Gothic Rock",
+    "Progressive Rock",
+    "Psychedelic Rock",
+    "Symphonic Rock",
+    "Slow Rock",
+    "Big Band",
+    "Chorus",
+    "Easy Listening",
+    "Acoustic",
+    "Humour",
+    "Speech",
+    "Chanson",
+    "Opera",
+    "Chamber Music",
+    "Sonata",
+    "Symphony",
+    "Booty Bass",
+    "Primus",
+    "Porn Groove",
+    "Satire",
+    "Slow Jam",
+    "Club",
+    "Tango",
+    "Samba",
+    "Folklore",
+    "Ballad",
+    "Power Ballad",
+    "Rhythmic Soul",
+    "Freestyle",
+    "Duet",
+    "Punk Rock",
+    "Drum Solo",
+    "A Cappella",
+    "Euro-House",
+    "Dance Hall",
+    "Goa",
+    "Drum & Bass",
+    "Club-House",
+    "Hardcore Techno",
+    "Terror",
+    "Indie",
+    "BritPop",
+    "Afro-Punk",
+    "Polsk Punk",
+    "Beat",
+    "Christian Gangsta Rap",
+    "Heavy Metal",
+    "Black Metal",
+    "Crossover",
+    "Contemporary Christian",
+    "Christian Rock",
+    "Merengue",
+    "Salsa",
+    "Thrash Metal",
+    "Anime",
+    "JPop",
+    "Synthpop",
+    "Abstract",
+    "Art Rock",
+    "Baroque",
+    "Bhangra",
+    "Big Beat",
+    "Breakbeat",
+    "Chillout",
+    "Downtempo",
+    "Dub",
+    "EBM",
+    "Eclectic",
+    "Electro",
+    "Electroclash",
+    "Emo",
+    "Experimental",
+    "Garage",
+    "Global",
+    "IDM",
+    "Illbient",
+    "Industro-Goth",
+    "Jam Band",
+    "Krautrock",
+    "Leftfield",
+    "Lounge",
+    "Math Rock",
+    "New Romantic",
+    "Nu-Breakz",
+    "Post-Punk",
+    "Post-Rock",
+    "Psytrance",
+    "Shoegaze",
+    "Space Rock",
+    "Trop Rock",
+    "World Music",
+    "Neoclassical",
+    "Audiobook",
+    "Audio Theatre",
+    "Neue Deutsche Welle",
+    "Podcast",
+    "Indie Rock",
+    "G-Funk",
+    "Dubstep",
+    "Garage Rock",
+    "Psybient",
+];
+
+/// A single genre of a `TCON` frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Genre {
+    /// An index in [`ID3V1_GENRES`]. Indexes past the end of the list are kept as is.
+    Id3v1(u8),
+    /// Written as `RX`.
+    Remix,
+    /// Written as `CR`.
+    Cover,
+    /// Any other text.
+    Custom(String),
+}
+
+impl Genre {
+    /// Interprets the values of a `TCON` frame. Each value may be an ID3v2.4 reference, such as
+    /// `17` or `RX`, or an ID3v2.3 string, such as `(17)(RX)Eurodisco`. ID3v2.3 refinements that
+    /// only repeat the name of the genre before them are dropped.
+    pub fn parse_values<S: AsRef<str>>(values: &[S]) -> Vec<Genre> {
+        let mut genres = Vec::new();
+        for value in values {
+            let value = value.as_ref().trim();
+            match Self::from_reference(value) {
+                Some(genre) => genres.push(genre),
+                None => Self::parse_v23(value, &mut genres),
+            }
+        }
+        genres
+    }
+
+    /// Writes genres the way the given version expects them: one value per genre in ID3v2.4,
+    /// or a single `(17)(RX)Eurodisco` value before that.
+    pub fn to_values(genres: &[Genre], major_version: u8) -> Vec<String> {
+        if major_version >= 4 {
+            return genres
+                .iter()
+                .map(|genre| match genre {
+                    Genre::Id3v1(index) => index.to_string(),
+                    Genre::Remix => "RX".to_string(),
+                    Genre::Cover => "CR".to_string(),
+                    Genre::Custom(name) => name.clone(),
+                })
+                .collect();
+        }
+        let mut value = String::new();
+        let mut names = Vec::new();
+        for genre in genres {
+            match genre {
+                Genre::Id3v1(index) => value.push_str(&format!("({})", index)),
+                Genre::Remix => value.push_str("(RX)"),
+                Genre::Cover => value.push_str("(CR)"),
+                Genre::Custom(name) => names.push(name.as_str()),
+            }
+        }
+        let names = names.join("/");
+        // A refinement that would be read back as references, such as "(17)Live", is escaped by
+        // doubling its "(". Others, such as "(Live)", are written as they are
+        let mut read_back = Vec::new();
+        Self::parse_v23(&names, &mut read_back);
+        if names.starts_with('(') && read_back != [Genre::Custom(names.clone())] {
+            value.push('(');
+        }
+        value.push_str(&names);
+        vec![value]
+    }
+
+    /// `17`, `RX` or `CR`.
+    fn from_reference(reference: &str) -> Option<Genre> {
+        match reference {
+            "RX" => Some(Genre::Remix),
+            "CR" => Some(Genre::Cover),
+            _ if !reference.is_empty() && reference.chars().all(|char| char.is_ascii_digit()) => {
+                reference.parse().ok().map(Genre::Id3v1)
+            }
+            _ => None,
+        }
+    }
+
+    fn parse_v23(mut value: &str, genres: &mut Vec<Genre>) {
+        while let Some(rest) = value.strip_prefix('(') {
+            if rest.starts_with('(') {
+                // An escaped parenthesis starts the refinement
+                value = rest;
+                break;
+            }
+            let Some((reference, remaining)) = rest.split_once(')') else {
+                break;
+            };
+            let Some(genre) = Self::from_reference(reference) else {
+                break;
+            };
+            genres.push(genre);
+            value = remaining;
+        }
+        if value.is_empty() {
+            return;
+        }
+        let repeats_last =
+            matches!(genres.last(), Some(last) if last.name().eq_ignore_ascii_case(value));
+        if !repeats_last {
+            genres.push(Genre::Custom(value.to_string()));
+        }
+    }
+
+    /// The name of the genre, such as `Rock`.
+    pub fn name(&self) -> String {
+        match self {
+            Genre::Id3v1(index) => match ID3V1_GENRES.get(*index as usize) {
+                Some(name) => name.to_string(),
+                None => format!("Unknown genre {}", index),
+            },
+            Genre::Remix => "Remix".to_string(),
+            Genre::Cover => "Cover".to_string(),
+            Genre::Custom(name) => name.clone(),
+        }
+    }
+}
+
+impl fmt::Display for Genre {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name())
+    }
+}
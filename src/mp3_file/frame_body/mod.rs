@@ -0,0 +1,499 @@
+//! The typed contents of frames.
+
+mod chapter;
+mod comment;
+mod encapsulated_object;
+mod genre;
+mod owned_data;
+mod picture;
+mod popularimeter;
+mod relative_volume;
+mod set_position;
+mod synchronised_lyrics;
+mod text;
+mod timestamp;
+mod user_defined;
+
+pub use chapter::{format_chapter_list, parse_chapter_list, Chapter, TableOfContents};
+pub use comment::{Comment, DEFAULT_LANGUAGE};
+pub use encapsulated_object::EncapsulatedObject;
+pub use genre::{Genre, ID3V1_GENRES};
+pub use owned_data::{OwnedData, MAX_UNIQUE_FILE_IDENTIFIER_LENGTH};
+pub use picture::{sniff_mime_type, Picture, PictureType, PICTURE_TYPES};
+pub use popularimeter::{rating_to_stars, stars_to_rating, PlayCounter, Popularimeter};
+pub(crate) use relative_volume::{format_gain, parse_gain};
+pub use relative_volume::{ChannelAdjustment, ChannelType, GainScope, RelativeVolume, ReplayGain};
+pub use set_position::SetPosition;
+pub use synchronised_lyrics::{ContentType, SynchronisedLyrics, SynchronisedText, TimestampFormat};
+pub use text::{is_text_frame_id, TextFrame};
+pub use timestamp::{is_timestamp_frame_id, Timestamp, TIMESTAMP_FRAME_IDS};
+pub use user_defined::{is_user_defined_frame_id, UserText, UserUrl};
+
+use super::id3v2_frame::ID3v2Frame;
+use super::text_encoding::TextEncoding;
+use crate::error::Result;
+use crate::utils::BinaryFormat;
+
+/// The contents of a frame, interpreted according to its ID.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FrameBody {
+    /// Text information frames: every frame whose ID starts with `T`, except `TXXX`.
+    Text(TextFrame),
+    /// URL link frames: every frame whose ID starts with `W`, except `WXXX`.
+    Url(String),
+    /// User defined text frames (`TXXX`).
+    UserText(UserText),
+    /// User defined URL link frames (`WXXX`).
+    UserUrl(UserUrl),
+    /// Attached pictures (`APIC`).
+    Picture(Picture),
+    /// Comments (`COMM`).
+    Comment(Comment),
+    /// Unsynchronised lyrics (`USLT`).
+    Lyrics(Comment),
+    /// Synchronised lyrics or text (`SYLT`).
+    SynchronisedLyrics(SynchronisedLyrics),
+    /// Chapters (`CHAP`).
+    Chapter(Chapter),
+    /// Tables of contents (`CTOC`).
+    TableOfContents(TableOfContents),
+    /// Popularimeters (`POPM`).
+    Popularimeter(Popularimeter),
+    /// Play counters (`PCNT`): how many times the file was played.
+    PlayCounter(PlayCounter),
+    /// Unique file identifiers (`UFID`).
+    UniqueFileIdentifier(OwnedData),
+    /// Private frames (`PRIV`).
+    Private(OwnedData),
+    /// General encapsulated objects (`GEOB`).
+    EncapsulatedObject(EncapsulatedObject),
+    /// Relative volume adjustments (`RVA2`).
+    RelativeVolume(RelativeVolume),
+    /// Frames that aren't interpreted, or whose data is compressed or encrypted. Their data is
+    /// written back exactly as it was read.
+    Unknown(Vec<u8>),
+}
+
+impl FrameBody {
+    /// Interprets the data of a frame with ID `frame_id` from a tag with the given version.
+    /// Falls back to [`FrameBody::Unknown`] if the data is malformed.
+    pub fn from_bytes(frame_id: &str, data: &[u8], major_version: u8) -> FrameBody {
+        let body = if is_text_frame_id(frame_id) {
+            TextFrame::from_bytes(data).map(FrameBody::Text)
+        } else if is_url_frame_id(frame_id) {
+            let (url, _) = TextEncoding::Latin1.split_terminated(data);
+            Some(FrameBody::Url(TextEncoding::Latin1.decode(url)))
+        } else if frame_id == "TXXX" {
+            UserText::from_bytes(data).map(FrameBody::UserText)
+        } else if frame_id == "WXXX" {
+            UserUrl::from_bytes(data).map(FrameBody::UserUrl)
+        } else if frame_id == "APIC" {
+            Picture::from_bytes(data).map(FrameBody::Picture)
+        } else if frame_id == "COMM" {
+            Comment::from_bytes(data).map(FrameBody::Comment)
+        } else if frame_id == "USLT" {
+            Comment::from_bytes(data).map(FrameBody::Lyrics)
+        } else if frame_id == "SYLT" {
+            SynchronisedLyrics::from_bytes(data).map(FrameBody::SynchronisedLyrics)
+        } else if frame_id == "CHAP" {
+            Chapter::from_bytes(data, major_version).map(FrameBody::Chapter)
+        } else if frame_id == "CTOC" {
+            TableOfContents::from_bytes(data, major_version).map(FrameBody::TableOfContents)
+        } else if frame_id == "POPM" {
+            Popularimeter::from_bytes(data).map(FrameBody::Popularimeter)
+        } else if frame_id == "PCNT" {
+            Some(FrameBody::PlayCounter(PlayCounter::from_bytes(data)))
+        } else if frame_id == "UFID" {
+            OwnedData::from_bytes(data).map(FrameBody::UniqueFileIdentifier)
+        } else if frame_id == "PRIV" {
+            OwnedData::from_bytes(data).map(FrameBody::Private)
+        } else if frame_id == "GEOB" {
+            EncapsulatedObject::from_bytes(data).map(FrameBody::EncapsulatedObject)
+        } else if frame_id == "RVA2" {
+            RelativeVolume::from_bytes(data).map(FrameBody::RelativeVolume)
+        } else {
+            return FrameBody::Unknown(data.to_vec());
+        };
+        body.unwrap_or_else(|| {
+            log::warn!(
+                "Frame \"{}\" is malformed. Its data will be kept as is.",
+                frame_id
+            );
+            FrameBody::Unknown(data.to_vec())
+        })
+    }
+
+    /// Builds a body from a value typed by the user. Frames that aren't interpreted get the
+    /// value's bytes as their data.
+    pub fn from_user_input(frame_id: &str, data: String, major_version: u8) -> FrameBody {
+        if is_text_frame_id(frame_id) {
+            FrameBody::Text(TextFrame::with_default_encoding(vec![data], major_version))
+        } else if is_url_frame_id(frame_id) {
+            FrameBody::Url(data)
+        } else if frame_id == "TXXX" {
+            FrameBody::UserText(UserText::new(String::new(), vec![data], major_version))
+        } else if frame_id == "WXXX" {
+            FrameBody::UserUrl(UserUrl::new(String::new(), data, major_version))
+        } else if is_comment_frame_id(frame_id) {
+            let comment = Comment::new(
+                DEFAULT_LANGUAGE.to_string(),
+                String::new(),
+                data,
+                major_version,
+            );
+            FrameBody::comment(frame_id, comment)
+        } else {
+            FrameBody::Unknown(data.into_bytes())
+        }
+    }
+
+    /// Encodes the body for a tag of the given version. Fails if the body embeds frames that
+    /// can't be written.
+    pub fn to_bytes(&self, major_version: u8) -> Result<Vec<u8>> {
+        Ok(match self {
+            FrameBody::Text(text_frame) => text_frame.to_bytes(major_version),
+            FrameBody::Url(url) => TextEncoding::Latin1.encode(url),
+            FrameBody::UserText(user_text) => user_text.to_bytes(major_version),
+            FrameBody::UserUrl(user_url) => user_url.to_bytes(major_version),
+            FrameBody::Picture(picture) => picture.to_bytes(major_version),
+            FrameBody::Comment(comment) | FrameBody::Lyrics(comment) => {
+                comment.to_bytes(major_version)
+            }
+            FrameBody::SynchronisedLyrics(lyrics) => lyrics.to_bytes(major_version),
+            FrameBody::Chapter(chapter) => chapter.to_bytes(major_version)?,
+            FrameBody::TableOfContents(table) => table.to_bytes(major_version)?,
+            FrameBody::Popularimeter(popularimeter) => popularimeter.to_bytes(),
+            FrameBody::PlayCounter(counter) => counter.to_bytes(),
+            FrameBody::UniqueFileIdentifier(owned_data) | FrameBody::Private(owned_data) => {
+                owned_data.to_bytes()
+            }
+            FrameBody::EncapsulatedObject(object) => object.to_bytes(major_version),
+            FrameBody::RelativeVolume(relative_volume) => relative_volume.to_bytes(),
+            FrameBody::Unknown(data) => data.clone(),
+        })
+    }
+
+    /// The encoding of the body's text, for bodies that have one.
+    pub fn text_encoding(&self) -> Option<TextEncoding> {
+        match self {
+            FrameBody::Text(text_frame) => Some(text_frame.encoding),
+            FrameBody::UserText(user_text) => Some(user_text.encoding),
+            FrameBody::UserUrl(user_url) => Some(user_url.encoding),
+            FrameBody::Picture(picture) => Some(picture.encoding),
+            FrameBody::Comment(comment) | FrameBody::Lyrics(comment) => Some(comment.encoding),
+            FrameBody::SynchronisedLyrics(lyrics) => Some(lyrics.encoding),
+            FrameBody::EncapsulatedObject(object) => Some(object.encoding),
+            FrameBody::Url(_)
+            | FrameBody::Chapter(_)
+            | FrameBody::TableOfContents(_)
+            | FrameBody::Popularimeter(_)
+            | FrameBody::PlayCounter(_)
+            | FrameBody::UniqueFileIdentifier(_)
+            | FrameBody::Private(_)
+            | FrameBody::RelativeVolume(_)
+            | FrameBody::Unknown(_) => None,
+        }
+    }
+
+    /// Changes the encoding of the body's text. Does nothing to bodies without text.
+    pub fn set_text_encoding(&mut self, encoding: TextEncoding) {
+        match self {
+            FrameBody::Text(text_frame) => text_frame.encoding = encoding,
+            FrameBody::UserText(user_text) => user_text.encoding = encoding,
+            FrameBody::UserUrl(user_url) => user_url.encoding = encoding,
+            FrameBody::Picture(picture) => picture.encoding = encoding,
+            FrameBody::Comment(comment) | FrameBody::Lyrics(comment) => comment.encoding = encoding,
+            FrameBody::SynchronisedLyrics(lyrics) => lyrics.encoding = encoding,
+            FrameBody::EncapsulatedObject(object) => object.encoding = encoding,
+            FrameBody::Url(_)
+            | FrameBody::Chapter(_)
+            | FrameBody::TableOfContents(_)
+            | FrameBody::Popularimeter(_)
+            | FrameBody::PlayCounter(_)
+            | FrameBody::UniqueFileIdentifier(_)
+            | FrameBody::Private(_)
+            | FrameBody::RelativeVolume(_)
+            | FrameBody::Unknown(_) => (),
+        }
+    }
+
+    /// Replaces the value of the body with one typed by the user. Bodies identified by a
+    /// language or a description keep them, and only their text changes. Other bodies are
+    /// replaced by [`FrameBody::from_user_input`].
+    pub fn set_user_input(&mut self, frame_id: &str, data: String, major_version: u8) {
+        match self {
+            FrameBody::UserText(user_text) => {
+                *user_text = UserText::new(user_text.description.clone(), vec![data], major_version)
+            }
+            FrameBody::UserUrl(user_url) => user_url.url = data,
+            FrameBody::Comment(comment) | FrameBody::Lyrics(comment) => {
+                *comment = Comment::new(
+                    comment.language.clone(),
+                    comment.description.clone(),
+                    data,
+                    major_version,
+                )
+            }
+            _ => *self = FrameBody::from_user_input(frame_id, data, major_version),
+        }
+    }
+
+    /// The description of `TXXX`, `WXXX` and `GEOB` bodies, which identifies them.
+    pub fn description(&self) -> Option<&str> {
+        match self {
+            FrameBody::UserText(user_text) => Some(&user_text.description),
+            FrameBody::UserUrl(user_url) => Some(&user_url.description),
+            FrameBody::EncapsulatedObject(object) => Some(&object.description),
+            _ => None,
+        }
+    }
+
+    /// The language and description of `COMM`, `USLT` and `SYLT` bodies, which identify them.
+    pub fn language_and_description(&self) -> Option<(&str, &str)> {
+        match self {
+            FrameBody::Comment(comment) | FrameBody::Lyrics(comment) => {
+                Some((&comment.language, &comment.description))
+            }
+            FrameBody::SynchronisedLyrics(lyrics) => Some((&lyrics.language, &lyrics.description)),
+            _ => None,
+        }
+    }
+
+    /// Wraps a comment in the variant for `frame_id`, which must be `COMM` or `USLT`.
+    pub fn comment(frame_id: &str, comment: Comment) -> FrameBody {
+        if frame_id == "USLT" {
+            FrameBody::Lyrics(comment)
+        } else {
+            FrameBody::Comment(comment)
+        }
+    }
+
+    /// The comment of `COMM` and `USLT` bodies.
+    pub fn as_comment(&self) -> Option<&Comment> {
+        match self {
+            FrameBody::Comment(comment) | FrameBody::Lyrics(comment) => Some(comment),
+            _ => None,
+        }
+    }
+
+    /// Wraps owned data in the variant for `frame_id`, which must be `UFID` or `PRIV`.
+    pub fn owned_data(frame_id: &str, owned_data: OwnedData) -> FrameBody {
+        if frame_id == "UFID" {
+            FrameBody::UniqueFileIdentifier(owned_data)
+        } else {
+            FrameBody::Private(owned_data)
+        }
+    }
+
+    /// The owned data of `UFID` and `PRIV` bodies, which are identified by its owner.
+    pub fn as_owned_data(&self) -> Option<&OwnedData> {
+        match self {
+            FrameBody::UniqueFileIdentifier(owned_data) | FrameBody::Private(owned_data) => {
+                Some(owned_data)
+            }
+            _ => None,
+        }
+    }
+
+    /// The frames embedded in `CHAP` and `CTOC` bodies.
+    pub fn sub_frames_mut(&mut self) -> Option<&mut Vec<ID3v2Frame>> {
+        match self {
+            FrameBody::Chapter(chapter) => Some(&mut chapter.sub_frames),
+            FrameBody::TableOfContents(table) => Some(&mut table.sub_frames),
+            _ => None,
+        }
+    }
+
+    /// A single line representation of the body. The data of `UFID` and `PRIV` bodies is shown
+    /// as hex.
+    pub fn format(&self) -> String {
+        self.format_with(BinaryFormat::Hex)
+    }
+
+    /// Like [`FrameBody::format`], but shows the data of `UFID` and `PRIV` bodies in
+    /// `binary_format`.
+    pub fn format_with(&self, binary_format: BinaryFormat) -> String {
+        match self {
+            FrameBody::Text(text_frame) => text_frame.values.join("; "),
+            FrameBody::Url(url) => url.clone(),
+            FrameBody::UserText(user_text) => {
+                format!(
+                    "\"{}\": {}",
+                    user_text.description,
+                    user_text.values.join("; ")
+                )
+            }
+            FrameBody::UserUrl(user_url) => {
+                format!("\"{}\": {}", user_url.description, user_url.url)
+            }
+            FrameBody::Picture(picture) => format_picture(picture),
+            FrameBody::Comment(comment) | FrameBody::Lyrics(comment) => format_comment(comment),
+            FrameBody::SynchronisedLyrics(lyrics) => format_synchronised_lyrics(lyrics),
+            FrameBody::Chapter(chapter) => format_chapter(chapter),
+            FrameBody::TableOfContents(table) => format_table_of_contents(table),
+            FrameBody::Popularimeter(popularimeter) => format_popularimeter(popularimeter),
+            FrameBody::EncapsulatedObject(object) => format_encapsulated_object(object),
+            FrameBody::RelativeVolume(relative_volume) => format_relative_volume(relative_volume),
+            FrameBody::PlayCounter(counter) => format!("played {} times", counter),
+            FrameBody::UniqueFileIdentifier(owned_data) | FrameBody::Private(owned_data) => {
+                format!(
+                    "\"{}\": {}",
+                    owned_data.owner,
+                    binary_format.encode(&owned_data.data)
+                )
+            }
+            FrameBody::Unknown(data) => format_binary(data),
+        }
+    }
+}
+
+/// Whether frames with this ID hold a [`FrameBody::Url`].
+pub fn is_url_frame_id(frame_id: &str) -> bool {
+    frame_id.starts_with('W') && frame_id != "WXXX"
+}
+
+fn format_comment(comment: &Comment) -> String {
+    let text = comment.text.replace('\n', " / ");
+    if comment.description.is_empty() {
+        format!("[{}] {}", comment.language, text)
+    } else {
+        format!(
+            "[{}] \"{}\": {}",
+            comment.language, comment.description, text
+        )
+    }
+}
+
+fn format_synchronised_lyrics(lyrics: &SynchronisedLyrics) -> String {
+    let unit = match lyrics.timestamp_format {
+        TimestampFormat::MpegFrames => "MPEG frames",
+        TimestampFormat::Milliseconds => "milliseconds",
+    };
+    let mut result = format!("[{}] ", lyrics.language);
+    if !lyrics.description.is_empty() {
+        result.push_str(&format!("\"{}\": ", lyrics.description));
+    }
+    result.push_str(&format!(
+        "{} lines of {:?} timed in {}",
+        lyrics.lines.len(),
+        lyrics.content_type,
+        unit
+    ));
+    result
+}
+
+fn format_chapter(chapter: &Chapter) -> String {
+    let mut result = format!(
+        "{} {}-{}",
+        chapter.element_id,
+        chapter::format_time(chapter.start_time),
+        chapter::format_time(chapter.end_time)
+    );
+    if let Some(title) = chapter.title() {
+        result.push_str(&format!(" \"{}\"", title));
+    }
+    result.push_str(&format_sub_frames(&chapter.sub_frames));
+    result
+}
+
+fn format_table_of_contents(table: &TableOfContents) -> String {
+    let mut result = table.element_id.clone();
+    if let Some(title) = table.title() {
+        result.push_str(&format!(" \"{}\"", title));
+    }
+    let mut flags = Vec::new();
+    if table.top_level {
+        flags.push("top level");
+    }
+    if table.ordered {
+        flags.push("ordered");
+    }
+    if !flags.is_empty() {
+        result.push_str(&format!(" ({})", flags.join(", ")));
+    }
+    result.push_str(&format!(": {}", table.child_element_ids.join(", ")));
+    result.push_str(&format_sub_frames(&table.sub_frames));
+    result
+}
+
+/// Lists the IDs of embedded frames, e.g. ` [TIT2, APIC]`.
+fn format_sub_frames(sub_frames: &[ID3v2Frame]) -> String {
+    if sub_frames.is_empty() {
+        return String::new();
+    }
+    let ids: Vec<&str> = sub_frames.iter().map(ID3v2Frame::id).collect();
+    format!(" [{}]", ids.join(", "))
+}
+
+fn format_popularimeter(popularimeter: &Popularimeter) -> String {
+    let mut result = format!("\"{}\": ", popularimeter.email);
+    match popularimeter.stars() {
+        Some(stars) => result.push_str(&format!(
+            "{} star{} ({}/255)",
+            stars,
+            if stars == 1 { "" } else { "s" },
+            popularimeter.rating
+        )),
+        None => result.push_str("not rated"),
+    }
+    if let Some(counter) = &popularimeter.counter {
+        result.push_str(&format!(", played {} times", counter));
+    }
+    result
+}
+
+fn format_encapsulated_object(object: &EncapsulatedObject) -> String {
+    let mut result = object.mime_type.clone();
+    if !object.filename.is_empty() {
+        result.push_str(&format!(" \"{}\"", object.filename));
+    }
+    result.push_str(&format!(" ({} bytes)", object.data.len()));
+    if !object.description.is_empty() {
+        result.push_str(&format!(": \"{}\"", object.description));
+    }
+    result
+}
+
+fn format_relative_volume(relative_volume: &RelativeVolume) -> String {
+    let channels: Vec<String> = relative_volume
+        .channels
+        .iter()
+        .map(|channel| {
+            let mut result = format!("{} {}", channel.channel.name(), format_gain(channel.gain()));
+            if let Some(peak) = channel.peak_ratio() {
+                result.push_str(&format!(", peak {:.6}", peak));
+            }
+            result
+        })
+        .collect();
+    format!(
+        "\"{}\": {}",
+        relative_volume.identification,
+        channels.join("; ")
+    )
+}
+
+fn format_picture(picture: &Picture) -> String {
+    let mut result = format!("{} ({}", picture.picture_type, picture.mime_type);
+    if let Some((width, height)) = picture.dimensions() {
+        result.push_str(&format!(", {}x{}", width, height));
+    }
+    result.push_str(&format!(", {} bytes)", picture.data.len()));
+    if !picture.description.is_empty() {
+        result.push_str(&format!(" \"{}\"", picture.description));
+    }
+    result
+}
+
+/// Whether frames with this ID hold a [`Comment`].
+pub fn is_comment_frame_id(frame_id: &str) -> bool {
+    frame_id == "COMM" || frame_id == "USLT"
+}
+
+/// Binary data is shown as is if it's printable text, or as its size otherwise.
+fn format_binary(data: &[u8]) -> String {
+    match std::str::from_utf8(data) {
+        Ok(text) if !text.chars().any(char::is_control) => text.to_string(),
+        _ => format!("<{} bytes of binary data>", data.len()),
+    }
+}
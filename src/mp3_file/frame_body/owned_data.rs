@@ -0,0 +1,58 @@
+use crate::mp3_file::text_encoding::TextEncoding;
+
+/// Unique file identifiers can't be longer than this.
+pub const MAX_UNIQUE_FILE_IDENTIFIER_LENGTH: usize = 64;
+
+/// The body of unique file identifier (`UFID`) and private (`PRIV`) frames, which share the
+/// same layout: binary data that only the owner knows how to interpret. A tag may have several
+/// of them, as long as no two share the same owner.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedData {
+    /// Identifies who wrote the data, usually with a URL or an email.
+    pub owner: String,
+    /// For `UFID` frames, an identifier of at most
+    /// [`MAX_UNIQUE_FILE_IDENTIFIER_LENGTH`] bytes in the owner's database.
+    pub data: Vec<u8>,
+}
+
+impl OwnedData {
+    /// Decodes the data of a `UFID` or `PRIV` frame. Returns `None` if the owner is not
+    /// terminated.
+    pub fn from_bytes(data: &[u8]) -> Option<OwnedData> {
+        let end = data.iter().position(|&byte| byte == 0)?;
+        Some(OwnedData {
+            owner: TextEncoding::Latin1.decode(&data[..end]),
+            data: data[end + 1..].to_vec(),
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = TextEncoding::Latin1.encode(&self.owner);
+        data.push(0);
+        data.extend(&self.data);
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn owned_data_round_trips() {
+        let data = b"http://musicbrainz.org\0\x12\x34\0\xFF";
+        let owned_data = OwnedData::from_bytes(data).unwrap();
+        assert_eq!(owned_data.owner, "http://musicbrainz.org");
+        assert_eq!(owned_data.data, [0x12, 0x34, 0, 0xFF]);
+        assert_eq!(owned_data.to_bytes(), data);
+
+        let owned_data = OwnedData::from_bytes(b"\0").unwrap();
+        assert_eq!((owned_data.owner.as_str(), owned_data.data.len()), ("", 0));
+    }
+
+    #[test]
+    fn owned_data_without_a_terminated_owner_is_invalid() {
+        assert_eq!(OwnedData::from_bytes(b"owner"), None);
+        assert_eq!(OwnedData::from_bytes(b""), None);
+    }
+}
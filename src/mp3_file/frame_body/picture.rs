@@ -0,0 +1,343 @@
+use std::fmt;
+
+use crate::mp3_file::text_encoding::TextEncoding;
+
+/// What an attached picture depicts, as defined by the ID3v2 specs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PictureType {
+    Other = 0x00,
+    /// 32x32 pixels, PNG only
+    FileIcon = 0x01,
+    OtherFileIcon = 0x02,
+    FrontCover = 0x03,
+    BackCover = 0x04,
+    LeafletPage = 0x05,
+    /// e.g. the label side of a CD
+    Media = 0x06,
+    LeadArtist = 0x07,
+    Artist = 0x08,
+    Conductor = 0x09,
+    Band = 0x0A,
+    Composer = 0x0B,
+    Lyricist = 0x0C,
+    RecordingLocation = 0x0D,
+    DuringRecording = 0x0E,
+    DuringPerformance = 0x0F,
+    /// A movie or video screen capture
+    ScreenCapture = 0x10,
+    BrightColouredFish = 0x11,
+    Illustration = 0x12,
+    BandLogotype = 0x13,
+    PublisherLogotype = 0x14,
+}
+
+/// Every picture type, in the order of their byte values, along with the name used to choose
+/// them on the command line.
+pub const PICTURE_TYPES: [(PictureType, &str); 21] = [
+    (PictureType::Other, "other"),
+    (PictureType::FileIcon, "file-icon"),
+    (PictureType::OtherFileIcon, "other-file-icon"),
+    (PictureType::FrontCover, "front-cover"),
+    (PictureType::BackCover, "back-cover"),
+    (PictureType::LeafletPage, "leaflet-page"),
+    (PictureType::Media, "media"),
+    (PictureType::LeadArtist, "lead-artist"),
+    (PictureType::Artist, "artist"),
+    (PictureType::Conductor, "conductor"),
+    (PictureType::Band, "band"),
+    (PictureType::Composer, "composer"),
+    (PictureType::Lyricist, "lyricist"),
+    (PictureType::RecordingLocation, "recording-location"),
+    (PictureType::DuringRecording, "during-recording"),
+    (PictureType::DuringPerformance, "during-performance"),
+    (PictureType::ScreenCapture, "screen-capture"),
+    (PictureType::BrightColouredFish, "bright-coloured-fish"),
+    (PictureType::Illustration, "illustration"),
+    (PictureType::BandLogotype, "band-logotype"),
+    (PictureType::PublisherLogotype, "publisher-logotype"),
+];
+
+impl PictureType {
+    pub fn from_byte(byte: u8) -> Option<PictureType> {
+        PICTURE_TYPES
+            .get(byte as usize)
+            .map(|(picture_type, _)| *picture_type)
+    }
+
+    /// Parses the names in [`PICTURE_TYPES`].
+    pub fn from_name(name: &str) -> Option<PictureType> {
+        PICTURE_TYPES
+            .iter()
+            .find(|(_, type_name)| *type_name == name)
+            .map(|(picture_type, _)| *picture_type)
+    }
+
+    /// The name of the picture type in [`PICTURE_TYPES`].
+    pub fn name(&self) -> &'static str {
+        PICTURE_TYPES[*self as usize].1
+    }
+}
+
+impl fmt::Display for PictureType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The body of an attached picture frame (`APIC`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Picture {
+    /// The encoding of the description.
+    pub encoding: TextEncoding,
+    pub mime_type: String,
+    pub picture_type: PictureType,
+    pub description: String,
+    /// The image file itself.
+    pub data: Vec<u8>,
+}
+
+impl Picture {
+    /// A picture whose MIME type is detected from the image's magic bytes. Returns `None` if
+    /// the image format isn't recognized.
+    pub fn from_image(
+        data: Vec<u8>,
+        picture_type: PictureType,
+        description: String,
+        major_version: u8,
+    ) -> Option<Picture> {
+        let mime_type = sniff_mime_type(&data)?.to_string();
+        let encoding = TextEncoding::default_for(&[&description], major_version);
+        Some(Picture {
+            encoding,
+            mime_type,
+            picture_type,
+            description,
+            data,
+        })
+    }
+
+    /// Decodes the data of an `APIC` frame. Returns `None` if the encoding byte or the
+    /// picture type is invalid.
+    pub fn from_bytes(data: &[u8]) -> Option<Picture> {
+        let (&encoding_byte, rest) = data.split_first()?;
+        let encoding = TextEncoding::from_byte(encoding_byte)?;
+        let (mime_type, rest) = TextEncoding::Latin1.split_terminated(rest);
+        let (&picture_type, rest) = rest.split_first()?;
+        let (description, image) = encoding.split_terminated(rest);
+        Some(Picture {
+            encoding,
+            mime_type: TextEncoding::Latin1.decode(mime_type),
+            picture_type: PictureType::from_byte(picture_type)?,
+            description: encoding.decode(description),
+            data: image.to_vec(),
+        })
+    }
+
+    /// Encodes the frame for a tag of the given version. Encodings the version doesn't support
+    /// are replaced by UTF-16.
+    pub fn to_bytes(&self, major_version: u8) -> Vec<u8> {
+        let encoding = self.encoding.or_supported_by(major_version);
+        let mut data = vec![encoding as u8];
+        data.extend(TextEncoding::Latin1.encode(&self.mime_type));
+        data.push(0);
+        data.push(self.picture_type as u8);
+        data.extend(encoding.encode(&self.description));
+        data.extend(encoding.terminator());
+        data.extend(&self.data);
+        data
+    }
+
+    /// The usual file extension for the picture's MIME type.
+    pub fn extension(&self) -> &str {
+        match self.mime_type.to_lowercase().as_str() {
+            "image/jpeg" | "image/jpg" | "jpg" => "jpg",
+            "image/png" | "png" => "png",
+            "image/gif" => "gif",
+            "image/bmp" => "bmp",
+            "image/webp" => "webp",
+            _ => "bin",
+        }
+    }
+
+    /// The width and height of the image in pixels, read from its header. Returns `None` for
+    /// formats other than PNG, JPEG and GIF, or if the header is malformed.
+    pub fn dimensions(&self) -> Option<(u32, u32)> {
+        let data = &self.data;
+        match sniff_mime_type(data)? {
+            "image/png" => {
+                let width = u32::from_be_bytes(data.get(16..20)?.try_into().ok()?);
+                let height = u32::from_be_bytes(data.get(20..24)?.try_into().ok()?);
+                Some((width, height))
+            }
+            "image/gif" => {
+                let width = u16::from_le_bytes(data.get(6..8)?.try_into().ok()?);
+                let height = u16::from_le_bytes(data.get(8..10)?.try_into().ok()?);
+                Some((width as u32, height as u32))
+            }
+            "image/jpeg" => jpeg_dimensions(data),
+            _ => None,
+        }
+    }
+}
+
+/// Detects the MIME type of an image from its magic bytes.
+pub fn sniff_mime_type(data: &[u8]) -> Option<&'static str> {
+    match data {
+        [0xFF, 0xD8, 0xFF, ..] => Some("image/jpeg"),
+        [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, ..] => Some("image/png"),
+        [b'G', b'I', b'F', b'8', b'7' | b'9', b'a', ..] => Some("image/gif"),
+        [b'B', b'M', ..] => Some("image/bmp"),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Some("image/webp"),
+        _ => None,
+    }
+}
+
+/// JPEG files are a list of segments, each starting with 0xFF and a marker byte. The
+/// dimensions are in the start of frame segment, whose marker is one of 0xC0 to 0xCF, except
+/// 0xC4, 0xC8 and 0xCC.
+fn jpeg_dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    let mut position = 2;
+    loop {
+        let marker = *data.get(position + 1)?;
+        if data[position] != 0xFF {
+            return None;
+        }
+        // Fill bytes and markers without a length
+        if marker == 0xFF {
+            position += 1;
+            continue;
+        }
+        if marker == 0x01 || (0xD0..=0xD9).contains(&marker) {
+            position += 2;
+            continue;
+        }
+        let length = u16::from_be_bytes(data.get(position + 2..position + 4)?.try_into().ok()?);
+        if (0xC0..=0xCF).contains(&marker) && ![0xC4, 0xC8, 0xCC].contains(&marker) {
+            let segment = data.get(position + 5..position + 9)?;
+            let height = u16::from_be_bytes([segment[0], segment[1]]);
+            let width = u16::from_be_bytes([segment[2], segment[3]]);
+            return Some((width as u32, height as u32));
+        }
+        position += 2 + length as usize;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+
+    fn picture(data: Vec<u8>) -> Picture {
+        Picture::from_image(data, PictureType::FrontCover, String::new(), 4).unwrap()
+    }
+
+    /// A PNG signature and the start of its header chunk, up to the width and height.
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut data = PNG_SIGNATURE.to_vec();
+        data.extend([0, 0, 0, 13]);
+        data.extend(b"IHDR");
+        data.extend(width.to_be_bytes());
+        data.extend(height.to_be_bytes());
+        data
+    }
+
+    /// A JPEG with an APP0 segment, then a baseline start of frame segment.
+    fn jpeg(width: u16, height: u16) -> Vec<u8> {
+        let mut data = vec![0xFF, 0xD8, 0xFF, 0xE0, 0, 4, b'J', b'F'];
+        data.extend([0xFF, 0xC0, 0, 11, 8]);
+        data.extend(height.to_be_bytes());
+        data.extend(width.to_be_bytes());
+        data
+    }
+
+    #[test]
+    fn mime_types_are_sniffed_from_magic_bytes() {
+        assert_eq!(
+            sniff_mime_type(&[0xFF, 0xD8, 0xFF, 0xE0]),
+            Some("image/jpeg")
+        );
+        assert_eq!(sniff_mime_type(&PNG_SIGNATURE), Some("image/png"));
+        assert_eq!(sniff_mime_type(b"GIF89a"), Some("image/gif"));
+        assert_eq!(sniff_mime_type(b"GIF87a"), Some("image/gif"));
+        assert_eq!(sniff_mime_type(b"BM"), Some("image/bmp"));
+        assert_eq!(sniff_mime_type(b"RIFF\0\0\0\0WEBPVP8 "), Some("image/webp"));
+
+        // Truncated headers
+        assert_eq!(sniff_mime_type(&[]), None);
+        assert_eq!(sniff_mime_type(&[0xFF, 0xD8]), None);
+        assert_eq!(sniff_mime_type(&PNG_SIGNATURE[..7]), None);
+        assert_eq!(sniff_mime_type(b"GIF8"), None);
+        assert_eq!(sniff_mime_type(b"RIFF\0\0\0\0WEB"), None);
+        assert_eq!(sniff_mime_type(b"RIFF\0\0\0\0WAVE"), None);
+        assert!(
+            Picture::from_image(b"text".to_vec(), PictureType::Other, String::new(), 4).is_none()
+        );
+    }
+
+    #[test]
+    fn dimensions_are_read_from_the_image_header() {
+        assert_eq!(picture(png(640, 480)).dimensions(), Some((640, 480)));
+        assert_eq!(
+            picture(b"GIF89a\x80\x02\xE0\x01".to_vec()).dimensions(),
+            Some((640, 480))
+        );
+        assert_eq!(picture(jpeg(640, 480)).dimensions(), Some((640, 480)));
+        // Fill bytes and markers without a length before the start of frame
+        let mut data = vec![0xFF, 0xD8, 0xFF, 0xFF, 0xD0];
+        data.extend(&jpeg(1, 2)[2..]);
+        assert_eq!(picture(data).dimensions(), Some((1, 2)));
+        assert_eq!(picture(b"BM".to_vec()).dimensions(), None);
+    }
+
+    #[test]
+    fn dimensions_of_truncated_headers_are_unknown() {
+        let png = png(640, 480);
+        for length in 8..png.len() {
+            assert_eq!(picture(png[..length].to_vec()).dimensions(), None);
+        }
+        assert_eq!(picture(b"GIF89a\x80\x02\xE0".to_vec()).dimensions(), None);
+        let jpeg = jpeg(640, 480);
+        for length in 3..jpeg.len() {
+            assert_eq!(picture(jpeg[..length].to_vec()).dimensions(), None);
+        }
+        // A segment that isn't started by 0xFF
+        assert_eq!(
+            picture(vec![0xFF, 0xD8, 0xFF, 0xE0, 0, 2, 0x00, 0xC0]).dimensions(),
+            None
+        );
+    }
+
+    #[test]
+    fn pictures_round_trip() {
+        let data = b"\x01image/png\0\x03\xFF\xFEC\0\0\0\x89PNG";
+        let picture = Picture::from_bytes(data).unwrap();
+        assert_eq!(picture.encoding, TextEncoding::Utf16);
+        assert_eq!(picture.mime_type, "image/png");
+        assert_eq!(picture.picture_type, PictureType::FrontCover);
+        assert_eq!(picture.description, "C");
+        assert_eq!(picture.data, b"\x89PNG");
+        assert_eq!(picture.extension(), "png");
+        assert_eq!(picture.to_bytes(3), data);
+
+        // ID3v2.3 has no UTF-8, so it's written as UTF-16
+        let utf8 = Picture {
+            encoding: TextEncoding::Utf8,
+            ..picture.clone()
+        };
+        assert_eq!(utf8.to_bytes(4)[..16], *b"\x03image/png\0\x03C\0\x89P");
+        assert_eq!(utf8.to_bytes(3), data);
+    }
+
+    #[test]
+    fn pictures_with_an_unknown_type_or_encoding_are_invalid() {
+        assert_eq!(Picture::from_bytes(b"\0image/png\0\x15\0data"), None);
+        assert_eq!(Picture::from_bytes(b"\x04image/png\0\x03\0data"), None);
+        assert_eq!(Picture::from_bytes(b"\0image/png\0"), None);
+        assert_eq!(
+            PictureType::from_name("front-cover"),
+            Some(PictureType::FrontCover)
+        );
+        assert_eq!(PictureType::from_name("cover"), None);
+    }
+}
@@ -0,0 +1,250 @@
+use std::fmt;
+
+use crate::mp3_file::text_encoding::TextEncoding;
+
+/// The rating byte written for 1 to 5 stars. This is the mapping used by Windows Media Player,
+/// which most other players follow.
+const STAR_RATINGS: [u8; 5] = [1, 64, 128, 196, 255];
+
+/// The body of popularimeter frames (`POPM`): how much the user identified by an email likes
+/// the file, and how many times they played it. A tag may have several of them, as long as no
+/// two share the same email.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Popularimeter {
+    /// Identifies the user or the program that set the rating.
+    pub email: String,
+    /// From 1 (worst) to 255 (best). 0 means the file is not rated.
+    pub rating: u8,
+    /// How many times the file was played. The counter is optional in the frame.
+    pub counter: Option<PlayCounter>,
+}
+
+impl Popularimeter {
+    /// Decodes the data of a `POPM` frame. Returns `None` if the rating is missing.
+    pub fn from_bytes(data: &[u8]) -> Option<Popularimeter> {
+        let (email, rest) = TextEncoding::Latin1.split_terminated(data);
+        let (&rating, counter) = rest.split_first()?;
+        let counter = (!counter.is_empty()).then(|| PlayCounter::from_bytes(counter));
+        Some(Popularimeter {
+            email: TextEncoding::Latin1.decode(email),
+            rating,
+            counter,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = TextEncoding::Latin1.encode(&self.email);
+        data.push(0);
+        data.push(self.rating);
+        if let Some(counter) = &self.counter {
+            data.extend(counter.to_bytes());
+        }
+        data
+    }
+
+    /// The rating as 1 to 5 stars, or `None` if the file is not rated.
+    pub fn stars(&self) -> Option<u8> {
+        rating_to_stars(self.rating)
+    }
+}
+
+/// Converts a rating byte to 1 to 5 stars. Each number of stars covers the bytes closest to
+/// the one written for it, so ratings written by players with slightly different mappings
+/// are read correctly. Returns `None` for 0, which means not rated.
+pub fn rating_to_stars(rating: u8) -> Option<u8> {
+    match rating {
+        0 => None,
+        1..=31 => Some(1),
+        32..=95 => Some(2),
+        96..=159 => Some(3),
+        160..=223 => Some(4),
+        224..=255 => Some(5),
+    }
+}
+
+/// Converts 1 to 5 stars to the rating byte used by most players. 0 stars means not rated, and
+/// more than 5 stars is the same as 5.
+pub fn stars_to_rating(stars: u8) -> u8 {
+    match stars {
+        0 => 0,
+        stars => STAR_RATINGS[(stars.min(5) - 1) as usize],
+    }
+}
+
+/// How many times a file was played, as kept by play counters (`PCNT`) and popularimeters.
+/// Counters are big endian integers of at least 4 bytes, which grow by one byte whenever they
+/// would overflow, so they have no maximum.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PlayCounter {
+    /// Big endian, without leading zeros, so empty for 0.
+    bytes: Vec<u8>,
+}
+
+impl PlayCounter {
+    /// Decodes a counter of any width.
+    pub fn from_bytes(bytes: &[u8]) -> PlayCounter {
+        let first_significant = bytes
+            .iter()
+            .position(|&byte| byte != 0)
+            .unwrap_or(bytes.len());
+        PlayCounter {
+            bytes: bytes[first_significant..].to_vec(),
+        }
+    }
+
+    /// Encodes the counter with as few bytes as possible, but never less than 4.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![0; 4usize.saturating_sub(self.bytes.len())];
+        bytes.extend(&self.bytes);
+        bytes
+    }
+
+    /// Counts `plays` more plays, adding bytes to the counter as needed.
+    pub fn add(&mut self, plays: u64) {
+        let mut carry = plays as u128;
+        for byte in self.bytes.iter_mut().rev() {
+            if carry == 0 {
+                return;
+            }
+            let sum = *byte as u128 + carry;
+            *byte = sum as u8;
+            carry = sum >> 8;
+        }
+        while carry != 0 {
+            self.bytes.insert(0, carry as u8);
+            carry >>= 8;
+        }
+    }
+
+    /// The counter as an integer, or `None` if it doesn't fit in 64 bits.
+    pub fn to_u64(&self) -> Option<u64> {
+        if self.bytes.len() > 8 {
+            return None;
+        }
+        Some(
+            self.bytes
+                .iter()
+                .fold(0u64, |counter, &byte| counter << 8 | byte as u64),
+        )
+    }
+}
+
+impl From<u64> for PlayCounter {
+    fn from(counter: u64) -> PlayCounter {
+        PlayCounter::from_bytes(&counter.to_be_bytes())
+    }
+}
+
+impl fmt::Display for PlayCounter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(counter) = self.to_u64() {
+            return write!(f, "{}", counter);
+        }
+        // Long division by 10, one byte at a time
+        let mut quotient = self.bytes.clone();
+        let mut digits = Vec::new();
+        while !quotient.is_empty() {
+            let mut remainder = 0u16;
+            for byte in quotient.iter_mut() {
+                let value = remainder << 8 | *byte as u16;
+                *byte = (value / 10) as u8;
+                remainder = value % 10;
+            }
+            digits.push(char::from(b'0' + remainder as u8));
+            let first_significant = quotient
+                .iter()
+                .position(|&byte| byte != 0)
+                .unwrap_or(quotient.len());
+            quotient.drain(..first_significant);
+        }
+        f.write_str(&digits.iter().rev().collect::<String>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn popularimeters_round_trip() {
+        let data = b"someone@example.com\0\xC4\0\0\x01\x00";
+        let popularimeter = Popularimeter::from_bytes(data).unwrap();
+        assert_eq!(popularimeter.email, "someone@example.com");
+        assert_eq!(popularimeter.stars(), Some(4));
+        assert_eq!(popularimeter.counter, Some(PlayCounter::from(256)));
+        assert_eq!(popularimeter.to_bytes(), data);
+
+        // The counter is optional, the rating isn't
+        let popularimeter = Popularimeter::from_bytes(b"\0\0").unwrap();
+        assert_eq!(
+            (popularimeter.stars(), &popularimeter.counter),
+            (None, &None)
+        );
+        assert_eq!(popularimeter.to_bytes(), b"\0\0");
+        assert_eq!(Popularimeter::from_bytes(b"someone@example.com\0"), None);
+    }
+
+    #[test]
+    fn counters_wider_than_64_bits_are_kept() {
+        let data = b"\0\xFF\x01\x02\x03\x04\x05\x06\x07\x08\x09";
+        let popularimeter = Popularimeter::from_bytes(data).unwrap();
+        let counter = popularimeter.counter.as_ref().unwrap();
+        assert_eq!(counter.to_u64(), None);
+        assert_eq!(counter.to_string(), "18591708106338011145");
+        assert_eq!(popularimeter.to_bytes(), data);
+    }
+
+    #[test]
+    fn counters_grow_by_a_byte_when_they_would_overflow() {
+        let mut counter = PlayCounter::from_bytes(&[0xFF; 4]);
+        counter.add(1);
+        assert_eq!(counter.to_bytes(), [1, 0, 0, 0, 0]);
+        assert_eq!(counter.to_u64(), Some(0x1_0000_0000));
+
+        let mut counter = PlayCounter::from(u64::MAX);
+        counter.add(u64::MAX);
+        assert_eq!(
+            counter.to_bytes(),
+            [1, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFE]
+        );
+        assert_eq!(counter.to_string(), "36893488147419103230");
+
+        let mut counter = PlayCounter::default();
+        assert_eq!(counter.to_bytes(), [0; 4]);
+        assert_eq!(counter.to_string(), "0");
+        counter.add(0);
+        assert_eq!(counter, PlayCounter::from(0));
+        counter.add(3);
+        assert_eq!(counter.to_bytes(), [0, 0, 0, 3]);
+        // Leading zeros are not part of the value
+        assert_eq!(PlayCounter::from_bytes(&[0, 0, 0, 0, 0, 3]), counter);
+    }
+
+    #[test]
+    fn ratings_map_to_the_closest_number_of_stars() {
+        let boundaries = [
+            (0, None),
+            (1, Some(1)),
+            (31, Some(1)),
+            (32, Some(2)),
+            (95, Some(2)),
+            (96, Some(3)),
+            (159, Some(3)),
+            (160, Some(4)),
+            (223, Some(4)),
+            (224, Some(5)),
+            (255, Some(5)),
+        ];
+        for (rating, stars) in boundaries {
+            assert_eq!(rating_to_stars(rating), stars, "rating {}", rating);
+        }
+        for stars in 0..=5 {
+            let rating = stars_to_rating(stars);
+            assert_eq!(rating_to_stars(rating), (stars > 0).then_some(stars));
+        }
+        assert_eq!(stars_to_rating(0), 0);
+        assert_eq!(stars_to_rating(1), 1);
+        assert_eq!(stars_to_rating(5), 255);
+        assert_eq!(stars_to_rating(6), 255);
+    }
+}
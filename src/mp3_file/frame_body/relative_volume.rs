@@ -0,0 +1,313 @@
+use crate::mp3_file::text_encoding::TextEncoding;
+
+/// The channel a volume adjustment applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelType {
+    Other = 0,
+    MasterVolume = 1,
+    FrontRight = 2,
+    FrontLeft = 3,
+    BackRight = 4,
+    BackLeft = 5,
+    FrontCentre = 6,
+    BackCentre = 7,
+    Subwoofer = 8,
+}
+
+impl ChannelType {
+    pub fn from_byte(byte: u8) -> Option<ChannelType> {
+        use ChannelType::*;
+        [
+            Other,
+            MasterVolume,
+            FrontRight,
+            FrontLeft,
+            BackRight,
+            BackLeft,
+            FrontCentre,
+            BackCentre,
+            Subwoofer,
+        ]
+        .get(byte as usize)
+        .copied()
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChannelType::Other => "other",
+            ChannelType::MasterVolume => "master volume",
+            ChannelType::FrontRight => "front right",
+            ChannelType::FrontLeft => "front left",
+            ChannelType::BackRight => "back right",
+            ChannelType::BackLeft => "back left",
+            ChannelType::FrontCentre => "front centre",
+            ChannelType::BackCentre => "back centre",
+            ChannelType::Subwoofer => "subwoofer",
+        }
+    }
+}
+
+/// The volume adjustment of a single channel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelAdjustment {
+    pub channel: ChannelType,
+    /// In 1/512 dB, so from -64 dB to +64 dB.
+    pub adjustment: i16,
+    peak_bits: u8,
+    peak: u64,
+}
+
+impl ChannelAdjustment {
+    /// An adjustment with a peak of `peak_bits` bits, 0 if there's no peak. Returns `None` if
+    /// there are more than 64 bits, or if `peak` doesn't fit in them.
+    pub fn new(
+        channel: ChannelType,
+        adjustment: i16,
+        peak_bits: u8,
+        peak: u64,
+    ) -> Option<ChannelAdjustment> {
+        if peak_bits > 64 || (peak_bits < 64 && peak >> peak_bits != 0) {
+            return None;
+        }
+        Some(ChannelAdjustment {
+            channel,
+            adjustment,
+            peak_bits,
+            peak,
+        })
+    }
+
+    /// An adjustment of `gain` dB, rounded to 1/512 dB and limited to ±64 dB. The peak, a
+    /// fraction of full scale, is stored with 16 bits.
+    pub fn from_db(channel: ChannelType, gain: f64, peak: Option<f64>) -> ChannelAdjustment {
+        let adjustment = (gain * 512.0)
+            .round()
+            .clamp(i16::MIN as f64, i16::MAX as f64) as i16;
+        match peak {
+            Some(peak) => ChannelAdjustment {
+                channel,
+                adjustment,
+                peak_bits: 16,
+                peak: (peak * 32768.0).round().clamp(0.0, u16::MAX as f64) as u64,
+            },
+            None => ChannelAdjustment {
+                channel,
+                adjustment,
+                peak_bits: 0,
+                peak: 0,
+            },
+        }
+    }
+
+    /// How many bits the peak has. 0 if there's no peak.
+    pub fn peak_bits(&self) -> u8 {
+        self.peak_bits
+    }
+
+    /// The highest absolute sample value of the channel, with [`Self::peak_bits`] bits.
+    pub fn peak(&self) -> u64 {
+        self.peak
+    }
+
+    /// The adjustment in dB.
+    pub fn gain(&self) -> f64 {
+        self.adjustment as f64 / 512.0
+    }
+
+    /// The peak as a fraction of full scale, which is half the range of `peak_bits` bits, as
+    /// for signed samples. `None` if there's no peak.
+    pub fn peak_ratio(&self) -> Option<f64> {
+        if self.peak_bits == 0 {
+            return None;
+        }
+        Some(self.peak as f64 / 2f64.powi(self.peak_bits as i32 - 1))
+    }
+}
+
+/// The body of relative volume adjustment frames (`RVA2`). A tag may have several of them, as
+/// long as no two share the same identification.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelativeVolume {
+    /// What the adjustment is for. ReplayGain uses `track` and `album`.
+    pub identification: String,
+    pub channels: Vec<ChannelAdjustment>,
+}
+
+impl RelativeVolume {
+    /// Decodes the data of an `RVA2` frame. Returns `None` if a channel is truncated or of an
+    /// unknown type, or if a peak has more than 64 bits or more bits than it says.
+    pub fn from_bytes(data: &[u8]) -> Option<RelativeVolume> {
+        let (identification, mut rest) = TextEncoding::Latin1.split_terminated(data);
+        let mut channels = Vec::new();
+        while !rest.is_empty() {
+            let channel = ChannelType::from_byte(rest[0])?;
+            let adjustment = i16::from_be_bytes(rest.get(1..3)?.try_into().ok()?);
+            let peak_bits = *rest.get(3)?;
+            if peak_bits > 64 {
+                return None;
+            }
+            let peak_length = (peak_bits as usize).div_ceil(8);
+            let peak = rest
+                .get(4..4 + peak_length)?
+                .iter()
+                .fold(0u64, |peak, &byte| peak << 8 | byte as u64);
+            channels.push(ChannelAdjustment::new(
+                channel, adjustment, peak_bits, peak,
+            )?);
+            rest = &rest[4 + peak_length..];
+        }
+        Some(RelativeVolume {
+            identification: TextEncoding::Latin1.decode(identification),
+            channels,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = TextEncoding::Latin1.encode(&self.identification);
+        data.push(0);
+        for channel in self.channels.iter() {
+            data.push(channel.channel as u8);
+            data.extend(channel.adjustment.to_be_bytes());
+            data.push(channel.peak_bits);
+            let peak_length = (channel.peak_bits as usize).div_ceil(8);
+            data.extend(&channel.peak.to_be_bytes()[8 - peak_length..]);
+        }
+        data
+    }
+
+    /// The adjustment of the master volume.
+    pub fn master(&self) -> Option<&ChannelAdjustment> {
+        self.channels
+            .iter()
+            .find(|channel| channel.channel == ChannelType::MasterVolume)
+    }
+}
+
+/// Whether a ReplayGain value applies to a single track or to the whole album.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GainScope {
+    Track,
+    Album,
+}
+
+impl GainScope {
+    /// The identification of the scope's `RVA2` frame, as written by most taggers.
+    pub fn name(&self) -> &'static str {
+        match self {
+            GainScope::Track => "track",
+            GainScope::Album => "album",
+        }
+    }
+
+    /// The description of the scope's `TXXX` gain frame, e.g. `REPLAYGAIN_TRACK_GAIN`.
+    pub fn gain_description(&self) -> String {
+        format!("REPLAYGAIN_{}_GAIN", self.name().to_uppercase())
+    }
+
+    /// The description of the scope's `TXXX` peak frame, e.g. `REPLAYGAIN_TRACK_PEAK`.
+    pub fn peak_description(&self) -> String {
+        format!("REPLAYGAIN_{}_PEAK", self.name().to_uppercase())
+    }
+}
+
+/// A ReplayGain value: how much the volume should change so the audio plays at the reference
+/// loudness.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReplayGain {
+    /// In dB.
+    pub gain: f64,
+    /// The highest sample value, as a fraction of full scale.
+    pub peak: Option<f64>,
+}
+
+/// Parses gains written like `-6.50 dB`. The unit is optional.
+pub(crate) fn parse_gain(text: &str) -> Option<f64> {
+    let text = text.trim();
+    let text = match text.len().checked_sub(2) {
+        Some(unit_start)
+            if text.is_char_boundary(unit_start)
+                && text[unit_start..].eq_ignore_ascii_case("db") =>
+        {
+            &text[..unit_start]
+        }
+        _ => text,
+    };
+    text.trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|gain| gain.is_finite())
+}
+
+pub(crate) fn format_gain(gain: f64) -> String {
+    format!("{:.2} dB", gain)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relative_volumes_round_trip() {
+        let data = b"track\0\x01\xFB\x00\x10\x80\x00\x02\x00\x20\x00";
+        let relative_volume = RelativeVolume::from_bytes(data).unwrap();
+        assert_eq!(relative_volume.identification, "track");
+        let master = relative_volume.master().unwrap();
+        assert_eq!(master.gain(), -2.5);
+        assert_eq!((master.peak_bits(), master.peak()), (16, 0x8000));
+        assert_eq!(master.peak_ratio(), Some(1.0));
+        let front_right = &relative_volume.channels[1];
+        assert_eq!(front_right.channel, ChannelType::FrontRight);
+        assert_eq!(front_right.gain(), 0.0625);
+        assert_eq!(front_right.peak_ratio(), None);
+        assert_eq!(relative_volume.to_bytes(), data);
+    }
+
+    #[test]
+    fn peaks_of_up_to_64_bits_are_kept() {
+        let peak = ChannelAdjustment::new(ChannelType::MasterVolume, 0, 64, u64::MAX).unwrap();
+        let relative_volume = RelativeVolume {
+            identification: "album".to_string(),
+            channels: vec![peak],
+        };
+        let data = relative_volume.to_bytes();
+        assert_eq!(data[10..], [0xFF; 8]);
+        assert_eq!(RelativeVolume::from_bytes(&data), Some(relative_volume));
+
+        // Peaks whose bits don't fill their last byte are right aligned
+        let peak = ChannelAdjustment::new(ChannelType::MasterVolume, 0, 12, 0xABC).unwrap();
+        let relative_volume = RelativeVolume {
+            identification: String::new(),
+            channels: vec![peak],
+        };
+        assert_eq!(relative_volume.to_bytes(), b"\0\x01\0\0\x0C\x0A\xBC");
+    }
+
+    #[test]
+    fn peaks_must_fit_in_their_bits() {
+        assert!(ChannelAdjustment::new(ChannelType::MasterVolume, 0, 65, 0).is_none());
+        assert!(ChannelAdjustment::new(ChannelType::MasterVolume, 0, 8, 0x100).is_none());
+        assert!(ChannelAdjustment::new(ChannelType::MasterVolume, 0, 0, 1).is_none());
+        assert!(
+            RelativeVolume::from_bytes(b"\0\x01\0\0\x41\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF")
+                .is_none()
+        );
+        assert!(RelativeVolume::from_bytes(b"\0\x01\0\0\x04\xFF").is_none());
+        // Truncated peaks and unknown channels
+        assert!(RelativeVolume::from_bytes(b"\0\x01\0\0\x10\xFF").is_none());
+        assert!(RelativeVolume::from_bytes(b"\0\x09\0\0\0").is_none());
+    }
+
+    #[test]
+    fn gains_are_rounded_and_limited() {
+        let adjustment = ChannelAdjustment::from_db(ChannelType::MasterVolume, -6.5, Some(0.5));
+        assert_eq!(adjustment.adjustment, -3328);
+        assert_eq!((adjustment.peak_bits(), adjustment.peak()), (16, 0x4000));
+        let adjustment = ChannelAdjustment::from_db(ChannelType::MasterVolume, 100.0, Some(3.0));
+        assert_eq!(adjustment.adjustment, i16::MAX);
+        assert_eq!(adjustment.peak(), u16::MAX as u64);
+        assert_eq!(parse_gain("-6.50 dB"), Some(-6.5));
+        assert_eq!(parse_gain("+1.5db"), Some(1.5));
+        assert_eq!(parse_gain("inf dB"), None);
+        assert_eq!(format_gain(-6.5), "-6.50 dB");
+    }
+}
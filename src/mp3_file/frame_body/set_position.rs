@@ -0,0 +1,95 @@
+use std::fmt;
+
+/// The value of `TRCK` and `TPOS` frames: the position of a track in its disc, or of a disc in
+/// its set, written as `n` or `n/total`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SetPosition {
+    pub number: u32,
+    pub total: Option<u32>,
+}
+
+impl SetPosition {
+    /// Parses `n` or `n/total`. Positions start at 1, so returns `None` if either isn't a
+    /// positive number. An empty total, as in `3/`, is the same as no total.
+    pub fn parse(text: &str) -> Option<SetPosition> {
+        let parse_number = |text: &str| {
+            let text = text.trim();
+            if text.is_empty() || !text.chars().all(|char| char.is_ascii_digit()) {
+                return None;
+            }
+            text.parse().ok().filter(|&number| number > 0)
+        };
+        let (number, total) = match text.split_once('/') {
+            Some((number, total)) if total.trim().is_empty() => (number, None),
+            Some((number, total)) => (number, Some(total)),
+            None => (text, None),
+        };
+        Some(SetPosition {
+            number: parse_number(number)?,
+            total: match total {
+                Some(total) => Some(parse_number(total)?),
+                None => None,
+            },
+        })
+    }
+
+    /// Writes the position with both numbers padded with zeros to at least `width` digits, as
+    /// in `03/12`.
+    pub fn to_padded_string(&self, width: usize) -> String {
+        match self.total {
+            Some(total) => format!("{:0width$}/{:0width$}", self.number, total),
+            None => format!("{:0width$}", self.number),
+        }
+    }
+}
+
+impl fmt::Display for SetPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_padded_string(0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(number: u32, total: Option<u32>) -> Option<SetPosition> {
+        Some(SetPosition { number, total })
+    }
+
+    #[test]
+    fn positions_may_have_a_total() {
+        assert_eq!(SetPosition::parse("3"), position(3, None));
+        assert_eq!(SetPosition::parse("03/12"), position(3, Some(12)));
+        assert_eq!(SetPosition::parse(" 3 / 12 "), position(3, Some(12)));
+        assert_eq!(SetPosition::parse("3/"), position(3, None));
+    }
+
+    #[test]
+    fn positions_without_a_positive_number_are_invalid() {
+        for text in [
+            "",
+            "/5",
+            "0",
+            "0/5",
+            "3/0",
+            "-1",
+            "3/x",
+            "three",
+            "3/12/1",
+            "4294967296",
+        ] {
+            assert_eq!(SetPosition::parse(text), None, "{}", text);
+        }
+    }
+
+    #[test]
+    fn positions_are_padded_to_a_width() {
+        let track = SetPosition::parse("3/12").unwrap();
+        assert_eq!(track.to_padded_string(2), "03/12");
+        assert_eq!(track.to_padded_string(3), "003/012");
+        assert_eq!(track.to_string(), "3/12");
+        let track = SetPosition::parse("123").unwrap();
+        assert_eq!(track.to_padded_string(2), "123");
+    }
+}
@@ -0,0 +1,337 @@
+use crate::error::{Error, Result};
+use crate::mp3_file::text_encoding::TextEncoding;
+
+/// The unit of the timestamps of a synchronised frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimestampFormat {
+    /// The number of MPEG frames since the start of the audio
+    MpegFrames = 1,
+    Milliseconds = 2,
+}
+
+impl TimestampFormat {
+    pub fn from_byte(byte: u8) -> Option<TimestampFormat> {
+        match byte {
+            1 => Some(TimestampFormat::MpegFrames),
+            2 => Some(TimestampFormat::Milliseconds),
+            _ => None,
+        }
+    }
+}
+
+/// What the text of a `SYLT` frame is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentType {
+    Other = 0,
+    Lyrics = 1,
+    TextTranscription = 2,
+    /// The name of each movement or part, e.g. "Adagio"
+    Movement = 3,
+    /// Events, e.g. "Don Quijote enters the stage"
+    Events = 4,
+    Chord = 5,
+    Trivia = 6,
+    WebpageUrls = 7,
+    ImageUrls = 8,
+}
+
+impl ContentType {
+    pub fn from_byte(byte: u8) -> Option<ContentType> {
+        use ContentType::*;
+        [
+            Other,
+            Lyrics,
+            TextTranscription,
+            Movement,
+            Events,
+            Chord,
+            Trivia,
+            WebpageUrls,
+            ImageUrls,
+        ]
+        .get(byte as usize)
+        .copied()
+    }
+}
+
+/// A piece of text and the moment it should be shown.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SynchronisedText {
+    /// In the unit given by [`SynchronisedLyrics::timestamp_format`].
+    pub timestamp: u32,
+    pub text: String,
+}
+
+/// The body of synchronised lyrics frames (`SYLT`). Like comments, a tag may have several of
+/// them, as long as no two share both language and description.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SynchronisedLyrics {
+    /// The encoding of the description and of every piece of text.
+    pub encoding: TextEncoding,
+    /// A three letter ISO-639-2 language code, such as `eng`.
+    pub language: String,
+    pub timestamp_format: TimestampFormat,
+    pub content_type: ContentType,
+    pub description: String,
+    /// In chronological order.
+    pub lines: Vec<SynchronisedText>,
+}
+
+impl SynchronisedLyrics {
+    /// Decodes the data of a `SYLT` frame. Returns `None` if the encoding, the timestamp format
+    /// or the content type is invalid.
+    pub fn from_bytes(data: &[u8]) -> Option<SynchronisedLyrics> {
+        let (&encoding_byte, rest) = data.split_first()?;
+        let encoding = TextEncoding::from_byte(encoding_byte)?;
+        let language = rest.get(0..3)?;
+        let timestamp_format = TimestampFormat::from_byte(*rest.get(3)?)?;
+        let content_type = ContentType::from_byte(*rest.get(4)?)?;
+        let (description, mut rest) = encoding.split_terminated(&rest[5..]);
+
+        let mut lines = Vec::new();
+        while !rest.is_empty() {
+            let (text, remaining) = encoding.split_terminated(rest);
+            let timestamp = u32::from_be_bytes(remaining.get(0..4)?.try_into().ok()?);
+            lines.push(SynchronisedText {
+                timestamp,
+                text: encoding.decode(text),
+            });
+            rest = &remaining[4..];
+        }
+        Some(SynchronisedLyrics {
+            encoding,
+            language: TextEncoding::Latin1.decode(language),
+            timestamp_format,
+            content_type,
+            description: encoding.decode(description),
+            lines,
+        })
+    }
+
+    /// Encodes the frame for a tag of the given version. Encodings the version doesn't support
+    /// are replaced by UTF-16.
+    pub fn to_bytes(&self, major_version: u8) -> Vec<u8> {
+        let encoding = self.encoding.or_supported_by(major_version);
+        let mut data = vec![encoding as u8];
+        let mut language = TextEncoding::Latin1.encode(&self.language);
+        language.resize(3, b' ');
+        data.extend(language);
+        data.push(self.timestamp_format as u8);
+        data.push(self.content_type as u8);
+        data.extend(encoding.encode(&self.description));
+        data.extend(encoding.terminator());
+        for line in self.lines.iter() {
+            data.extend(encoding.encode(&line.text));
+            data.extend(encoding.terminator());
+            data.extend(line.timestamp.to_be_bytes());
+        }
+        data
+    }
+
+    /// Builds lyrics timed in milliseconds from the contents of an LRC file. Lines with
+    /// several timestamps are repeated at each of them, the `[offset:]` tag is applied, and
+    /// other tags and lines without a timestamp are ignored.
+    pub fn from_lrc(
+        lrc: &str,
+        language: String,
+        description: String,
+        major_version: u8,
+    ) -> SynchronisedLyrics {
+        let mut offset = 0i64;
+        let mut lines = Vec::new();
+        for (line_number, line) in lrc.lines().enumerate() {
+            let mut rest = line.trim();
+            let mut timestamps = Vec::new();
+            while let Some((tag, remaining)) =
+                rest.strip_prefix('[').and_then(|tag| tag.split_once(']'))
+            {
+                if let Some(value) = tag.strip_prefix("offset:") {
+                    match value.trim().parse::<i64>() {
+                        Ok(value) => offset = value,
+                        Err(_) => log::warn!(
+                            "Line {} of the LRC file has an invalid offset \"{}\"",
+                            line_number + 1,
+                            value
+                        ),
+                    }
+                } else if let Some(timestamp) = parse_lrc_timestamp(tag) {
+                    timestamps.push(timestamp);
+                } else if !timestamps.is_empty() {
+                    // Brackets after the timestamps are part of the text
+                    break;
+                }
+                rest = remaining;
+            }
+            for timestamp in timestamps {
+                lines.push((timestamp, rest.to_string()));
+            }
+        }
+
+        // A positive offset makes the lyrics show up sooner
+        let mut lines: Vec<SynchronisedText> = lines
+            .into_iter()
+            .map(|(timestamp, text)| SynchronisedText {
+                timestamp: (timestamp - offset).clamp(0, u32::MAX as i64) as u32,
+                text,
+            })
+            .collect();
+        lines.sort_by_key(|line| line.timestamp);
+
+        let mut texts: Vec<&str> = lines.iter().map(|line| line.text.as_str()).collect();
+        texts.push(&description);
+        SynchronisedLyrics {
+            encoding: TextEncoding::default_for(&texts, major_version),
+            language,
+            timestamp_format: TimestampFormat::Milliseconds,
+            content_type: ContentType::Lyrics,
+            description,
+            lines,
+        }
+    }
+
+    /// Formats the lyrics as an LRC file. Fails if the timestamps are in MPEG frames, since
+    /// converting them to time needs the audio's frame rate.
+    pub fn to_lrc(&self) -> Result<String> {
+        if self.timestamp_format != TimestampFormat::Milliseconds {
+            return Err(Error::UnsupportedTimestampFormat);
+        }
+        let mut lrc = String::new();
+        for line in self.lines.iter() {
+            let centiseconds = (line.timestamp as u64 + 5) / 10;
+            lrc.push_str(&format!(
+                "[{:02}:{:02}.{:02}]{}\n",
+                centiseconds / 6000,
+                centiseconds / 100 % 60,
+                centiseconds % 100,
+                // Some taggers start each line with a line break
+                line.text.trim_matches(|char| char == '\n' || char == '\r')
+            ));
+        }
+        Ok(lrc)
+    }
+}
+
+/// Parses `mm:ss`, `mm:ss.xx` or `mm:ss.xxx` into milliseconds.
+fn parse_lrc_timestamp(tag: &str) -> Option<i64> {
+    let (minutes, seconds) = tag.split_once(':')?;
+    let (seconds, fraction) = seconds.split_once('.').unwrap_or((seconds, ""));
+    let all_digits = |text: &str| text.chars().all(|char| char.is_ascii_digit());
+    if minutes.is_empty() || seconds.is_empty() || !all_digits(minutes) || !all_digits(seconds) {
+        return None;
+    }
+    if !all_digits(fraction) || fraction.len() > 3 {
+        return None;
+    }
+    let fraction_milliseconds = match fraction.len() {
+        0 => 0,
+        length => fraction.parse::<i64>().ok()? * 10i64.pow(3 - length as u32),
+    };
+    Some(
+        minutes.parse::<i64>().ok()? * 60_000
+            + seconds.parse::<i64>().ok()? * 1000
+            + fraction_milliseconds,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timed_lines(lyrics: &SynchronisedLyrics) -> Vec<(u32, &str)> {
+        lyrics
+            .lines
+            .iter()
+            .map(|line| (line.timestamp, line.text.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn lrc_lines_with_several_timestamps_are_repeated() {
+        let lrc = "[ar:Artist]\n\
+                   [00:01.00][00:03.5]Chorus\n\
+                   [00:02.123]Verse [live]\n\
+                   Not timed\n\
+                   [01:00]Last\n";
+        let lyrics = SynchronisedLyrics::from_lrc(lrc, "eng".to_string(), String::new(), 4);
+        assert_eq!(
+            timed_lines(&lyrics),
+            [
+                (1000, "Chorus"),
+                (2123, "Verse [live]"),
+                (3500, "Chorus"),
+                (60_000, "Last")
+            ]
+        );
+        assert_eq!(lyrics.timestamp_format, TimestampFormat::Milliseconds);
+        assert_eq!(lyrics.content_type, ContentType::Lyrics);
+    }
+
+    #[test]
+    fn the_lrc_offset_moves_every_line() {
+        // A positive offset shows the lines sooner, but never before the start
+        let lrc = "[offset:+500]\n[00:00.20]Early\n[00:01.00]Later\n";
+        let lyrics = SynchronisedLyrics::from_lrc(lrc, "eng".to_string(), String::new(), 4);
+        assert_eq!(timed_lines(&lyrics), [(0, "Early"), (500, "Later")]);
+
+        // Wherever it is in the file
+        let lrc = "[00:01.00]Later\n[offset:-250]\n";
+        let lyrics = SynchronisedLyrics::from_lrc(lrc, "eng".to_string(), String::new(), 4);
+        assert_eq!(timed_lines(&lyrics), [(1250, "Later")]);
+
+        // An invalid offset is ignored
+        let lrc = "[offset:soon]\n[00:01.00]Later\n";
+        let lyrics = SynchronisedLyrics::from_lrc(lrc, "eng".to_string(), String::new(), 4);
+        assert_eq!(timed_lines(&lyrics), [(1000, "Later")]);
+    }
+
+    #[test]
+    fn lyrics_are_written_as_lrc_in_centiseconds() {
+        let lrc = "[00:01.00][00:03.5]Chorus\n[00:02.125]Verse\n[00:59.996]\nLast\n";
+        let lyrics = SynchronisedLyrics::from_lrc(lrc, "eng".to_string(), String::new(), 4);
+        assert_eq!(
+            lyrics.to_lrc().unwrap(),
+            "[00:01.00]Chorus\n[00:02.13]Verse\n[00:03.50]Chorus\n[01:00.00]\n"
+        );
+        let lrc = lyrics.to_lrc().unwrap();
+        let read_back = SynchronisedLyrics::from_lrc(&lrc, "eng".to_string(), String::new(), 4);
+        assert_eq!(
+            timed_lines(&read_back)[..3],
+            [(1000, "Chorus"), (2130, "Verse"), (3500, "Chorus")]
+        );
+
+        let frames = SynchronisedLyrics {
+            timestamp_format: TimestampFormat::MpegFrames,
+            ..lyrics
+        };
+        assert!(matches!(
+            frames.to_lrc(),
+            Err(Error::UnsupportedTimestampFormat)
+        ));
+    }
+
+    #[test]
+    fn synchronised_lyrics_round_trip() {
+        let data = b"\0eng\x02\x01desc\0One\0\0\0\x03\xE8Two\0\0\0\x07\xD0";
+        let lyrics = SynchronisedLyrics::from_bytes(data).unwrap();
+        assert_eq!(lyrics.description, "desc");
+        assert_eq!(timed_lines(&lyrics), [(1000, "One"), (2000, "Two")]);
+        assert_eq!(lyrics.to_bytes(3), data);
+
+        let data = b"\x01eng\x01\x05\xFF\xFE\0\0\xFF\xFEA\0\0\0\0\0\0\x10";
+        let lyrics = SynchronisedLyrics::from_bytes(data).unwrap();
+        assert_eq!(lyrics.timestamp_format, TimestampFormat::MpegFrames);
+        assert_eq!(lyrics.content_type, ContentType::Chord);
+        assert_eq!(timed_lines(&lyrics), [(16, "A")]);
+        assert_eq!(lyrics.to_bytes(3), data);
+    }
+
+    #[test]
+    fn synchronised_lyrics_with_a_truncated_timestamp_are_invalid() {
+        assert_eq!(
+            SynchronisedLyrics::from_bytes(b"\0eng\x02\x01\0One\0\0\0\x03"),
+            None
+        );
+        assert_eq!(SynchronisedLyrics::from_bytes(b"\0eng\x03\x01\0"), None);
+        assert_eq!(SynchronisedLyrics::from_bytes(b"\0eng\x02\x09\0"), None);
+    }
+}
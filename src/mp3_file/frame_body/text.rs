@@ -0,0 +1,100 @@
+use crate::mp3_file::text_encoding::TextEncoding;
+
+/// The body of a text information frame: every frame whose ID starts with `T`, except `TXXX`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextFrame {
+    pub encoding: TextEncoding,
+    /// ID3v2.4 allows a frame to hold several null separated values. ID3v2.3 doesn't, so when
+    /// writing an ID3v2.3 tag they are joined with "/".
+    pub values: Vec<String>,
+}
+
+impl TextFrame {
+    pub fn new(encoding: TextEncoding, values: Vec<String>) -> TextFrame {
+        TextFrame { encoding, values }
+    }
+
+    /// A text frame in the best encoding for `values` on a tag of the given version. See
+    /// [`TextEncoding::default_for`].
+    pub fn with_default_encoding(values: Vec<String>, major_version: u8) -> TextFrame {
+        let encoding = TextEncoding::default_for(&values, major_version);
+        TextFrame { encoding, values }
+    }
+
+    /// Decodes the data of a text frame. Returns `None` if the encoding byte is invalid.
+    pub fn from_bytes(data: &[u8]) -> Option<TextFrame> {
+        let (&encoding_byte, rest) = data.split_first()?;
+        let encoding = TextEncoding::from_byte(encoding_byte)?;
+        Some(TextFrame {
+            encoding,
+            values: decode_values(encoding, rest),
+        })
+    }
+
+    /// Encodes the frame for a tag of the given version. Encodings the version doesn't support
+    /// are replaced by UTF-16.
+    pub fn to_bytes(&self, major_version: u8) -> Vec<u8> {
+        let encoding = self.encoding.or_supported_by(major_version);
+        let mut data = vec![encoding as u8];
+        data.extend(encode_values(encoding, &self.values, major_version));
+        data
+    }
+
+    /// The first value of the frame.
+    pub fn text(&self) -> &str {
+        self.values.first().map(String::as_str).unwrap_or_default()
+    }
+}
+
+/// Whether frames with this ID hold a [`TextFrame`].
+pub fn is_text_frame_id(frame_id: &str) -> bool {
+    frame_id.starts_with('T') && frame_id != "TXXX"
+}
+
+/// Decodes a list of null separated values. There's always at least one value.
+pub(super) fn decode_values(encoding: TextEncoding, mut bytes: &[u8]) -> Vec<String> {
+    let mut values = Vec::new();
+    let mut byte_order_mark: &[u8] = &[];
+    // A terminator at the very end doesn't start a new value
+    while !bytes.is_empty() {
+        let (value, remaining) = encoding.split_terminated(bytes);
+        if encoding == TextEncoding::Utf16 {
+            // Some taggers only write the byte order mark on the first value
+            match value {
+                [0xFF, 0xFE, ..] | [0xFE, 0xFF, ..] => byte_order_mark = &value[..2],
+                _ if !byte_order_mark.is_empty() => {
+                    values.push(encoding.decode(&[byte_order_mark, value].concat()));
+                    bytes = remaining;
+                    continue;
+                }
+                _ => (),
+            }
+        }
+        values.push(encoding.decode(value));
+        bytes = remaining;
+    }
+    if values.is_empty() {
+        values.push(String::new());
+    }
+    values
+}
+
+/// Encodes a list of values for a tag of the given version. ID3v2.4 separates them with nulls,
+/// while older versions join them with "/".
+pub(super) fn encode_values(
+    encoding: TextEncoding,
+    values: &[String],
+    major_version: u8,
+) -> Vec<u8> {
+    if major_version < 4 {
+        return encoding.encode(&values.join("/"));
+    }
+    let mut data = Vec::new();
+    for (index, value) in values.iter().enumerate() {
+        if index != 0 {
+            data.extend(encoding.terminator());
+        }
+        data.extend(encoding.encode(value));
+    }
+    data
+}
@@ -0,0 +1,288 @@
+use std::fmt;
+
+/// The ID3v2.4 frames whose values are timestamps.
+pub const TIMESTAMP_FRAME_IDS: [&str; 5] = ["TDEN", "TDOR", "TDRC", "TDRL", "TDTG"];
+
+/// Whether the frame's values are timestamps.
+pub fn is_timestamp_frame_id(frame_id: &str) -> bool {
+    TIMESTAMP_FRAME_IDS.contains(&frame_id)
+}
+
+/// A timestamp of ID3v2.4 time frames, written as `yyyy-MM-ddTHH:mm:ss`. It may stop after any
+/// part, as in `2004-07` or `2004-07-12T18`, so a part is only set when the ones before it are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timestamp {
+    pub year: u16,
+    pub month: Option<u8>,
+    pub day: Option<u8>,
+    pub hour: Option<u8>,
+    pub minute: Option<u8>,
+    pub second: Option<u8>,
+}
+
+impl Timestamp {
+    /// A timestamp with only a year.
+    pub fn from_year(year: u16) -> Timestamp {
+        Timestamp {
+            year,
+            month: None,
+            day: None,
+            hour: None,
+            minute: None,
+            second: None,
+        }
+    }
+
+    /// Parses `yyyy-MM-ddTHH:mm:ss`, stopping after any part. Returns `None` if the text has
+    /// another format or describes a date or time that doesn't exist.
+    pub fn parse(text: &str) -> Option<Timestamp> {
+        let text = text.trim();
+        let mut timestamp = Timestamp::from_year(parse_digits(text.get(0..4)?)?);
+        let mut rest = &text[4..];
+        let separators = ['-', '-', 'T', ':', ':'];
+        for (part, separator) in separators.into_iter().enumerate() {
+            let Some(digits) = rest.strip_prefix(separator) else {
+                break;
+            };
+            let value = parse_digits(digits.get(0..2)?)? as u8;
+            match part {
+                0 => timestamp.month = Some(value),
+                1 => timestamp.day = Some(value),
+                2 => timestamp.hour = Some(value),
+                3 => timestamp.minute = Some(value),
+                _ => timestamp.second = Some(value),
+            }
+            rest = &digits[2..];
+        }
+        (rest.is_empty() && timestamp.is_valid()).then_some(timestamp)
+    }
+
+    /// Builds a timestamp out of the values of the ID3v2.3 frames `TYER` (yyyy), `TDAT` (DDMM)
+    /// and `TIME` (HHMM). Returns `None` if the year is invalid. An invalid date is ignored, and
+    /// so is the time when there's no date.
+    pub fn from_v23(year: &str, date: Option<&str>, time: Option<&str>) -> Option<Timestamp> {
+        let year = year.trim();
+        if year.len() != 4 {
+            return None;
+        }
+        let mut timestamp = Timestamp::from_year(parse_digits(year)?);
+        let Some((day, month)) = date.and_then(parse_pair) else {
+            return Some(timestamp);
+        };
+        let dated = Timestamp {
+            month: Some(month),
+            day: Some(day),
+            ..timestamp
+        };
+        if !dated.is_valid() {
+            return Some(timestamp);
+        }
+        timestamp = dated;
+        if let Some((hour, minute)) = time.and_then(parse_pair) {
+            let timed = Timestamp {
+                hour: Some(hour),
+                minute: Some(minute),
+                ..timestamp
+            };
+            if timed.is_valid() {
+                timestamp = timed;
+            }
+        }
+        Some(timestamp)
+    }
+
+    /// The values of the ID3v2.3 frames `TYER`, `TDAT` and `TIME` for this timestamp. ID3v2.3
+    /// can't hold a month without a day, an hour without minutes, nor seconds, so those are
+    /// left out.
+    pub fn to_v23(&self) -> (String, Option<String>, Option<String>) {
+        let year = format!("{:04}", self.year);
+        let (Some(month), Some(day)) = (self.month, self.day) else {
+            return (year, None, None);
+        };
+        let date = format!("{:02}{:02}", day, month);
+        let time = match (self.hour, self.minute) {
+            (Some(hour), Some(minute)) => Some(format!("{:02}{:02}", hour, minute)),
+            _ => None,
+        };
+        (year, Some(date), time)
+    }
+
+    /// The part of the timestamp that ID3v2.3 tags can hold.
+    pub fn truncated_for_v23(&self) -> Timestamp {
+        let (year, date, time) = self.to_v23();
+        Timestamp::from_v23(&year, date.as_deref(), time.as_deref()).unwrap_or(*self)
+    }
+
+    fn is_valid(&self) -> bool {
+        let parts = [self.month, self.day, self.hour, self.minute, self.second];
+        // A part can't be set when the one before it isn't
+        let set_parts = parts.iter().take_while(|part| part.is_some()).count();
+        if parts[set_parts..].iter().any(Option::is_some) {
+            return false;
+        }
+        let in_range = |part: Option<u8>, range: std::ops::RangeInclusive<u8>| {
+            part.is_none_or(|value| range.contains(&value))
+        };
+        in_range(self.month, 1..=12)
+            && in_range(
+                self.day,
+                1..=days_in_month(self.year, self.month.unwrap_or(1)),
+            )
+            && in_range(self.hour, 0..=23)
+            && in_range(self.minute, 0..=59)
+            && in_range(self.second, 0..=59)
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}", self.year)?;
+        let parts = [
+            ('-', self.month),
+            ('-', self.day),
+            ('T', self.hour),
+            (':', self.minute),
+            (':', self.second),
+        ];
+        for (separator, part) in parts {
+            let Some(value) = part else {
+                break;
+            };
+            write!(f, "{}{:02}", separator, value)?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_digits(text: &str) -> Option<u16> {
+    if text.is_empty() || !text.chars().all(|char| char.is_ascii_digit()) {
+        return None;
+    }
+    text.parse().ok()
+}
+
+/// Splits the four digits of `TDAT` and `TIME` values in two numbers.
+fn parse_pair(text: &str) -> Option<(u8, u8)> {
+    let text = text.trim();
+    if text.len() != 4 {
+        return None;
+    }
+    let first = parse_digits(text.get(0..2)?)? as u8;
+    let second = parse_digits(text.get(2..4)?)? as u8;
+    Some((first, second))
+}
+
+fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => {
+            29
+        }
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamps_may_stop_after_any_part() {
+        for text in [
+            "2004",
+            "2004-07",
+            "2004-07-12",
+            "2004-07-12T18",
+            "2004-07-12T18:30:05",
+        ] {
+            assert_eq!(Timestamp::parse(text).unwrap().to_string(), text);
+        }
+        let timestamp = Timestamp::parse(" 2004-07-12T18:30 ").unwrap();
+        assert_eq!(timestamp.year, 2004);
+        assert_eq!((timestamp.month, timestamp.day), (Some(7), Some(12)));
+        assert_eq!(
+            (timestamp.hour, timestamp.minute, timestamp.second),
+            (Some(18), Some(30), None)
+        );
+    }
+
+    #[test]
+    fn timestamps_of_dates_that_do_not_exist_are_invalid() {
+        let invalid = [
+            "2004-00",
+            "2004-13",
+            "2004-04-31",
+            "2004-07-00",
+            "2003-02-29",
+            "1900-02-29",
+            "2004-07-12T24",
+            "2004-07-12T23:60",
+            "2004-07-12T23:59:60",
+        ];
+        for text in invalid {
+            assert_eq!(Timestamp::parse(text), None, "{}", text);
+        }
+        // Leap years
+        assert!(Timestamp::parse("2004-02-29").is_some());
+        assert!(Timestamp::parse("2000-02-29").is_some());
+    }
+
+    #[test]
+    fn timestamps_in_other_formats_are_invalid() {
+        let invalid = [
+            "",
+            "04",
+            "20041",
+            "year",
+            "2004-7",
+            "2004-07-",
+            "2004/07/12",
+            "2004-07-12 18:30",
+            "2004-07-12T18:30:05Z",
+            "2004-+7",
+        ];
+        for text in invalid {
+            assert_eq!(Timestamp::parse(text), None, "{}", text);
+        }
+    }
+
+    #[test]
+    fn id3v23_dates_are_read_as_far_as_they_are_valid() {
+        let timestamp = Timestamp::from_v23("2004", Some("1207"), Some("1830")).unwrap();
+        assert_eq!(timestamp.to_string(), "2004-07-12T18:30");
+        // An invalid date is ignored, and so is the time without it
+        let timestamp = Timestamp::from_v23("2004", Some("3102"), Some("1830")).unwrap();
+        assert_eq!(timestamp.to_string(), "2004");
+        let timestamp = Timestamp::from_v23("2004", None, Some("1830")).unwrap();
+        assert_eq!(timestamp.to_string(), "2004");
+        let timestamp = Timestamp::from_v23("2004", Some("1207"), Some("2460")).unwrap();
+        assert_eq!(timestamp.to_string(), "2004-07-12");
+        assert_eq!(Timestamp::from_v23("04", None, None), None);
+        assert_eq!(Timestamp::from_v23("2oo4", None, None), None);
+    }
+
+    #[test]
+    fn id3v23_dates_keep_what_they_can_hold() {
+        let timestamp = Timestamp::parse("2004-07-12T18:30:05").unwrap();
+        let v23 = (
+            "2004".to_string(),
+            Some("1207".to_string()),
+            Some("1830".to_string()),
+        );
+        assert_eq!(timestamp.to_v23(), v23);
+        assert_eq!(
+            timestamp.truncated_for_v23().to_string(),
+            "2004-07-12T18:30"
+        );
+        // A month without a day, and an hour without minutes, can't be held
+        let timestamp = Timestamp::parse("2004-07").unwrap();
+        assert_eq!(timestamp.to_v23(), ("2004".to_string(), None, None));
+        assert_eq!(timestamp.truncated_for_v23().to_string(), "2004");
+        let timestamp = Timestamp::parse("2004-07-12T18").unwrap();
+        assert_eq!(
+            timestamp.to_v23(),
+            ("2004".to_string(), Some("1207".to_string()), None)
+        );
+    }
+}
@@ -0,0 +1,178 @@
+use super::text::{decode_values, encode_values};
+use crate::mp3_file::text_encoding::TextEncoding;
+
+/// The body of user defined text frames (`TXXX`). A tag may have several of them, as long as no
+/// two share the same description.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserText {
+    /// The encoding of the description and the values.
+    pub encoding: TextEncoding,
+    pub description: String,
+    /// ID3v2.4 allows a frame to hold several null separated values. ID3v2.3 doesn't, so when
+    /// writing an ID3v2.3 tag they are joined with "/".
+    pub values: Vec<String>,
+}
+
+impl UserText {
+    /// A frame in the best encoding for its description and values on a tag of the given
+    /// version.
+    pub fn new(description: String, values: Vec<String>, major_version: u8) -> UserText {
+        let mut texts = values.clone();
+        texts.push(description.clone());
+        UserText {
+            encoding: TextEncoding::default_for(&texts, major_version),
+            description,
+            values,
+        }
+    }
+
+    /// Decodes the data of a `TXXX` frame. Returns `None` if the encoding byte is invalid.
+    pub fn from_bytes(data: &[u8]) -> Option<UserText> {
+        let (&encoding_byte, rest) = data.split_first()?;
+        let encoding = TextEncoding::from_byte(encoding_byte)?;
+        let (description, rest) = encoding.split_terminated(rest);
+        Some(UserText {
+            encoding,
+            description: encoding.decode(description),
+            values: decode_values(encoding, rest),
+        })
+    }
+
+    /// Encodes the frame for a tag of the given version. Encodings the version doesn't support
+    /// are replaced by UTF-16.
+    pub fn to_bytes(&self, major_version: u8) -> Vec<u8> {
+        let encoding = self.encoding.or_supported_by(major_version);
+        let mut data = vec![encoding as u8];
+        data.extend(encoding.encode(&self.description));
+        data.extend(encoding.terminator());
+        data.extend(encode_values(encoding, &self.values, major_version));
+        data
+    }
+
+    /// The first value of the frame.
+    pub fn text(&self) -> &str {
+        self.values.first().map(String::as_str).unwrap_or_default()
+    }
+}
+
+/// The body of user defined URL link frames (`WXXX`). A tag may have several of them, as long
+/// as no two share the same description.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserUrl {
+    /// The encoding of the description. The URL itself is always ISO-8859-1.
+    pub encoding: TextEncoding,
+    pub description: String,
+    pub url: String,
+}
+
+impl UserUrl {
+    /// A frame in the best encoding for its description on a tag of the given version.
+    pub fn new(description: String, url: String, major_version: u8) -> UserUrl {
+        UserUrl {
+            encoding: TextEncoding::default_for(&[&description], major_version),
+            description,
+            url,
+        }
+    }
+
+    /// Decodes the data of a `WXXX` frame. Returns `None` if the encoding byte is invalid.
+    pub fn from_bytes(data: &[u8]) -> Option<UserUrl> {
+        let (&encoding_byte, rest) = data.split_first()?;
+        let encoding = TextEncoding::from_byte(encoding_byte)?;
+        let (description, rest) = encoding.split_terminated(rest);
+        let (url, _) = TextEncoding::Latin1.split_terminated(rest);
+        Some(UserUrl {
+            encoding,
+            description: encoding.decode(description),
+            url: TextEncoding::Latin1.decode(url),
+        })
+    }
+
+    /// Encodes the frame for a tag of the given version. Encodings the version doesn't support
+    /// are replaced by UTF-16.
+    pub fn to_bytes(&self, major_version: u8) -> Vec<u8> {
+        let encoding = self.encoding.or_supported_by(major_version);
+        let mut data = vec![encoding as u8];
+        data.extend(encoding.encode(&self.description));
+        data.extend(encoding.terminator());
+        data.extend(TextEncoding::Latin1.encode(&self.url));
+        data
+    }
+}
+
+/// Whether frames with this ID are identified by their description.
+pub fn is_user_defined_frame_id(frame_id: &str) -> bool {
+    frame_id == "TXXX" || frame_id == "WXXX"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn user_texts_round_trip() {
+        let data = b"\0REPLAYGAIN_TRACK_GAIN\0-6.50 dB";
+        let user_text = UserText::from_bytes(data).unwrap();
+        assert_eq!(user_text.description, "REPLAYGAIN_TRACK_GAIN");
+        assert_eq!(user_text.text(), "-6.50 dB");
+        assert_eq!(user_text.to_bytes(3), data);
+
+        let data = b"\x01\xFF\xFEd\0\0\0\xFF\xFEv\0";
+        let user_text = UserText::from_bytes(data).unwrap();
+        assert_eq!(
+            (user_text.description.as_str(), user_text.text()),
+            ("d", "v")
+        );
+        assert_eq!(user_text.to_bytes(3), data);
+    }
+
+    #[test]
+    fn user_texts_hold_several_values_in_id3v24_only() {
+        let data = b"\x03artists\0A\0B";
+        let user_text = UserText::from_bytes(data).unwrap();
+        assert_eq!(user_text.values, ["A", "B"]);
+        assert_eq!(user_text.text(), "A");
+        assert_eq!(user_text.to_bytes(4), data);
+        assert_eq!(
+            user_text.to_bytes(3),
+            b"\x01\xFF\xFEa\0r\0t\0i\0s\0t\0s\0\0\0\xFF\xFEA\0/\0B\0"
+        );
+    }
+
+    #[test]
+    fn user_texts_without_a_value_are_empty() {
+        let user_text = UserText::from_bytes(b"\0description").unwrap();
+        assert_eq!(user_text.description, "description");
+        assert_eq!(user_text.text(), "");
+        assert_eq!(UserText::from_bytes(b"\x04d\0v"), None);
+        assert_eq!(UserText::from_bytes(b""), None);
+    }
+
+    #[test]
+    fn user_urls_round_trip() {
+        let data = b"\x01\xFF\xFE\xE9\0\0\0https://example.com";
+        let user_url = UserUrl::from_bytes(data).unwrap();
+        assert_eq!(user_url.description, "é");
+        assert_eq!(user_url.url, "https://example.com");
+        assert_eq!(user_url.to_bytes(3), data);
+
+        // The URL is always ISO-8859-1, and may be terminated
+        let user_url = UserUrl::from_bytes(b"\x03\0https://example.com\0").unwrap();
+        assert_eq!(
+            (user_url.description.as_str(), user_url.url.as_str()),
+            ("", "https://example.com")
+        );
+        assert_eq!(user_url.to_bytes(4), b"\x03\0https://example.com");
+        let user_url = UserUrl::new(String::new(), "https://example.com".to_string(), 3);
+        assert_eq!(user_url.to_bytes(3), b"\0\0https://example.com");
+        assert_eq!(UserUrl::from_bytes(b"\x05\0url"), None);
+    }
+
+    #[test]
+    fn user_defined_frames_are_txxx_and_wxxx() {
+        assert!(is_user_defined_frame_id("TXXX"));
+        assert!(is_user_defined_frame_id("WXXX"));
+        assert!(!is_user_defined_frame_id("TIT2"));
+        assert!(!is_user_defined_frame_id("WOAR"));
+    }
+}
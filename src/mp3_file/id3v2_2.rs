@@ -0,0 +1,138 @@
+//! ID3v2.2 uses three-character frame IDs, 3 byte frame sizes and no frame flags. Frames are
+//! translated to their ID3v2.3 equivalents when read, and back when written.
+
+/// Every ID3v2.2 frame ID with an ID3v2.3 equivalent.
+pub const V22_TO_V23_IDS: [(&str, &str); 62] = [
+    ("BUF", "RBUF"),
+    ("CNT", "PCNT"),
+    ("COM", "COMM"),
+    ("CRA", "AENC"),
+    ("ETC", "ETCO"),
+    ("EQU", "EQUA"),
+    ("GEO", "GEOB"),
+    ("IPL", "IPLS"),
+    ("LNK", "LINK"),
+    ("MCI", "MCDI"),
+    ("MLL", "MLLT"),
+    ("PIC", "APIC"),
+    ("POP", "POPM"),
+    ("REV", "RVRB"),
+    ("RVA", "RVAD"),
+    ("SLT", "SYLT"),
+    ("STC", "SYTC"),
+    ("TAL", "TALB"),
+    ("TBP", "TBPM"),
+    ("TCM", "TCOM"),
+    ("TCO", "TCON"),
+    ("TCR", "TCOP"),
+    ("TDA", "TDAT"),
+    ("TDY", "TDLY"),
+    ("TEN", "TENC"),
+    ("TFT", "TFLT"),
+    ("TIM", "TIME"),
+    ("TKE", "TKEY"),
+    ("TLA", "TLAN"),
+    ("TLE", "TLEN"),
+    ("TMT", "TMED"),
+    ("TOA", "TOPE"),
+    ("TOF", "TOFN"),
+    ("TOL", "TOLY"),
+    ("TOR", "TORY"),
+    ("TOT", "TOAL"),
+    ("TP1", "TPE1"),
+    ("TP2", "TPE2"),
+    ("TP3", "TPE3"),
+    ("TP4", "TPE4"),
+    ("TPA", "TPOS"),
+    ("TPB", "TPUB"),
+    ("TRC", "TSRC"),
+    ("TRD", "TRDA"),
+    ("TRK", "TRCK"),
+    ("TSI", "TSIZ"),
+    ("TSS", "TSSE"),
+    ("TT1", "TIT1"),
+    ("TT2", "TIT2"),
+    ("TT3", "TIT3"),
+    ("TXT", "TEXT"),
+    ("TXX", "TXXX"),
+    ("TYE", "TYER"),
+    ("UFI", "UFID"),
+    ("ULT", "USLT"),
+    ("WAF", "WOAF"),
+    ("WAR", "WOAR"),
+    ("WAS", "WOAS"),
+    ("WCM", "WCOM"),
+    ("WCP", "WCOP"),
+    ("WPB", "WPUB"),
+    ("WXX", "WXXX"),
+];
+
+/// The image formats of ID3v2.2 `PIC` frames and the MIME types used by `APIC` frames.
+const IMAGE_FORMATS: [(&str, &str); 5] = [
+    ("JPG", "image/jpeg"),
+    ("PNG", "image/png"),
+    ("GIF", "image/gif"),
+    ("BMP", "image/bmp"),
+    // A link to the image instead of the image itself
+    ("-->", "-->"),
+];
+
+/// Returns the ID3v2.3 equivalent of the ID3v2.2 frame ID `id`.
+pub fn upgrade_frame_id(id: &str) -> Option<&'static str> {
+    V22_TO_V23_IDS
+        .iter()
+        .find(|(v22_id, _)| *v22_id == id)
+        .map(|(_, v23_id)| *v23_id)
+}
+
+/// Returns the ID3v2.2 equivalent of the ID3v2.3 frame ID `id`.
+pub fn downgrade_frame_id(id: &str) -> Option<&'static str> {
+    V22_TO_V23_IDS
+        .iter()
+        .find(|(_, v23_id)| *v23_id == id)
+        .map(|(v22_id, _)| *v22_id)
+}
+
+/// Converts the body of a `PIC` frame to the body of an `APIC` frame. The only difference
+/// between the two is that `PIC` has a 3 character image format, while `APIC` has a null
+/// terminated MIME type.
+pub fn pic_to_apic(body: &[u8]) -> Vec<u8> {
+    if body.len() < 4 {
+        return body.to_vec();
+    }
+    let format = String::from_utf8_lossy(&body[1..4]).to_string();
+    let mime_type = IMAGE_FORMATS
+        .iter()
+        .find(|(image_format, _)| image_format.eq_ignore_ascii_case(&format))
+        .map(|(_, mime_type)| mime_type.to_string())
+        .unwrap_or_else(|| format!("image/{}", format.to_lowercase()));
+
+    let mut apic = Vec::with_capacity(body.len() + mime_type.len());
+    apic.push(body[0]);
+    apic.extend_from_slice(mime_type.as_bytes());
+    apic.push(0);
+    apic.extend_from_slice(&body[4..]);
+    apic
+}
+
+/// Converts the body of an `APIC` frame to the body of a `PIC` frame. See [`pic_to_apic`].
+pub fn apic_to_pic(body: &[u8]) -> Vec<u8> {
+    let Some(mime_type_end) = body.iter().skip(1).position(|byte| *byte == 0) else {
+        return body.to_vec();
+    };
+    let mime_type = String::from_utf8_lossy(&body[1..mime_type_end + 1]).to_lowercase();
+    let format = IMAGE_FORMATS
+        .iter()
+        .find(|(_, image_mime_type)| *image_mime_type == mime_type)
+        .map(|(image_format, _)| image_format.to_string())
+        .unwrap_or_else(|| {
+            let subtype = mime_type.rsplit('/').next().unwrap_or_default();
+            format!("{:<3.3}", subtype.to_uppercase())
+        });
+
+    let mut pic = Vec::with_capacity(body.len());
+    pic.push(body[0]);
+    pic.extend_from_slice(format.as_bytes());
+    pic.extend_from_slice(&body[mime_type_end + 2..]);
+    pic
+}
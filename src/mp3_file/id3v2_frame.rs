@@ -1,10 +1,23 @@
+use super::frame_body::{FrameBody, Genre, TextFrame};
+use super::id3v2_2;
+use crate::error::{Error, Result};
 use crate::utils::{
-    check_bit, is_valid_syncsafe_integer, log, read_syncsafe_integer, write_syncsafe_integer,
+    check_bit, is_valid_syncsafe_integer, read_syncsafe_integer, write_syncsafe_integer,
 };
-use std::fs::File;
-use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
 
-#[derive(Debug)]
+/// What comes next in a tag, as found by [`ID3v2Frame::scan`].
+pub(crate) enum NextInTag {
+    Frame,
+    /// A zero byte, which no frame ID starts with.
+    Padding,
+    /// Bytes that can't be read as a frame, and why.
+    Garbage(String),
+    /// The tag, or the data, ended.
+    End,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 struct ID3v2FrameFlags {
     /**
      * This flag tells the software what to do with this frame if it is unknown and
@@ -60,117 +73,346 @@ struct ID3v2FrameFlags {
      */
     grouping_identity: bool,
 
-    raw_flags_byte: [u8; 2],
+    /**
+     * This flag indicates whether or not unsynchronisation was applied to this
+     * frame. Only exists in ID3v2.4.
+     *  0 - Frame has not been unsynchronised.
+     *  1 - Frame has been unsyrchronised.
+     */
+    unsynchronisation: bool,
+
+    /**
+     * This flag indicates that a data length indicator has been added to the
+     * frame. Only exists in ID3v2.4.
+     *  0 - There is no Data Length Indicator.
+     *  1 - A data length Indicator has been added to the frame.
+     */
+    data_length_indicator: bool,
+}
+
+impl ID3v2FrameFlags {
+    fn empty() -> ID3v2FrameFlags {
+        ID3v2FrameFlags {
+            tag_alter_preservation: false,
+            file_alter_preservation: false,
+            read_only: false,
+            compression: false,
+            encryption: false,
+            grouping_identity: false,
+            unsynchronisation: false,
+            data_length_indicator: false,
+        }
+    }
+
+    fn has_opaque_data(&self) -> bool {
+        self.compression || self.encryption || self.unsynchronisation || self.data_length_indicator
+    }
+
+    /// ID3v2.3 uses %abc00000 %ijk00000 while ID3v2.4 uses %0abc0000 %0h00kmnp.
+    fn from_bytes(bytes: [u8; 2], major_version: u8) -> ID3v2FrameFlags {
+        if major_version == 3 {
+            if (bytes[0] | bytes[1]) << 3 != 0 {
+                log::warn!("Frame has unofficial flag bits set");
+            }
+            ID3v2FrameFlags {
+                tag_alter_preservation: check_bit(bytes[0], 7),
+                file_alter_preservation: check_bit(bytes[0], 6),
+                read_only: check_bit(bytes[0], 5),
+                compression: check_bit(bytes[1], 7),
+                encryption: check_bit(bytes[1], 6),
+                grouping_identity: check_bit(bytes[1], 5),
+                unsynchronisation: false,
+                data_length_indicator: false,
+            }
+        } else {
+            if bytes[0] & 0b1000_1111 != 0 || bytes[1] & 0b1011_0000 != 0 {
+                log::warn!("Frame has unofficial flag bits set");
+            }
+            ID3v2FrameFlags {
+                tag_alter_preservation: check_bit(bytes[0], 6),
+                file_alter_preservation: check_bit(bytes[0], 5),
+                read_only: check_bit(bytes[0], 4),
+                grouping_identity: check_bit(bytes[1], 6),
+                compression: check_bit(bytes[1], 3),
+                encryption: check_bit(bytes[1], 2),
+                unsynchronisation: check_bit(bytes[1], 1),
+                data_length_indicator: check_bit(bytes[1], 0),
+            }
+        }
+    }
+
+    fn to_bytes(&self, major_version: u8) -> [u8; 2] {
+        let bit = |flag: bool, index: u8| (flag as u8) << index;
+        if major_version == 3 {
+            [
+                bit(self.tag_alter_preservation, 7)
+                    | bit(self.file_alter_preservation, 6)
+                    | bit(self.read_only, 5),
+                bit(self.compression, 7) | bit(self.encryption, 6) | bit(self.grouping_identity, 5),
+            ]
+        } else {
+            [
+                bit(self.tag_alter_preservation, 6)
+                    | bit(self.file_alter_preservation, 5)
+                    | bit(self.read_only, 4),
+                bit(self.grouping_identity, 6)
+                    | bit(self.compression, 3)
+                    | bit(self.encryption, 2)
+                    | bit(self.unsynchronisation, 1)
+                    | bit(self.data_length_indicator, 0),
+            ]
+        }
+    }
 }
 
-#[derive(Debug)]
+/// The data of a frame as it was read, for frames whose body would be encoded differently,
+/// such as text with a big endian byte order mark or a trailing terminator.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ReadData {
+    major_version: u8,
+    /// The body the data was decoded into. Once the frame's body differs from it, the frame
+    /// was edited and the data is no longer used.
+    body: FrameBody,
+    data: Vec<u8>,
+}
+
+/// A single ID3v2 frame: a four-character ID, its flags and its body.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ID3v2Frame {
     flags: ID3v2FrameFlags,
-    pub size: u32,
-    pub id: String,
-    pub data: String,
+    pub(crate) id: String,
+    pub(crate) body: FrameBody,
+    /// The group the frame belongs to, whose identifier comes right before the frame's data.
+    /// Only set on frames whose data isn't opaque, which keep it as part of their data.
+    group_identifier: Option<u8>,
+    read_data: Option<Box<ReadData>>,
 }
 
 impl ID3v2Frame {
-    pub fn from_user_input(id: String, data: String) -> ID3v2Frame {
+    /// Creates a frame with no flags set. The frame ID is not validated, use
+    /// [`is_string_valid_id3v2_id`] before calling this if it comes from the user. Writing a
+    /// frame whose ID isn't 4 bytes long fails with [`Error::UnwritableFrameId`].
+    pub fn new(id: String, body: FrameBody) -> ID3v2Frame {
         ID3v2Frame {
-            size: data.len() as u32,
             id,
-            data,
-            flags: ID3v2FrameFlags {
-                tag_alter_preservation: false,
-                file_alter_preservation: false,
-                read_only: false,
-                compression: false,
-                encryption: false,
-                grouping_identity: false,
-                raw_flags_byte: [0, 0],
-            },
+            body,
+            flags: ID3v2FrameFlags::empty(),
+            group_identifier: None,
+            read_data: None,
         }
     }
-    pub fn from_read_file(file: &mut File) -> Result<ID3v2Frame, ()> {
+
+    /// Builds a frame read from a tag of the given version out of its `data`. The data is kept
+    /// if encoding the body wouldn't give it back, so that the frame is written unchanged
+    /// unless it's edited.
+    fn read(
+        flags: ID3v2FrameFlags,
+        group_identifier: Option<u8>,
+        id: String,
+        body: FrameBody,
+        data: Vec<u8>,
+        major_version: u8,
+    ) -> ID3v2Frame {
+        let mut frame = ID3v2Frame {
+            flags,
+            id,
+            body,
+            group_identifier,
+            read_data: None,
+        };
+        if frame.data(major_version).ok().as_ref() != Some(&data) {
+            frame.read_data = Some(Box::new(ReadData {
+                major_version,
+                body: frame.body.clone(),
+                data,
+            }));
+        }
+        frame
+    }
+
+    /// The frame's four-character ID, such as `TIT2`.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The frame's contents.
+    pub fn body(&self) -> &FrameBody {
+        &self.body
+    }
+
+    pub fn body_mut(&mut self) -> &mut FrameBody {
+        &mut self.body
+    }
+
+    pub fn set_body(&mut self, body: FrameBody) {
+        self.body = body;
+    }
+
+    /// The frame's text, if this is a text frame that could be interpreted.
+    pub fn text(&self) -> Option<&TextFrame> {
+        match &self.body {
+            FrameBody::Text(text_frame) => Some(text_frame),
+            _ => None,
+        }
+    }
+
+    /// Whether the frame's data is compressed, encrypted or unsynchronised, and so can't be
+    /// interpreted.
+    pub(crate) fn has_opaque_data(&self) -> bool {
+        self.flags.has_opaque_data()
+    }
+
+    /// Reads a frame of a tag with the given major version. Versions 3 and 4 differ in how
+    /// the frame size and flags are encoded. Version 2 frames are translated to their version 3
+    /// equivalents.
+    pub fn from_reader<R: Read + Seek>(reader: &mut R, major_version: u8) -> Result<ID3v2Frame> {
+        if major_version == 2 {
+            return Self::from_reader_v22(reader);
+        }
+        let offset = reader.stream_position()?;
         let mut buffer = [0u8; 10];
-        file.read_exact(&mut buffer).or_else(|error| {
-            match error.kind() {
-                ErrorKind::UnexpectedEof => {
-                    log::error("Failed to read frame header. File ended too soon.".to_string())
-                }
-                _ => log::error("Failed to read frame header. Unknown error.".to_string()),
-            };
-            Err(())
+        reader.read_exact(&mut buffer).map_err(|error| {
+            Error::from_read(error, || Error::TruncatedFrame {
+                offset,
+                frame_id: None,
+            })
         })?;
-        let id = std::str::from_utf8(&buffer[0..4])
-            .or_else(|_| {
-                log::error(
-                    "Failed to transform frame ID to UTF8 string. Invalid string.".to_string(),
-                );
-                Err(())
+        let id_bytes: [u8; 4] = buffer[0..4].try_into().unwrap();
+        let id = std::str::from_utf8(&id_bytes)
+            .map_err(|_| Error::InvalidFrameId {
+                offset,
+                bytes: id_bytes,
             })?
             .to_string();
 
         if !is_string_valid_id3v2_id(&id) {
-            log::warn(format!(
-                "{}{}{}",
-                "Frame id \"", id, "\" is not a valid frame id."
-            ));
+            log::warn!("Frame id \"{}\" is not a valid frame id.", id);
         } else if !is_string_known_id3v2_id(&id) {
-            log::warn(format!(
-                "{}{}{}",
-                "Frame id \"", id, "\" is valid, but not a known frame id."
-            ));
+            log::warn!("Frame id \"{}\" is valid, but not a known frame id.", id);
         };
         let size = {
             let size_bytes: [u8; 4] = buffer[4..8].try_into().unwrap();
-            if !is_valid_syncsafe_integer(&size_bytes) {
-                log::warn(
-                    "Frame size is not properly represented as a syncsafe integer".to_string(),
-                );
+            if major_version == 3 {
+                // ID3v2.3 frame sizes are plain 32 bit big endian integers
+                u32::from_be_bytes(size_bytes)
+            } else {
+                if !is_valid_syncsafe_integer(&size_bytes) {
+                    log::warn!("Frame size is not properly represented as a syncsafe integer");
+                }
+                read_syncsafe_integer(&size_bytes)
             }
-            read_syncsafe_integer(&size_bytes)
         };
-        let flags = {
-            let flags_bytes: [u8; 2] = buffer[8..10].try_into().unwrap();
-            if (flags_bytes[0] | flags_bytes[1]) << 3 != 0 {
-                log::warn("Frame has unofficial flag bits set".to_string());
-            }
-            ID3v2FrameFlags {
-                tag_alter_preservation: check_bit(flags_bytes[0], 7),
-                file_alter_preservation: check_bit(flags_bytes[0], 6),
-                read_only: check_bit(flags_bytes[0], 5),
-                compression: check_bit(flags_bytes[1], 7),
-                encryption: check_bit(flags_bytes[1], 6),
-                grouping_identity: check_bit(flags_bytes[1], 5),
-                raw_flags_byte: flags_bytes,
-            }
+        let flags = ID3v2FrameFlags::from_bytes(buffer[8..10].try_into().unwrap(), major_version);
+        let mut data = Self::read_data(reader, size, offset, &id)?;
+        // The group identifier is the first byte of the data, unless the data is opaque
+        let is_opaque = flags.has_opaque_data() || (flags.grouping_identity && data.is_empty());
+        let group_identifier = if flags.grouping_identity && !is_opaque {
+            Some(data.remove(0))
+        } else {
+            None
         };
-        let mut data_buffer = vec![0u8; size.try_into().unwrap()];
-        file.read_exact(&mut data_buffer).or_else(|err| {
-            match err.kind() {
-                ErrorKind::UnexpectedEof => {
-                    log::error("Failed to read frame data. File ended too soon.".to_string())
-                }
-                _ => log::error("Failed to read frame data. Unknown error.".to_string()),
-            };
-            Err(())
-        })?;
-        let data = String::from_utf8(data_buffer).or_else(|_| {
-            log::error("Failed to convert frame data to UTF-8 string.".to_string());
-            Err(())
-        })?;
-        Ok(ID3v2Frame {
+        if is_opaque {
+            // Unknown bodies are written back as they are
+            return Ok(ID3v2Frame {
+                flags,
+                id,
+                body: FrameBody::Unknown(data),
+                group_identifier: None,
+                read_data: None,
+            });
+        }
+        let body = FrameBody::from_bytes(&id, &data, major_version);
+        Ok(ID3v2Frame::read(
             flags,
-            size,
+            group_identifier,
             id,
+            body,
             data,
-        })
+            major_version,
+        ))
+    }
+
+    /// ID3v2.2 frames have a 3 character ID, a 3 byte big endian size and no flags.
+    fn from_reader_v22<R: Read + Seek>(reader: &mut R) -> Result<ID3v2Frame> {
+        let offset = reader.stream_position()?;
+        let mut buffer = [0u8; 6];
+        reader.read_exact(&mut buffer).map_err(|error| {
+            Error::from_read(error, || Error::TruncatedFrame {
+                offset,
+                frame_id: None,
+            })
+        })?;
+        let v22_id = std::str::from_utf8(&buffer[0..3])
+            .map_err(|_| Error::InvalidFrameId {
+                offset,
+                bytes: [buffer[0], buffer[1], buffer[2], 0],
+            })?
+            .to_string();
+        let size = u32::from_be_bytes([0, buffer[3], buffer[4], buffer[5]]);
+        let data_buffer = Self::read_data(reader, size, offset, &v22_id)?;
+
+        let (id, data_buffer) = match id3v2_2::upgrade_frame_id(&v22_id) {
+            Some("APIC") => ("APIC".to_string(), id3v2_2::pic_to_apic(&data_buffer)),
+            Some(id) => (id.to_string(), data_buffer),
+            None => {
+                log::warn!(
+                    "Frame id \"{}\" has no ID3v2.3 equivalent. It will be kept as is.",
+                    v22_id
+                );
+                (v22_id, data_buffer)
+            }
+        };
+        let body = FrameBody::from_bytes(&id, &data_buffer, 2);
+        let flags = ID3v2FrameFlags::empty();
+        Ok(ID3v2Frame::read(flags, None, id, body, data_buffer, 2))
+    }
+
+    /// Reads the frames embedded in the data of another frame, such as the title of a chapter.
+    /// Returns `None` if any of them is truncated. Padding after the last frame is ignored.
+    pub(crate) fn read_embedded(data: &[u8], major_version: u8) -> Option<Vec<ID3v2Frame>> {
+        let header_size = if major_version == 2 { 6 } else { 10 };
+        let mut reader = Cursor::new(data);
+        let mut frames = Vec::new();
+        loop {
+            let position = reader.position() as usize;
+            if position + header_size > data.len() || data[position] == 0 {
+                break;
+            }
+            frames.push(Self::from_reader(&mut reader, major_version).ok()?);
+        }
+        Some(frames)
+    }
+
+    /// Writes frames to be embedded in the data of another frame.
+    pub(crate) fn write_embedded(frames: &[ID3v2Frame], major_version: u8) -> Result<Vec<u8>> {
+        let mut data = Vec::new();
+        for frame in frames {
+            frame.write_to(&mut data, major_version)?;
+        }
+        Ok(data)
+    }
+
+    /// Reads `size` bytes of frame data. The size comes from the frame header, so the buffer
+    /// only grows with the bytes actually read, and a size past the end of the data fails
+    /// without allocating it first.
+    fn read_data<R: Read>(reader: &mut R, size: u32, offset: u64, id: &str) -> Result<Vec<u8>> {
+        let mut data_buffer = Vec::new();
+        reader.take(size as u64).read_to_end(&mut data_buffer)?;
+        if data_buffer.len() < size as usize {
+            return Err(Error::TruncatedFrame {
+                offset,
+                frame_id: Some(id.to_string()),
+            });
+        }
+        Ok(data_buffer)
     }
 
     pub fn format_id(&self) -> String {
-        format!("{}", self.id)
+        self.id.clone()
     }
 
     pub fn format_data(&self) -> String {
-        format!("{}", self.data)
+        self.body.format()
     }
 
     pub fn is_valid_frame_header(bytes: &[char; 4]) -> bool {
@@ -180,18 +422,59 @@ impl ID3v2Frame {
             && ((bytes[3] >= 'A' && bytes[3] <= 'Z') || (bytes[3] >= '0' && bytes[3] <= '9'))
     }
 
-    pub fn has_new_frame(read_file: &mut File) -> bool {
-        let mut buffer = [0u8; 4];
-        read_file
-            .read_exact(&mut buffer)
-            .expect("Failed to read file");
-        if let Err(_e) = read_file.seek(SeekFrom::Current(-4)) {
-            false
-        } else if !Self::is_valid_frame_header(&mut buffer.map(|x| x as char)) {
-            false
-        } else {
-            true
+    /// Looks at what comes next in a tag that ends at byte `tag_end`, leaving `reader` where it
+    /// was. A frame is only reported if its header is valid and it ends within the tag.
+    pub(crate) fn scan<R: Read + Seek>(
+        reader: &mut R,
+        major_version: u8,
+        tag_end: u64,
+    ) -> Result<NextInTag> {
+        let offset = reader.stream_position()?;
+        let remaining = tag_end.saturating_sub(offset);
+        let header_length: u64 = if major_version == 2 { 6 } else { 10 };
+        let mut header = Vec::new();
+        reader
+            .by_ref()
+            .take(header_length.min(remaining))
+            .read_to_end(&mut header)?;
+        reader.seek(SeekFrom::Start(offset))?;
+
+        match header.first() {
+            // The data ended before the tag did, so there's nothing more to read
+            None => return Ok(NextInTag::End),
+            Some(0) => return Ok(NextInTag::Padding),
+            Some(_) => (),
+        }
+        if (header.len() as u64) < header_length {
+            return Ok(NextInTag::Garbage(format!(
+                "{} bytes are too few for a frame",
+                header.len()
+            )));
         }
+        let id_length = if major_version == 2 { 3 } else { 4 };
+        let mut id_bytes = [b'0'; 4];
+        // ID3v2.2 IDs have only 3 characters, so the 4th stays a valid one
+        id_bytes[..id_length].copy_from_slice(&header[..id_length]);
+        if !Self::is_valid_frame_header(&id_bytes.map(|byte| byte as char)) {
+            return Ok(NextInTag::Garbage(format!(
+                "{:02X?} is not a frame ID",
+                &header[..id_length]
+            )));
+        }
+        let size = match major_version {
+            2 => u32::from_be_bytes([0, header[3], header[4], header[5]]),
+            3 => u32::from_be_bytes(header[4..8].try_into().unwrap()),
+            _ => read_syncsafe_integer(header[4..8].try_into().unwrap()),
+        } as u64;
+        if header_length + size > remaining {
+            return Ok(NextInTag::Garbage(format!(
+                "frame \"{}\" claims {} bytes, but only {} are left in the tag",
+                String::from_utf8_lossy(&header[..id_length]),
+                size,
+                remaining - header_length
+            )));
+        }
+        Ok(NextInTag::Frame)
     }
 
     pub fn format_flags(&self, human_readable: bool) -> String {
@@ -215,15 +498,21 @@ impl ID3v2Frame {
             if self.flags.grouping_identity {
                 result.push("grouping-identity");
             };
+            if self.flags.unsynchronisation {
+                result.push("unsynchronisation");
+            };
+            if self.flags.data_length_indicator {
+                result.push("data-length-indicator");
+            };
             let result_string = result.iter().fold(String::new(), |mut acc, s| {
-                if acc.len() != 0 {
+                if !acc.is_empty() {
                     acc.push_str(", ")
                 }
                 acc.push_str(s);
                 acc
             });
 
-            if result_string.len() != 0 {
+            if !result_string.is_empty() {
                 format!("({})", result_string)
             } else {
                 String::new()
@@ -248,39 +537,100 @@ impl ID3v2Frame {
                 } else {
                     '.'
                 },
+                if self.flags.unsynchronisation {
+                    'u'
+                } else {
+                    '.'
+                },
+                if self.flags.data_length_indicator {
+                    'd'
+                } else {
+                    '.'
+                },
             ]
             .iter()
             .collect()
         }
     }
-    pub fn write_to_file(&self, file: &mut File) -> Result<(), ()> {
-        let mut id_chars = self.id.chars();
-        let id_buffer: Vec<u8> = [
-            id_chars.next(),
-            id_chars.next(),
-            id_chars.next(),
-            id_chars.next(),
-        ]
-        .iter()
-        .map(|option| option.expect("Failed to extract ID3v2 ID from frame") as u8)
-        .collect();
-
-        let size_buffer = write_syncsafe_integer(self.size);
-        file.write(&id_buffer)
-            .and_then(|_| file.write(&size_buffer))
-            .and_then(|_| file.write(&self.flags.raw_flags_byte))
-            .and_then(|_| file.write(&self.data.as_bytes()))
-            .or_else(|error| {
-                log::error(format!(
-                    "Failed to write to file. Uknown error. Error kind is {}",
-                    error.kind()
-                ));
-                Err(())
-            })?;
+
+    /// Writes the frame as it should appear in a tag with the given major version. Frames
+    /// that can't be represented in ID3v2.2 are skipped when writing a version 2 tag. Fails
+    /// with [`Error::UnwritableFrameId`] if the ID isn't 4 bytes long.
+    pub fn write_to<W: Write>(&self, writer: &mut W, major_version: u8) -> Result<()> {
+        if major_version == 2 {
+            return self.write_to_v22(writer);
+        }
+        let id_buffer: [u8; 4] =
+            self.id
+                .as_bytes()
+                .try_into()
+                .map_err(|_| Error::UnwritableFrameId {
+                    frame_id: self.id.clone(),
+                    version: major_version,
+                })?;
+
+        let mut data = self.data(major_version)?;
+        if let Some(group_identifier) = self.group_identifier {
+            data.insert(0, group_identifier);
+        }
+        let size_buffer = if major_version == 3 {
+            (data.len() as u32).to_be_bytes()
+        } else {
+            write_syncsafe_integer(data.len() as u32)
+        };
+        writer.write_all(&id_buffer)?;
+        writer.write_all(&size_buffer)?;
+        writer.write_all(&self.flags.to_bytes(major_version))?;
+        writer.write_all(&data)?;
+        Ok(())
+    }
+
+    /// The body's data for a tag of the given version: the data the frame was read with, if
+    /// it wasn't edited since and the version is the same, or the encoded body. The genres of
+    /// edited or converted `TCON` frames are rewritten the way the version expects them.
+    fn data(&self, major_version: u8) -> Result<Vec<u8>> {
+        if let Some(read_data) = &self.read_data {
+            if read_data.major_version == major_version && read_data.body == self.body {
+                return Ok(read_data.data.clone());
+            }
+        }
+        match &self.body {
+            FrameBody::Text(text_frame) if self.id == "TCON" => {
+                let genres = Genre::parse_values(&text_frame.values);
+                let values = Genre::to_values(&genres, major_version);
+                Ok(TextFrame::new(text_frame.encoding, values).to_bytes(major_version))
+            }
+            body => body.to_bytes(major_version),
+        }
+    }
+
+    fn write_to_v22<W: Write>(&self, writer: &mut W) -> Result<()> {
+        let v22_id = match id3v2_2::downgrade_frame_id(&self.id) {
+            Some(v22_id) => v22_id,
+            None if self.id.len() == 3 => &self.id,
+            None => {
+                log::warn!(
+                    "Frame id \"{}\" has no ID3v2.2 equivalent. It will not be written.",
+                    self.id
+                );
+                return Ok(());
+            }
+        };
+        let data = self.data(2)?;
+        let data = if self.id == "APIC" {
+            id3v2_2::apic_to_pic(&data)
+        } else {
+            data
+        };
+        let size = (data.len() as u32).to_be_bytes();
+        writer.write_all(v22_id.as_bytes())?;
+        writer.write_all(&size[1..4])?;
+        writer.write_all(&data)?;
         Ok(())
     }
 }
 
+/// Returns whether `value` is a syntactically valid frame ID: four uppercase letters or digits.
 pub fn is_string_valid_id3v2_id(value: &str) -> bool {
     let bytes = value.as_bytes();
 
@@ -291,24 +641,24 @@ pub fn is_string_valid_id3v2_id(value: &str) -> bool {
         && ((bytes[3] >= b'A' && bytes[3] <= b'Z') || (bytes[3] >= b'0' && bytes[3] <= b'9'))
 }
 
+/// Returns whether `value` is one of the frame IDs listed in [`KNOWN_ID3V2_IDS`].
 pub fn is_string_known_id3v2_id(value: &str) -> bool {
     if !is_string_valid_id3v2_id(value) {
         return false;
     }
 
-    if let Some(_) = KNOWN_ID3V2_IDS.iter().find(|item| (**item).0 == value) {
-        true
-    } else {
-        false
-    }
+    KNOWN_ID3V2_IDS.iter().any(|item| item.0 == value)
 }
 
-pub const KNOWN_ID3V2_IDS: [(&str, &str); 92] = [
+/// Every frame ID defined by the ID3v2.3 and ID3v2.4 specs, along with a short description.
+pub const KNOWN_ID3V2_IDS: [(&str, &str); 94] = [
     ("AENC", "Audio encryption"),
     ("ASPI", "Audio seek point index (v4 only)"),
     ("APIC", "Attached picture"),
+    ("CHAP", "Chapter"),
     ("COMM", "Comments"),
     ("COMR", "Commercial frame"),
+    ("CTOC", "Table of contents"),
     ("ENCR", "Encryption method registration"),
     ("EQUA", "Equalization (v3)"),
     ("EQU2", "Equalization (v4)"),
@@ -406,3 +756,161 @@ pub const KNOWN_ID3V2_IDS: [(&str, &str); 92] = [
     ("WPUB", "Publishers official webpage"),
     ("WXXX", "User defined URL link frame"),
 ];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(bytes: &[u8], major_version: u8) -> Result<ID3v2Frame> {
+        ID3v2Frame::from_reader(&mut Cursor::new(bytes), major_version)
+    }
+
+    fn write(frame: &ID3v2Frame, major_version: u8) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        frame.write_to(&mut bytes, major_version).unwrap();
+        bytes
+    }
+
+    /// A text frame with 255 characters, so its 256 bytes of data have different sizes when
+    /// read as a plain integer or as a syncsafe one.
+    fn long_text_frame(header: [u8; 10]) -> Vec<u8> {
+        let mut bytes = header.to_vec();
+        bytes.push(0);
+        bytes.extend([b'a'; 255]);
+        bytes
+    }
+
+    #[test]
+    fn id3v23_frame_sizes_are_plain_integers() {
+        let bytes = long_text_frame([b'T', b'I', b'T', b'2', 0, 0, 0x01, 0x00, 0, 0]);
+        let frame = read(&bytes, 3).unwrap();
+        assert_eq!(frame.id(), "TIT2");
+        assert_eq!(frame.text().unwrap().text(), "a".repeat(255));
+        assert_eq!(write(&frame, 3), bytes);
+    }
+
+    #[test]
+    fn id3v24_frame_sizes_are_syncsafe() {
+        let bytes = long_text_frame([b'T', b'I', b'T', b'2', 0, 0, 0x02, 0x00, 0, 0]);
+        let frame = read(&bytes, 4).unwrap();
+        assert_eq!(frame.text().unwrap().text(), "a".repeat(255));
+        assert_eq!(write(&frame, 4), bytes);
+    }
+
+    #[test]
+    fn frame_flags_are_kept_in_each_version_layout() {
+        // File alter preservation and read only
+        let v23 = [b'T', b'A', b'L', b'B', 0, 0, 0, 2, 0b0110_0000, 0, 0, b'A'];
+        assert_eq!(write(&read(&v23, 3).unwrap(), 3), v23);
+        let v24 = [b'T', b'A', b'L', b'B', 0, 0, 0, 2, 0b0011_0000, 0, 0, b'A'];
+        let frame = read(&v24, 4).unwrap();
+        assert_eq!(write(&frame, 4), v24);
+        assert_eq!(write(&frame, 3), v23);
+    }
+
+    #[test]
+    fn id3v22_frames_are_read_as_their_id3v23_equivalent() {
+        let bytes = [b'T', b'T', b'2', 0, 0, 3, 0, b'H', b'i'];
+        let frame = read(&bytes, 2).unwrap();
+        assert_eq!(frame.id(), "TIT2");
+        assert_eq!(frame.text().unwrap().text(), "Hi");
+        assert_eq!(write(&frame, 2), bytes);
+        assert_eq!(
+            write(&frame, 3),
+            [b'T', b'I', b'T', b'2', 0, 0, 0, 3, 0, 0, 0, b'H', b'i']
+        );
+    }
+
+    #[test]
+    fn grouped_frames_keep_their_group_identifier() {
+        let v23 = [
+            b'T',
+            b'I',
+            b'T',
+            b'2',
+            0,
+            0,
+            0,
+            4,
+            0,
+            0b0010_0000,
+            7,
+            0,
+            b'H',
+            b'i',
+        ];
+        let frame = read(&v23, 3).unwrap();
+        assert_eq!(frame.text().unwrap().text(), "Hi");
+        assert_eq!(write(&frame, 3), v23);
+        let v24 = [
+            b'T',
+            b'I',
+            b'T',
+            b'2',
+            0,
+            0,
+            0,
+            4,
+            0,
+            0b0100_0000,
+            7,
+            0,
+            b'H',
+            b'i',
+        ];
+        assert_eq!(write(&frame, 4), v24);
+        assert_eq!(write(&read(&v24, 4).unwrap(), 4), v24);
+    }
+
+    #[test]
+    fn compressed_frames_are_kept_as_they_are() {
+        // Grouped too, so the group identifier is part of the opaque data
+        let bytes = [
+            b'T',
+            b'I',
+            b'T',
+            b'2',
+            0,
+            0,
+            0,
+            5,
+            0,
+            0b1010_0000,
+            1,
+            2,
+            3,
+            4,
+            5,
+        ];
+        let frame = read(&bytes, 3).unwrap();
+        assert_eq!(frame.body(), &FrameBody::Unknown(vec![1, 2, 3, 4, 5]));
+        assert_eq!(write(&frame, 3), bytes);
+    }
+
+    #[test]
+    fn a_frame_whose_data_ends_too_soon_is_truncated() {
+        let bytes = [b'T', b'I', b'T', b'2', 0, 0, 0, 5, 0, 0, 0, b'H', b'i'];
+        assert!(matches!(
+            read(&bytes, 3),
+            Err(Error::TruncatedFrame {
+                offset: 0,
+                frame_id: Some(frame_id),
+            }) if frame_id == "TIT2"
+        ));
+    }
+
+    #[test]
+    fn embedded_frames_larger_than_their_parent_are_rejected() {
+        let title = b"TIT2\0\0\0\x02\0\0\0A";
+        let oversized = b"TALB\xFF\xFF\xFF\xFF\0\0\0B";
+        let frames = ID3v2Frame::read_embedded(title, 3).unwrap();
+        assert_eq!(frames[0].text().unwrap().text(), "A");
+        assert!(ID3v2Frame::read_embedded(&[title.as_slice(), oversized].concat(), 3).is_none());
+
+        let chapter = [b"chp0\0".as_slice(), &[0; 8], &[0xFF; 8], oversized].concat();
+        assert_eq!(
+            FrameBody::from_bytes("CHAP", &chapter, 3),
+            FrameBody::Unknown(chapter)
+        );
+    }
+}
@@ -1,13 +1,18 @@
-use crate::utils::{is_valid_syncsafe_integer, log, read_syncsafe_integer, write_syncsafe_integer};
-use std::fs::File;
-use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+use super::extended_header::ExtendedHeader;
+use crate::error::{Error, Result};
+use crate::utils::{
+    check_bit, is_valid_syncsafe_integer, read_syncsafe_integer, write_syncsafe_integer,
+};
+use std::io::{Read, Seek, Write};
 
 #[derive(Debug)]
 #[allow(dead_code)]
 struct ID3v2HeaderFlags {
-    has_extended_header: bool,
     has_unsynchronization: bool,
+    has_extended_header: bool,
     has_experimental_indicator: bool,
+    /// ID3v2.4 only. A copy of the header follows the tag, after the frames.
+    has_footer: bool,
     raw_flags_byte: u8,
 }
 
@@ -17,51 +22,60 @@ pub struct ID3v2Header {
     flags: ID3v2HeaderFlags,
     size: u32,
     version: u16,
+    extended_header: Option<ExtendedHeader>,
+    /// The CRC the extended header held when the tag was read.
+    crc: Option<u32>,
 }
 
 impl ID3v2Header {
-    pub fn from_read_file(file: &mut File) -> Result<ID3v2Header, ()> {
+    pub fn from_reader<R: Read + Seek>(reader: &mut R) -> Result<ID3v2Header> {
         let mut buffer: [u8; 10] = [0; 10];
 
-        file.read_exact(&mut buffer).or_else(|err| {
-            match err.kind() {
-                ErrorKind::UnexpectedEof => {
-                    log::error("Could not read the ID3v2 Header. File ended too soon.".to_string())
-                }
-                _ => log::error("Could not read the ID3v2 Header. Unknown error.".to_string()),
-            };
-            Err(())
-        })?;
+        reader
+            .read_exact(&mut buffer)
+            .map_err(|err| Error::from_read(err, || Error::TruncatedHeader))?;
 
         // According to the specs, the first 3 bytes must be "ID3"
         if buffer[0] != b'I' || buffer[1] != b'D' || buffer[2] != b'3' {
-            log::error(
-                "Invalid header. It does not start with \"ID3\" as it's first 3 bytes".to_string(),
-            );
-            return Err(());
+            return Err(Error::NotAnId3Tag);
         };
 
         let version = ((buffer[4] as u16) << 8) + buffer[3] as u16;
-        if version != 4 {
-            log::warn(format!(
-                "Header version is {}, but this software only supports version 4",
-                version
-            ));
+        let major_version = buffer[3];
+        if !(2..=4).contains(&major_version) {
+            log::warn!(
+                "Header version is {}, but this software only supports versions 2, 3 and 4",
+                major_version
+            );
         };
         let flags = {
             let flags_byte = buffer[5];
 
-            // Check if bits 1, 2, 3, 4 and 5 are set on the flags byte
-            // According to the ID3v2 specs, they should be cleared.
-            if flags_byte << 3 != 0 {
-                log::warn("Header has unofficial flag bits set".to_string());
+            // Check if the bits after the last defined flag are set on the flags byte.
+            // According to the ID3v2 specs, they should be cleared. Version 4 defines an extra
+            // footer flag.
+            let has_unofficial_bits = match major_version {
+                2 => flags_byte << 2 != 0,
+                3 => flags_byte << 3 != 0,
+                _ => flags_byte << 4 != 0,
+            };
+            if has_unofficial_bits {
+                log::warn!("Header has unofficial flag bits set");
+            }
+
+            // In ID3v2.2 the 6th bit means the tag is compressed, and there is no extended header
+            if major_version == 2 && check_bit(flags_byte, 6) {
+                log::warn!(
+                    "Header says the tag is compressed, but ID3v2.2 defines no compression scheme"
+                );
             }
 
             ID3v2HeaderFlags {
                 raw_flags_byte: flags_byte,
-                has_experimental_indicator: (flags_byte >> 7) & 1 == 1,
-                has_extended_header: (flags_byte >> 6) & 1 == 1,
-                has_unsynchronization: (flags_byte >> 5) & 1 == 1,
+                has_unsynchronization: check_bit(flags_byte, 7),
+                has_extended_header: major_version != 2 && check_bit(flags_byte, 6),
+                has_experimental_indicator: major_version != 2 && check_bit(flags_byte, 5),
+                has_footer: major_version == 4 && check_bit(flags_byte, 4),
             }
         };
 
@@ -70,46 +84,37 @@ impl ID3v2Header {
             let size_bytes: [u8; 4] = buffer[6..10].try_into().unwrap();
 
             if !is_valid_syncsafe_integer(&size_bytes) {
-                log::warn(
-                    "Header size is not properly represented as a syncsafe integer".to_string(),
-                );
+                log::warn!("Header size is not properly represented as a syncsafe integer");
             }
             read_syncsafe_integer(&size_bytes)
         };
 
-        // Skip extended header if needed
-        if flags.has_extended_header {
-            let mut buffer: [u8; 4] = [0; 4];
-
-            file.read_exact(&mut buffer).or_else(|err| {
-                match err.kind() {
-                    ErrorKind::UnexpectedEof => log::error(
-                        "Could not read the extended header's size. File ended too soon."
-                            .to_string(),
-                    ),
-                    _ => log::error(
-                        "Could not read the exetended header's size. Unknown error.".to_string(),
-                    ),
-                };
-                Err(())
-            })?;
-            let extended_header_size = read_syncsafe_integer(&buffer);
-
-            file.seek(SeekFrom::Current(extended_header_size as i64))
-                .or_else(|_| {
-                    log::error("Failed to seek backwards while reading ID3v2 Header".to_string());
-                    Err(())
-                })?;
-        }
+        let (extended_header, crc) = if flags.has_extended_header {
+            let (extended_header, crc) = ExtendedHeader::from_reader(reader, major_version, size)?;
+            (Some(extended_header), crc)
+        } else {
+            (None, None)
+        };
 
         Ok(ID3v2Header {
             flags,
             size,
             version,
+            extended_header,
+            crc,
         })
     }
 
-    pub fn write_to_file(&self, file: &mut File, size: u32) -> Result<(), ()> {
+    /// Writes the header, and the extended header if there's one. `frames` is the already
+    /// serialized frames of the tag, used to calculate the extended header's CRC, and `padding`
+    /// is how many bytes of padding follow them.
+    pub fn write_to<W: Write>(
+        &self,
+        writer: &mut W,
+        size: u32,
+        padding: u32,
+        frames: &[u8],
+    ) -> Result<()> {
         let size_bytes = write_syncsafe_integer(size);
         let buffer = [
             b'I',
@@ -117,25 +122,156 @@ impl ID3v2Header {
             b'3',
             self.version as u8,
             (self.version >> 8) as u8,
-            self.flags.raw_flags_byte,
+            // Tags are written with padding, which can't come with a footer, so they never have one
+            self.flags.raw_flags_byte & !0b0001_0000,
             size_bytes[0],
             size_bytes[1],
             size_bytes[2],
             size_bytes[3],
         ];
 
-        file.write(&buffer).or_else(|error| {
-            log::error(format!(
-                "Failed to write to file. Uknown error. Error kind is {}",
-                error.kind()
-            ));
-            Err(())
-        })?;
+        writer.write_all(&buffer)?;
+
+        if let Some(extended_header) = &self.extended_header {
+            extended_header.write_to(writer, self.major_version(), frames, padding)?;
+        }
 
         Ok(())
     }
 
-    pub fn has_extended_header(&self) -> bool {
-        self.flags.has_extended_header
+    /// The major version of the tag, e.g. 4 for ID3v2.4.0.
+    pub fn major_version(&self) -> u8 {
+        self.version as u8
+    }
+
+    /// The revision number of the tag, e.g. 0 for ID3v2.4.0.
+    pub fn revision(&self) -> u8 {
+        (self.version >> 8) as u8
+    }
+
+    /// Changes the version of the tag, keeping only the part of the extended header the new
+    /// version can hold.
+    pub fn set_major_version(&mut self, major_version: u8) {
+        // Only the unsynchronisation and experimental flags mean the same thing on every
+        // version. The ID3v2.2 compression flag would be read as the extended header flag.
+        let kept_flags = if self.major_version() == 2 {
+            0b1000_0000
+        } else {
+            0b1010_0000
+        };
+        self.flags.raw_flags_byte &= kept_flags;
+        self.version = major_version as u16;
+        let extended_header = match major_version {
+            2 => None,
+            _ => self
+                .extended_header
+                .map(|extended_header| extended_header.for_version(major_version)),
+        };
+        self.set_extended_header(extended_header);
+    }
+
+    pub fn extended_header(&self) -> Option<&ExtendedHeader> {
+        self.extended_header.as_ref()
+    }
+
+    /// Adds, replaces or removes the extended header, updating the header's flag.
+    pub fn set_extended_header(&mut self, extended_header: Option<ExtendedHeader>) {
+        self.flags.has_extended_header = extended_header.is_some();
+        if extended_header.is_some() {
+            self.flags.raw_flags_byte |= 0b0100_0000;
+        } else {
+            self.flags.raw_flags_byte &= !0b0100_0000;
+        }
+        self.extended_header = extended_header;
+    }
+
+    /// The CRC the extended header held when the tag was read, if any.
+    pub fn crc(&self) -> Option<u32> {
+        self.crc
+    }
+
+    /// The size of the tag, excluding the 10 bytes of this header.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// The size of the footer that followed the tag when it was read: 10 bytes if it had one,
+    /// 0 otherwise. It isn't written back.
+    pub fn footer_size(&self) -> u32 {
+        if self.flags.has_footer {
+            10
+        } else {
+            0
+        }
+    }
+
+    /// The size of the extended header, including its own size bytes.
+    pub fn extended_header_size(&self) -> u32 {
+        match &self.extended_header {
+            Some(extended_header) => extended_header.size(self.major_version()),
+            None => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn read(bytes: &[u8]) -> Result<ID3v2Header> {
+        ID3v2Header::from_reader(&mut Cursor::new(bytes))
+    }
+
+    fn write(header: &ID3v2Header) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        header.write_to(&mut bytes, header.size(), 0, &[]).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn reads_and_writes_an_id3v24_header() {
+        let bytes = [b'I', b'D', b'3', 4, 0, 0, 0, 0, 0x02, 0x01];
+        let header = read(&bytes).unwrap();
+        assert_eq!(header.major_version(), 4);
+        assert_eq!(header.revision(), 0);
+        // Sizes are syncsafe: 2 << 7 | 1
+        assert_eq!(header.size(), 257);
+        assert!(header.extended_header().is_none());
+        assert_eq!(write(&header), bytes);
+    }
+
+    #[test]
+    fn reads_and_writes_an_id3v23_header() {
+        let bytes = [b'I', b'D', b'3', 3, 0, 0b1000_0000, 0, 0, 0x01, 0x7F];
+        let header = read(&bytes).unwrap();
+        assert_eq!(header.major_version(), 3);
+        assert_eq!(header.size(), 255);
+        assert!(header.flags.has_unsynchronization);
+        assert_eq!(write(&header), bytes);
+    }
+
+    #[test]
+    fn the_id3v22_compression_flag_is_not_an_extended_header() {
+        // The extended header would need bytes that aren't there
+        let bytes = [b'I', b'D', b'3', 2, 0, 0b0100_0000, 0, 0, 0, 10];
+        let header = read(&bytes).unwrap();
+        assert_eq!(header.major_version(), 2);
+        assert!(header.extended_header().is_none());
+        assert_eq!(write(&header), bytes);
+    }
+
+    #[test]
+    fn data_that_does_not_start_with_id3_is_not_a_tag() {
+        let bytes = [b'T', b'A', b'G', 4, 0, 0, 0, 0, 0, 0];
+        assert!(matches!(read(&bytes), Err(Error::NotAnId3Tag)));
+    }
+
+    #[test]
+    fn a_header_shorter_than_10_bytes_is_truncated() {
+        assert!(matches!(
+            read(&[b'I', b'D', b'3', 4, 0]),
+            Err(Error::TruncatedHeader)
+        ));
     }
 }
@@ -1,6 +1,29 @@
+mod convert;
+mod extended_header;
+mod frame_body;
+mod id3v2_2;
 mod id3v2_frame;
 mod id3v2_header;
+#[allow(clippy::module_inception)]
 mod mp3_file;
+mod tag;
+mod text_encoding;
 
-pub use id3v2_frame::{is_string_known_id3v2_id, is_string_valid_id3v2_id, KNOWN_ID3V2_IDS};
-pub use mp3_file::Mp3File;
+pub use convert::FrameConversion;
+pub use extended_header::{ExtendedHeader, TagRestrictions};
+pub use frame_body::{
+    format_chapter_list, is_comment_frame_id, is_text_frame_id, is_timestamp_frame_id,
+    is_url_frame_id, is_user_defined_frame_id, parse_chapter_list, rating_to_stars,
+    sniff_mime_type, stars_to_rating, ChannelAdjustment, ChannelType, Chapter, Comment,
+    ContentType, EncapsulatedObject, FrameBody, GainScope, Genre, OwnedData, Picture, PictureType,
+    PlayCounter, Popularimeter, RelativeVolume, ReplayGain, SetPosition, SynchronisedLyrics,
+    SynchronisedText, TableOfContents, TextFrame, Timestamp, TimestampFormat, UserText, UserUrl,
+    DEFAULT_LANGUAGE, ID3V1_GENRES, MAX_UNIQUE_FILE_IDENTIFIER_LENGTH, PICTURE_TYPES,
+    TIMESTAMP_FRAME_IDS,
+};
+pub use id3v2_frame::{
+    is_string_known_id3v2_id, is_string_valid_id3v2_id, ID3v2Frame, KNOWN_ID3V2_IDS,
+};
+pub use mp3_file::{Mp3File, WriteOptions};
+pub use tag::{Garbage, Tag};
+pub use text_encoding::TextEncoding;
@@ -1,164 +1,280 @@
-use std::fs::File;
-use std::io::{ErrorKind, Read, Write};
+use std::fs::{self, File, FileTimes, Metadata, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::ops::{Deref, DerefMut};
+use std::path::PathBuf;
 
-use super::{id3v2_frame::ID3v2Frame, id3v2_header::ID3v2Header};
-use crate::utils::log;
-use std::path::{Path, PathBuf};
+use super::tag::Tag;
+use crate::error::{Error, Result};
+use std::path::Path;
 
+/// How [`Mp3File::write_to_file_with_options`] treats the file it replaces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriteOptions {
+    /// Gives the rewritten file the permissions of the original. On by default.
+    pub preserve_permissions: bool,
+    /// Gives the rewritten file the owner and group of the original. Only has an effect on
+    /// Unix, and only warns when the owner can't be changed, which usually needs root. On by
+    /// default.
+    pub preserve_ownership: bool,
+    /// Gives the rewritten file the modification time of the original, as if it hadn't been
+    /// touched. Off by default.
+    pub preserve_modification_time: bool,
+    /// How many bytes of padding to leave after the tag when it doesn't fit in the space the
+    /// old one took and the whole file has to be rewritten, so later edits fit in place. Those
+    /// in-place writes aren't atomic, so a padding of 0 makes every write that grows the tag a
+    /// safe rewrite instead. 1024 by default.
+    pub padding: u32,
+}
+
+impl Default for WriteOptions {
+    fn default() -> WriteOptions {
+        WriteOptions {
+            preserve_permissions: true,
+            preserve_ownership: true,
+            preserve_modification_time: false,
+            padding: 1024,
+        }
+    }
+}
+
+/// An MP3 file opened for tagging. Dereferences to its [`Tag`], so every tag operation is
+/// available directly on the file.
 #[derive(Debug)]
 pub struct Mp3File {
-    header: ID3v2Header,
-    frames: Vec<ID3v2Frame>,
+    tag: Tag,
     read_file: File,
+    /// How many bytes the tag takes at the start of the file, header, padding and footer
+    /// included.
+    tag_size: u64,
 }
 
 impl Mp3File {
-    pub fn remove_frame(&mut self, frame_id: &str, user_frame_index: u32) -> Result<(), u32> {
-        self.find_index_of_frame_with_id(frame_id, user_frame_index)
-            .and_then(|index| {
-                self.frames.remove(index);
-                Ok(())
-            })
+    pub fn from_path(path: &Path) -> Result<Mp3File> {
+        let mut read_file = File::open(path).map_err(|source| Error::Open {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        let tag = Tag::from_reader(&mut read_file)?;
+        let tag_size = 10 + tag.declared_size() as u64 + tag.footer_size() as u64;
+
+        Ok(Mp3File {
+            tag,
+            read_file,
+            tag_size,
+        })
     }
 
-    pub fn edit_frame(
-        &mut self,
-        frame_id: &str,
-        new_data: String,
-        user_frame_index: u32,
-    ) -> Result<(), u32> {
-        self.find_index_of_frame_with_id(frame_id, user_frame_index)
-            .and_then(|index| {
-                self.frames[index].data = new_data;
-                Ok(())
-            })
+    pub fn tag(&self) -> &Tag {
+        &self.tag
     }
 
-    fn find_index_of_frame_with_id(&self, frame_id: &str, frame_index: u32) -> Result<usize, u32> {
-        let mut frames_found_counter = 0u32;
-
-        self.frames
-            .iter()
-            .position(|item| {
-                if item.id == frame_id {
-                    if frames_found_counter < frame_index {
-                        frames_found_counter += 1;
-                        false
-                    } else {
-                        true
-                    }
-                } else {
-                    false
-                }
-            })
-            .ok_or(frames_found_counter)
+    pub fn tag_mut(&mut self) -> &mut Tag {
+        &mut self.tag
     }
 
-    pub fn from_path(path: &Path) -> Result<Mp3File, ()> {
-        let mut read_file = File::open(path).or_else(|error| {
-            let path = path.to_string_lossy();
-            match error.kind() {
-                ErrorKind::NotFound => log::error(format!("File {} does not exist.", path)),
-                ErrorKind::PermissionDenied => {
-                    log::error(format!("Failed to open file {}. Permission denied", path))
-                }
-                _ => log::error(format!(
-                    "Failed to open file {}. Unknown error: {}",
-                    path,
-                    error.kind()
-                )),
-            }
-            Err(())
-        })?;
+    /// Rewrites the file with the current tag, using the default [`WriteOptions`].
+    pub fn write_to_file(&mut self, read_file_path: &Path) -> Result<()> {
+        self.write_to_file_with_options(read_file_path, &WriteOptions::default())
+    }
 
-        let header = ID3v2Header::from_read_file(&mut read_file)?;
-        let mut frames: Vec<ID3v2Frame> = vec![];
-        while ID3v2Frame::has_new_frame(&mut read_file) {
-            let new_frame = ID3v2Frame::from_read_file(&mut read_file)?;
-            frames.push(new_frame);
+    /// Saves the current tag to the file it was read from. When the tag fits in the space the
+    /// old one took, it's written over it and the padding takes up the rest, so the audio isn't
+    /// touched. Otherwise the whole file is rewritten, leaving `options.padding` bytes of
+    /// padding after the tag.
+    ///
+    /// Writing in place is much faster on large files, but unlike a rewrite it isn't atomic: if
+    /// it fails or is interrupted halfway, the file is left with a partly written tag. The
+    /// audio is never touched either way.
+    pub fn write_to_file_with_options(
+        &mut self,
+        read_file_path: &Path,
+        options: &WriteOptions,
+    ) -> Result<()> {
+        let tag_size = self.tag.to_bytes()?.len() as u64;
+        if tag_size <= self.tag_size {
+            let padding = (self.tag_size - tag_size) as u32;
+            let tag = self.tag.to_bytes_with_padding(padding)?;
+            return overwrite_tag(read_file_path, &tag, options);
         }
-
-        Ok(Mp3File {
-            frames,
-            header,
-            read_file,
-        })
+        self.rewrite_file(read_file_path, options)
     }
 
-    fn calculate_id3v2_size(&self) -> u32 {
-        let extended_header_size = if self.header.has_extended_header() {
-            10
-        } else {
-            0
+    /// Rewrites the whole file. The new contents are written to a temporary file in the same
+    /// directory, which then replaces the original in a single rename, so the original is left
+    /// untouched if anything fails.
+    fn rewrite_file(&mut self, read_file_path: &Path, options: &WriteOptions) -> Result<()> {
+        let metadata = fs::metadata(read_file_path).map_err(|source| Error::Open {
+            path: read_file_path.to_path_buf(),
+            source,
+        })?;
+        let (write_file_path, mut write_file) = create_temp_file(read_file_path)?;
+
+        let written = self
+            .write_contents(read_file_path, &mut write_file, &metadata, options)
+            .and_then(|tag_size| {
+                drop(write_file);
+                fs::rename(&write_file_path, read_file_path).map_err(|source| Error::Replace {
+                    path: read_file_path.to_path_buf(),
+                    source,
+                })?;
+                Ok(tag_size)
+            });
+        let tag_size = match written {
+            Ok(tag_size) => tag_size,
+            Err(error) => {
+                let _ = fs::remove_file(&write_file_path);
+                return Err(error);
+            }
         };
-        let mut frames_size = 0;
-        for frame in self.frames.iter() {
-            frames_size += 10;
-            frames_size += frame.size;
+        sync_parent_directory(read_file_path);
+
+        // The old file is gone, so further writes must copy the audio from the new one
+        self.read_file = File::open(read_file_path).map_err(|source| Error::Open {
+            path: read_file_path.to_path_buf(),
+            source,
+        })?;
+        self.tag_size = tag_size;
+        Ok(())
+    }
+
+    /// Copies the `audio_size` bytes that follow the tag the file was read with to
+    /// `write_file`. Fails if there are fewer, which means the file changed since it was read.
+    fn copy_audio(&mut self, write_file: &mut File, audio_size: u64) -> Result<()> {
+        self.read_file.seek(SeekFrom::Start(self.tag_size))?;
+        let copied = io::copy(&mut (&self.read_file).take(audio_size), write_file)?;
+        if copied != audio_size {
+            return Err(Error::Io(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!(
+                    "expected {} bytes of audio after the tag, but the file ended after {}",
+                    audio_size, copied
+                ),
+            )));
         }
+        Ok(())
+    }
+
+    /// Writes the tag and the audio to `write_file`, gives it the attributes of the original
+    /// that `options` asks for and flushes it to disk. Returns the size of the tag.
+    fn write_contents(
+        &mut self,
+        read_file_path: &Path,
+        write_file: &mut File,
+        metadata: &Metadata,
+        options: &WriteOptions,
+    ) -> Result<u64> {
+        let tag = self.tag.to_bytes_with_padding(options.padding)?;
+        write_file.write_all(&tag)?;
 
-        extended_header_size + frames_size
+        self.copy_audio(write_file, metadata.len().saturating_sub(self.tag_size))?;
+
+        // Changing the owner may clear the setuid and setgid bits, so it goes first
+        #[cfg(unix)]
+        if options.preserve_ownership {
+            use std::os::unix::fs::MetadataExt;
+            let current = write_file.metadata()?;
+            if (current.uid(), current.gid()) != (metadata.uid(), metadata.gid()) {
+                // Only root may give a file away, so like `cp -p` this is best effort
+                if let Err(error) = std::os::unix::fs::fchown(
+                    &*write_file,
+                    Some(metadata.uid()),
+                    Some(metadata.gid()),
+                ) {
+                    log::warn!(
+                        "Couldn't give {} its original owner and group: {}",
+                        read_file_path.display(),
+                        error
+                    );
+                }
+            }
+        }
+        if options.preserve_permissions {
+            write_file.set_permissions(metadata.permissions())?;
+        }
+        if options.preserve_modification_time {
+            write_file.set_times(FileTimes::new().set_modified(metadata.modified()?))?;
+        }
+        write_file.sync_all()?;
+        Ok(tag.len() as u64)
     }
+}
 
-    pub fn write_to_file(&mut self, read_file_path: &PathBuf) -> Result<(), ()> {
-        let mut write_file_path = read_file_path.clone();
-        write_file_path.set_extension("mp3.temp");
-        let mut write_file = File::create(&write_file_path).or_else(|error| {
-            let file_path = write_file_path.to_string_lossy();
-            match error.kind() {
-                ErrorKind::PermissionDenied => log::error(format!(
-                    "Failed to open file {} for writing. Permission denied",
-                    file_path,
-                )),
-                _ => log::error(format!(
-                    "Failed to open file {}. Unknown error: {}",
-                    file_path,
-                    error.kind()
-                )),
-            };
-            Err(())
+/// Writes `tag` over the start of the file, which must hold a tag of exactly the same size.
+fn overwrite_tag(path: &Path, tag: &[u8], options: &WriteOptions) -> Result<()> {
+    let modified = options
+        .preserve_modification_time
+        .then(|| fs::metadata(path).and_then(|metadata| metadata.modified()));
+    let mut file = OpenOptions::new()
+        .write(true)
+        .open(path)
+        .map_err(|source| Error::Create {
+            path: path.to_path_buf(),
+            source,
         })?;
-        let size = self.calculate_id3v2_size();
-        self.header.write_to_file(&mut write_file, size)?;
-        for frame in self.frames.iter() {
-            frame.write_to_file(&mut write_file)?;
-        }
+    file.write_all(tag)?;
+    if let Some(modified) = modified {
+        file.set_times(FileTimes::new().set_modified(modified?))?;
+    }
+    file.sync_all()?;
+    Ok(())
+}
 
-        let mut buffer = [0; 16 * (2 ^ 10)];
-        while self
-            .read_file
-            .read(&mut buffer)
-            .expect("Failed to read file chunk")
-            > 0
+/// Creates a new file next to `path`, named after it, that no other file is using.
+fn create_temp_file(path: &Path) -> Result<(PathBuf, File)> {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    for attempt in 0.. {
+        let temp_path = path.with_file_name(format!(
+            ".{}.{}-{}.temp",
+            file_name,
+            std::process::id(),
+            attempt
+        ));
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temp_path)
         {
-            write_file
-                .write(&buffer)
-                .expect("Failed to write to file chunk");
+            Ok(file) => return Ok((temp_path, file)),
+            Err(error) if error.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(source) => {
+                return Err(Error::Create {
+                    path: temp_path,
+                    source,
+                })
+            }
         }
+    }
+    unreachable!("Ran out of temporary file names")
+}
 
-        Ok(())
+/// Makes the rename that replaced `path` durable. Only possible on Unix, and a failure only
+/// means the rename may be lost on a crash, so errors are ignored.
+fn sync_parent_directory(path: &Path) {
+    #[cfg(unix)]
+    {
+        let parent = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        if let Ok(directory) = File::open(parent) {
+            let _ = directory.sync_all();
+        }
     }
+    #[cfg(not(unix))]
+    let _ = path;
+}
 
-    pub fn format_frames(&self, frame_flags: bool, human_readable: bool) -> String {
-        self.frames
-            .iter()
-            .map(|frame| -> String {
-                if frame_flags {
-                    let mut flags_str = frame.format_flags(human_readable);
-                    if flags_str.len() != 0 {
-                        flags_str.push(' ');
-                    };
-                    format!("{} {}{}", frame.format_id(), flags_str, frame.format_data())
-                } else {
-                    format!("{} {}", frame.format_id(), frame.format_data())
-                }
-            })
-            .reduce(|a, b| format!("{}\n{}", a, b))
-            .unwrap_or("No frames in file.".to_string())
+impl Deref for Mp3File {
+    type Target = Tag;
+
+    fn deref(&self) -> &Tag {
+        &self.tag
     }
+}
 
-    pub fn add_frame(&mut self, id: String, data: String) {
-        let new_frame = ID3v2Frame::from_user_input(id, data);
-        self.frames.push(new_frame);
+impl DerefMut for Mp3File {
+    fn deref_mut(&mut self) -> &mut Tag {
+        &mut self.tag
     }
 }
@@ -0,0 +1,1636 @@
+use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
+
+use crate::error::{Error, Result};
+use crate::utils::{crc32, BinaryFormat};
+
+use super::convert::{self, FrameConversion};
+use super::extended_header::ExtendedHeader;
+use super::frame_body::{
+    format_gain, is_timestamp_frame_id, parse_gain, ChannelAdjustment, ChannelType, Chapter,
+    Comment, EncapsulatedObject, FrameBody, GainScope, Genre, OwnedData, Picture, PlayCounter,
+    Popularimeter, RelativeVolume, ReplayGain, SetPosition, SynchronisedLyrics, TableOfContents,
+    TextFrame, Timestamp, UserText, UserUrl,
+};
+use super::id3v2_frame::{is_string_valid_id3v2_id, ID3v2Frame, NextInTag};
+use super::id3v2_header::ID3v2Header;
+use super::text_encoding::TextEncoding;
+
+/// Bytes inside a tag that are neither frames nor padding. Reading stops at them, and they're
+/// replaced by padding when the tag is written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Garbage {
+    /// Where the bytes start, counted from the start of the data the tag was read from.
+    pub offset: u64,
+    /// How many bytes there are from `offset` to the end of the tag.
+    pub length: u64,
+    pub reason: String,
+}
+
+/// An ID3v2 tag: its header and the list of frames it contains, in file order.
+#[derive(Debug)]
+pub struct Tag {
+    header: ID3v2Header,
+    frames: Vec<ID3v2Frame>,
+    padding: u64,
+    garbage: Option<Garbage>,
+}
+
+impl Tag {
+    /// Reads the tag header and every frame that follows it, up to the end of the tag as given
+    /// by its header. Frames end at the first zero byte, which starts the padding, or at
+    /// bytes that can't be read as a frame, which are kept as [`Garbage`]. When this returns,
+    /// `reader` is positioned right after the tag.
+    pub fn from_reader<R: Read + Seek>(reader: &mut R) -> Result<Tag> {
+        let tag_start = reader.stream_position()?;
+        let header = ID3v2Header::from_reader(reader)?;
+        let tag_end = tag_start + 10 + header.size() as u64;
+        let frames_start = reader.stream_position()?;
+        let mut frames: Vec<ID3v2Frame> = vec![];
+        let mut garbage_reason = None;
+        loop {
+            match ID3v2Frame::scan(reader, header.major_version(), tag_end)? {
+                NextInTag::Frame => {
+                    frames.push(ID3v2Frame::from_reader(reader, header.major_version())?)
+                }
+                NextInTag::Garbage(reason) => {
+                    garbage_reason = Some(reason);
+                    break;
+                }
+                NextInTag::Padding | NextInTag::End => break,
+            }
+        }
+
+        let offset = reader.stream_position()?;
+        if let Some(expected_crc) = header.crc() {
+            // ID3v2.3 CRCs only cover the frames, and ID3v2.4 ones the padding too
+            let crc_end = if header.major_version() == 3 {
+                offset
+            } else {
+                tag_end
+            };
+            reader.seek(SeekFrom::Start(frames_start))?;
+            let mut data = Vec::new();
+            reader
+                .by_ref()
+                .take(crc_end - frames_start)
+                .read_to_end(&mut data)?;
+            reader.seek(SeekFrom::Start(offset))?;
+            if crc32(&data) != expected_crc {
+                log::warn!("The CRC-32 in the extended header doesn't match the tag, which may be corrupted");
+            }
+        }
+        let remaining = tag_end.saturating_sub(offset);
+        let padding = match garbage_reason {
+            Some(_) => 0,
+            None => count_zeros(reader, remaining)?,
+        };
+        let garbage_length = io::copy(
+            &mut reader.by_ref().take(remaining - padding),
+            &mut io::sink(),
+        )?;
+        let garbage = if garbage_length == 0 {
+            None
+        } else {
+            let garbage = Garbage {
+                offset: offset + padding,
+                length: garbage_length,
+                reason: garbage_reason
+                    .unwrap_or_else(|| "the padding is not made of zeros".to_string()),
+            };
+            log::warn!(
+                "Ignoring {} bytes of the tag at byte {}: {}",
+                garbage.length,
+                garbage.offset,
+                garbage.reason
+            );
+            Some(garbage)
+        };
+        // The footer only repeats the header
+        let footer_size = header.footer_size() as u64;
+        let footer_length = io::copy(&mut reader.by_ref().take(footer_size), &mut io::sink())?;
+        if footer_length < footer_size {
+            log::warn!("Header says the tag has a footer, but the data ends before it");
+        }
+
+        Ok(Tag {
+            header,
+            frames,
+            padding,
+            garbage,
+        })
+    }
+
+    /// Reads a tag from the start of `bytes`. Anything after the tag is ignored.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Tag> {
+        Self::from_reader(&mut Cursor::new(bytes))
+    }
+
+    /// Writes the tag header followed by every frame.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<()> {
+        self.write_with_padding(writer, 0)
+    }
+
+    /// Writes the tag header, every frame and then `padding` zero bytes, which later edits can
+    /// take up without moving the audio that follows the tag.
+    pub fn write_with_padding<W: Write>(&self, writer: &mut W, padding: u32) -> Result<()> {
+        let mut frames = Vec::new();
+        for frame in self.frames.iter() {
+            frame.write_to(&mut frames, self.major_version())?;
+        }
+        let size = self.calculate_id3v2_size(frames.len() as u32) + padding;
+        self.header.write_to(writer, size, padding, &frames)?;
+        writer.write_all(&frames)?;
+        writer.write_all(&vec![0; padding as usize])?;
+        Ok(())
+    }
+
+    /// Serializes the tag, header included.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        self.to_bytes_with_padding(0)
+    }
+
+    /// Serializes the tag, header included, followed by `padding` zero bytes.
+    pub fn to_bytes_with_padding(&self, padding: u32) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        self.write_with_padding(&mut bytes, padding)?;
+        Ok(bytes)
+    }
+
+    /// The size of the tag as declared in its header when it was read, padding included but
+    /// not the 10 bytes of the header itself.
+    pub(crate) fn declared_size(&self) -> u32 {
+        self.header.size()
+    }
+
+    /// The size of the footer that followed the tag when it was read, which is 10 bytes if
+    /// there was one. Tags are always written without.
+    pub(crate) fn footer_size(&self) -> u32 {
+        self.header.footer_size()
+    }
+
+    /// The extended header, if the tag has one.
+    pub fn extended_header(&self) -> Option<&ExtendedHeader> {
+        self.header.extended_header()
+    }
+
+    /// Adds, replaces or removes the extended header. The parts of it that the tag's version
+    /// can't hold are dropped. Fails on ID3v2.2 tags, which have no extended header.
+    pub fn set_extended_header(&mut self, extended_header: Option<ExtendedHeader>) -> Result<()> {
+        if self.major_version() == 2 && extended_header.is_some() {
+            return Err(Error::UnsupportedVersion { version: 2 });
+        }
+        let major_version = self.major_version();
+        self.header.set_extended_header(
+            extended_header.map(|extended_header| extended_header.for_version(major_version)),
+        );
+        Ok(())
+    }
+
+    /// How many bytes of padding followed the frames when the tag was read.
+    pub fn padding(&self) -> u64 {
+        self.padding
+    }
+
+    /// The bytes that couldn't be read as frames nor as padding when the tag was read, if any.
+    pub fn garbage(&self) -> Option<&Garbage> {
+        self.garbage.as_ref()
+    }
+
+    /// The major version of the tag, e.g. 4 for ID3v2.4.0.
+    pub fn major_version(&self) -> u8 {
+        self.header.major_version()
+    }
+
+    /// The revision number of the tag, e.g. 0 for ID3v2.4.0.
+    pub fn revision(&self) -> u8 {
+        self.header.revision()
+    }
+
+    /// Turns an ID3v2.2 tag into an ID3v2.3 tag. Frames were already translated to their
+    /// ID3v2.3 equivalents when read, so this drops the ones that had no equivalent and returns
+    /// their IDs. Does nothing to tags of other versions.
+    pub fn upgrade_v22(&mut self) -> Vec<String> {
+        if self.major_version() != 2 {
+            return Vec::new();
+        }
+        self.header.set_major_version(3);
+        let mut dropped_ids = Vec::new();
+        self.frames.retain(|frame| {
+            let keep = is_string_valid_id3v2_id(&frame.id);
+            if !keep {
+                dropped_ids.push(frame.id.clone());
+            }
+            keep
+        });
+        dropped_ids
+    }
+
+    /// Rewrites the tag as ID3v2.3 or ID3v2.4, translating the frames that changed between
+    /// versions. Returns every change made to the frames, including the ones that were dropped
+    /// or lost information.
+    pub fn convert(&mut self, major_version: u8) -> Result<Vec<FrameConversion>> {
+        if major_version != 3 && major_version != 4 {
+            return Err(Error::UnsupportedVersion {
+                version: major_version,
+            });
+        }
+        let mut report: Vec<FrameConversion> = self
+            .upgrade_v22()
+            .into_iter()
+            .map(|frame_id| FrameConversion::Dropped {
+                frame_id,
+                reason: "the frame has no ID3v2.3 equivalent".to_string(),
+            })
+            .collect();
+
+        if self.major_version() == major_version {
+            return Ok(report);
+        }
+        report.extend(match major_version {
+            3 => convert::downgrade_frames(&mut self.frames),
+            _ => convert::upgrade_frames(&mut self.frames),
+        });
+        self.header.set_major_version(major_version);
+        Ok(report)
+    }
+
+    /// All frames in the tag, in file order.
+    pub fn frames(&self) -> &[ID3v2Frame] {
+        &self.frames
+    }
+
+    /// All frames with the given ID, in file order.
+    pub fn frames_with_id<'a>(&'a self, frame_id: &'a str) -> impl Iterator<Item = &'a ID3v2Frame> {
+        self.frames.iter().filter(move |frame| frame.id == frame_id)
+    }
+
+    /// The `user_frame_index`-th (zero indexed) frame with ID `frame_id`. If there's no such
+    /// frame, returns [`Error::FrameNotFound`].
+    pub fn frame_at(&self, frame_id: &str, user_frame_index: u32) -> Result<&ID3v2Frame> {
+        let index = self.find_index_of_frame_with_id(frame_id, user_frame_index)?;
+        Ok(&self.frames[index])
+    }
+
+    /// Appends a new frame to the end of the tag.
+    pub fn add_frame(&mut self, id: String, data: String) {
+        let body = FrameBody::from_user_input(&id, data, self.major_version());
+        self.frames.push(ID3v2Frame::new(id, body));
+    }
+
+    /// Appends an already built frame to the end of the tag.
+    pub fn push_frame(&mut self, frame: ID3v2Frame) {
+        self.frames.push(frame);
+    }
+
+    /// Appends a new text frame to the end of the tag. Fails if the tag's version doesn't
+    /// support the frame's encoding, or if the frame holds timestamps and a value isn't one.
+    pub fn add_text_frame(&mut self, id: String, text_frame: &TextFrame) -> Result<()> {
+        let body = self.text_frame_body(&id, text_frame)?;
+        self.frames.push(ID3v2Frame::new(id, body));
+        Ok(())
+    }
+
+    /// Replaces the `user_frame_index`-th (zero indexed) text frame with ID `frame_id`. Fails
+    /// if there's no such frame, if the tag's version doesn't support the frame's encoding, or
+    /// if the frame holds timestamps and a value isn't one.
+    pub fn edit_text_frame(
+        &mut self,
+        frame_id: &str,
+        text_frame: &TextFrame,
+        user_frame_index: u32,
+    ) -> Result<()> {
+        let body = self.text_frame_body(frame_id, text_frame)?;
+        let index = self.find_index_of_frame_with_id(frame_id, user_frame_index)?;
+        self.frames[index].set_body(body);
+        Ok(())
+    }
+
+    fn text_frame_body(&self, frame_id: &str, text_frame: &TextFrame) -> Result<FrameBody> {
+        self.check_encoding(text_frame.encoding)?;
+        if is_timestamp_frame_id(frame_id) {
+            let invalid_value = text_frame
+                .values
+                .iter()
+                .find(|value| Timestamp::parse(value).is_none());
+            if let Some(value) = invalid_value {
+                return Err(Error::InvalidTimestamp {
+                    frame_id: frame_id.to_string(),
+                    value: value.clone(),
+                });
+            }
+        }
+        Ok(FrameBody::Text(text_frame.clone()))
+    }
+
+    /// Fails if the tag's version doesn't support `encoding`.
+    fn check_encoding(&self, encoding: TextEncoding) -> Result<()> {
+        if !encoding.is_supported_by(self.major_version()) {
+            return Err(Error::UnsupportedEncoding {
+                encoding,
+                version: self.major_version(),
+            });
+        }
+        Ok(())
+    }
+
+    /// The genres of the `TCON` frame, with references to ID3v1 genres resolved.
+    pub fn genres(&self) -> Vec<Genre> {
+        self.frames_with_id("TCON")
+            .find_map(ID3v2Frame::text)
+            .map(|text_frame| Genre::parse_values(&text_frame.values))
+            .unwrap_or_default()
+    }
+
+    /// Replaces the genres of the `TCON` frame, or adds one.
+    pub fn set_genres(&mut self, genres: &[Genre]) {
+        let major_version = self.major_version();
+        let values = Genre::to_values(genres, major_version);
+        let body = FrameBody::Text(TextFrame::with_default_encoding(values, major_version));
+        let position = self.frames.iter().position(|frame| frame.id == "TCON");
+        self.upsert_frame(position, "TCON", body);
+    }
+
+    /// The timestamp of a time frame, such as `TDRC`. ID3v2.3 tags don't have `TDRC` and `TDOR`
+    /// frames, so their recording time is read from `TYER`, `TDAT` and `TIME`, and their
+    /// original release year from `TORY`.
+    pub fn timestamp(&self, frame_id: &str) -> Option<Timestamp> {
+        let text = |frame_id: &str| {
+            self.frames_with_id(frame_id)
+                .find_map(ID3v2Frame::text)
+                .map(|text_frame| text_frame.text().to_string())
+        };
+        match frame_id {
+            "TDRC" if self.major_version() < 4 => Timestamp::from_v23(
+                &text("TYER")?,
+                text("TDAT").as_deref(),
+                text("TIME").as_deref(),
+            ),
+            "TDOR" if self.major_version() < 4 => Timestamp::from_v23(&text("TORY")?, None, None),
+            _ => Timestamp::parse(&text(frame_id)?),
+        }
+    }
+
+    /// Replaces the value of a time frame, such as `TDRC`, or adds one. ID3v2.3 tags get `TYER`,
+    /// `TDAT` and `TIME` frames instead of `TDRC`, and `TORY` instead of `TDOR`, holding only
+    /// the part of the timestamp they can.
+    pub fn set_timestamp(&mut self, frame_id: &str, timestamp: &Timestamp) {
+        let (year, date, time) = timestamp.to_v23();
+        match frame_id {
+            "TDRC" if self.major_version() < 4 => {
+                self.set_text("TYER", Some(year));
+                self.set_text("TDAT", date);
+                self.set_text("TIME", time);
+            }
+            "TDOR" if self.major_version() < 4 => self.set_text("TORY", Some(year)),
+            _ => self.set_text(frame_id, Some(timestamp.to_string())),
+        }
+    }
+
+    /// The track number and the number of tracks of the disc, from the `TRCK` frame.
+    pub fn track(&self) -> Option<SetPosition> {
+        self.set_position("TRCK")
+    }
+
+    /// Replaces the `TRCK` frame, or adds one. Both numbers are padded with zeros to at least
+    /// `width` digits.
+    pub fn set_track(&mut self, track: &SetPosition, width: usize) {
+        self.set_text("TRCK", Some(track.to_padded_string(width)));
+    }
+
+    /// The disc number and the number of discs of the set, from the `TPOS` frame.
+    pub fn disc(&self) -> Option<SetPosition> {
+        self.set_position("TPOS")
+    }
+
+    /// Replaces the `TPOS` frame, or adds one. Both numbers are padded with zeros to at least
+    /// `width` digits.
+    pub fn set_disc(&mut self, disc: &SetPosition, width: usize) {
+        self.set_text("TPOS", Some(disc.to_padded_string(width)));
+    }
+
+    fn set_position(&self, frame_id: &str) -> Option<SetPosition> {
+        self.frames_with_id(frame_id)
+            .find_map(ID3v2Frame::text)
+            .and_then(|text_frame| SetPosition::parse(text_frame.text()))
+    }
+
+    /// Replaces the value of the first frame with ID `frame_id`, or adds one. Removes every
+    /// frame with that ID if `text` is `None`.
+    fn set_text(&mut self, frame_id: &str, text: Option<String>) {
+        let Some(text) = text else {
+            self.frames.retain(|frame| frame.id != frame_id);
+            return;
+        };
+        let body = FrameBody::Text(TextFrame::with_default_encoding(
+            vec![text],
+            self.major_version(),
+        ));
+        let position = self.frames.iter().position(|frame| frame.id == frame_id);
+        self.upsert_frame(position, frame_id, body);
+    }
+
+    /// Every attached picture in the tag, in file order. `APIC` frames that couldn't be
+    /// interpreted are skipped.
+    pub fn pictures(&self) -> impl Iterator<Item = &Picture> {
+        self.frames_with_id("APIC")
+            .filter_map(|frame| match frame.body() {
+                FrameBody::Picture(picture) => Some(picture),
+                _ => None,
+            })
+    }
+
+    /// Appends an attached picture to the end of the tag. Fails if the tag's version doesn't
+    /// support the encoding of the picture's description.
+    pub fn add_picture(&mut self, picture: Picture) -> Result<()> {
+        self.check_encoding(picture.encoding)?;
+        self.frames.push(ID3v2Frame::new(
+            "APIC".to_string(),
+            FrameBody::Picture(picture),
+        ));
+        Ok(())
+    }
+
+    /// The `user_frame_index`-th (zero indexed) `APIC` frame. Fails if there's no such frame,
+    /// or if its data couldn't be interpreted.
+    pub fn picture(&self, user_frame_index: u32) -> Result<&Picture> {
+        match self.frame_at("APIC", user_frame_index)?.body() {
+            FrameBody::Picture(picture) => Ok(picture),
+            _ => Err(Error::MalformedFrame {
+                frame_id: "APIC".to_string(),
+            }),
+        }
+    }
+
+    /// Like [`Tag::picture`], but allows changing the picture.
+    pub fn picture_mut(&mut self, user_frame_index: u32) -> Result<&mut Picture> {
+        let index = self.find_index_of_frame_with_id("APIC", user_frame_index)?;
+        match self.frames[index].body_mut() {
+            FrameBody::Picture(picture) => Ok(picture),
+            _ => Err(Error::MalformedFrame {
+                frame_id: "APIC".to_string(),
+            }),
+        }
+    }
+
+    /// The `COMM` or `USLT` frame, depending on `frame_id`, identified by `language` and
+    /// `description`.
+    pub fn comment(&self, frame_id: &str, language: &str, description: &str) -> Option<&Comment> {
+        self.position_of_language_and_description(frame_id, language, description)
+            .and_then(|index| self.frames[index].body().as_comment())
+    }
+
+    /// The `user_frame_index`-th (zero indexed) `COMM` or `USLT` frame, depending on
+    /// `frame_id`. Fails if there's no such frame, or if its data couldn't be interpreted.
+    pub fn comment_at(&self, frame_id: &str, user_frame_index: u32) -> Result<&Comment> {
+        self.frame_at(frame_id, user_frame_index)?
+            .body()
+            .as_comment()
+            .ok_or_else(|| Error::MalformedFrame {
+                frame_id: frame_id.to_string(),
+            })
+    }
+
+    /// Adds a `COMM` or `USLT` frame, depending on `frame_id`. If there's already one with the
+    /// same language and description, it's replaced. Fails if the tag's version doesn't support
+    /// the comment's encoding.
+    pub fn set_comment(&mut self, frame_id: &str, comment: Comment) -> Result<()> {
+        self.check_encoding(comment.encoding)?;
+        let position = self.position_of_language_and_description(
+            frame_id,
+            &comment.language,
+            &comment.description,
+        );
+        self.upsert_frame(position, frame_id, FrameBody::comment(frame_id, comment));
+        Ok(())
+    }
+
+    /// Removes the `COMM` or `USLT` frame, depending on `frame_id`, identified by `language`
+    /// and `description`.
+    pub fn remove_comment(
+        &mut self,
+        frame_id: &str,
+        language: &str,
+        description: &str,
+    ) -> Result<()> {
+        self.remove_by_language_and_description(frame_id, language, description)
+    }
+
+    /// The `SYLT` frame identified by `language` and `description`.
+    pub fn synchronised_lyrics(
+        &self,
+        language: &str,
+        description: &str,
+    ) -> Option<&SynchronisedLyrics> {
+        let index = self.position_of_language_and_description("SYLT", language, description)?;
+        match self.frames[index].body() {
+            FrameBody::SynchronisedLyrics(lyrics) => Some(lyrics),
+            _ => None,
+        }
+    }
+
+    /// Adds a `SYLT` frame. If there's already one with the same language and description,
+    /// it's replaced. Fails if the tag's version doesn't support the lyrics' encoding.
+    pub fn set_synchronised_lyrics(&mut self, lyrics: SynchronisedLyrics) -> Result<()> {
+        self.check_encoding(lyrics.encoding)?;
+        let position = self.position_of_language_and_description(
+            "SYLT",
+            &lyrics.language,
+            &lyrics.description,
+        );
+        self.upsert_frame(position, "SYLT", FrameBody::SynchronisedLyrics(lyrics));
+        Ok(())
+    }
+
+    /// Removes the `SYLT` frame identified by `language` and `description`.
+    pub fn remove_synchronised_lyrics(&mut self, language: &str, description: &str) -> Result<()> {
+        self.remove_by_language_and_description("SYLT", language, description)
+    }
+
+    fn remove_by_language_and_description(
+        &mut self,
+        frame_id: &str,
+        language: &str,
+        description: &str,
+    ) -> Result<()> {
+        let index = self
+            .position_of_language_and_description(frame_id, language, description)
+            .ok_or_else(|| Error::KeyedFrameNotFound {
+                frame_id: frame_id.to_string(),
+                key: format!(
+                    "language \"{}\" and description \"{}\"",
+                    language, description
+                ),
+            })?;
+        self.frames.remove(index);
+        Ok(())
+    }
+
+    /// Languages are compared ignoring case.
+    fn position_of_language_and_description(
+        &self,
+        frame_id: &str,
+        language: &str,
+        description: &str,
+    ) -> Option<usize> {
+        self.frames.iter().position(|frame| {
+            frame.id == frame_id
+                && frame.body().language_and_description().is_some_and(
+                    |(frame_language, frame_description)| {
+                        frame_language.eq_ignore_ascii_case(language)
+                            && frame_description == description
+                    },
+                )
+        })
+    }
+
+    /// The `TXXX` frame with the given description. Descriptions are compared ignoring case.
+    pub fn user_text(&self, description: &str) -> Option<&UserText> {
+        let index = self.position_of_description("TXXX", description)?;
+        match self.frames[index].body() {
+            FrameBody::UserText(user_text) => Some(user_text),
+            _ => None,
+        }
+    }
+
+    /// The `WXXX` frame with the given description. Descriptions are compared ignoring case.
+    pub fn user_url(&self, description: &str) -> Option<&UserUrl> {
+        let index = self.position_of_description("WXXX", description)?;
+        match self.frames[index].body() {
+            FrameBody::UserUrl(user_url) => Some(user_url),
+            _ => None,
+        }
+    }
+
+    /// Adds a `TXXX` frame. If there's already one with the same description, it's replaced.
+    /// Fails if the tag's version doesn't support the frame's encoding.
+    pub fn set_user_text(&mut self, user_text: UserText) -> Result<()> {
+        self.check_encoding(user_text.encoding)?;
+        let position = self.position_of_description("TXXX", &user_text.description);
+        self.upsert_frame(position, "TXXX", FrameBody::UserText(user_text));
+        Ok(())
+    }
+
+    /// Adds a `WXXX` frame. If there's already one with the same description, it's replaced.
+    /// Fails if the tag's version doesn't support the frame's encoding.
+    pub fn set_user_url(&mut self, user_url: UserUrl) -> Result<()> {
+        self.check_encoding(user_url.encoding)?;
+        let position = self.position_of_description("WXXX", &user_url.description);
+        self.upsert_frame(position, "WXXX", FrameBody::UserUrl(user_url));
+        Ok(())
+    }
+
+    /// Removes the `TXXX` or `WXXX` frame, depending on `frame_id`, with the given
+    /// description.
+    pub fn remove_user_defined_frame(&mut self, frame_id: &str, description: &str) -> Result<()> {
+        self.remove_by_description(frame_id, description)
+    }
+
+    /// The `GEOB` frame with the given description. Descriptions are compared ignoring case.
+    pub fn encapsulated_object(&self, description: &str) -> Option<&EncapsulatedObject> {
+        let index = self.position_of_description("GEOB", description)?;
+        match self.frames[index].body() {
+            FrameBody::EncapsulatedObject(object) => Some(object),
+            _ => None,
+        }
+    }
+
+    /// Adds a `GEOB` frame. If there's already one with the same description, it's replaced.
+    /// Fails if the tag's version doesn't support the frame's encoding.
+    pub fn set_encapsulated_object(&mut self, object: EncapsulatedObject) -> Result<()> {
+        self.check_encoding(object.encoding)?;
+        let position = self.position_of_description("GEOB", &object.description);
+        self.upsert_frame(position, "GEOB", FrameBody::EncapsulatedObject(object));
+        Ok(())
+    }
+
+    /// Removes the `GEOB` frame with the given description.
+    pub fn remove_encapsulated_object(&mut self, description: &str) -> Result<()> {
+        self.remove_by_description("GEOB", description)
+    }
+
+    fn remove_by_description(&mut self, frame_id: &str, description: &str) -> Result<()> {
+        let index = self
+            .position_of_description(frame_id, description)
+            .ok_or_else(|| Error::KeyedFrameNotFound {
+                frame_id: frame_id.to_string(),
+                key: format!("description \"{}\"", description),
+            })?;
+        self.frames.remove(index);
+        Ok(())
+    }
+
+    fn position_of_description(&self, frame_id: &str, description: &str) -> Option<usize> {
+        self.frames.iter().position(|frame| {
+            frame.id == frame_id
+                && frame.body().description().is_some_and(|frame_description| {
+                    frame_description.eq_ignore_ascii_case(description)
+                })
+        })
+    }
+
+    /// The `POPM` frame of the user identified by `email`. Emails are compared ignoring case.
+    pub fn popularimeter(&self, email: &str) -> Option<&Popularimeter> {
+        let index = self.position_of_email(email)?;
+        match self.frames[index].body() {
+            FrameBody::Popularimeter(popularimeter) => Some(popularimeter),
+            _ => None,
+        }
+    }
+
+    /// Adds a `POPM` frame. If there's already one with the same email, it's replaced.
+    pub fn set_popularimeter(&mut self, popularimeter: Popularimeter) {
+        let position = self.position_of_email(&popularimeter.email);
+        self.upsert_frame(position, "POPM", FrameBody::Popularimeter(popularimeter));
+    }
+
+    /// Removes the `POPM` frame of the user identified by `email`.
+    pub fn remove_popularimeter(&mut self, email: &str) -> Result<()> {
+        let index = self
+            .position_of_email(email)
+            .ok_or_else(|| Error::KeyedFrameNotFound {
+                frame_id: "POPM".to_string(),
+                key: format!("email \"{}\"", email),
+            })?;
+        self.frames.remove(index);
+        Ok(())
+    }
+
+    fn position_of_email(&self, email: &str) -> Option<usize> {
+        self.frames.iter().position(|frame| {
+            matches!(frame.body(), FrameBody::Popularimeter(popularimeter)
+                if popularimeter.email.eq_ignore_ascii_case(email))
+        })
+    }
+
+    /// The `UFID` or `PRIV` frame, depending on `frame_id`, written by `owner`.
+    pub fn owned_data(&self, frame_id: &str, owner: &str) -> Option<&OwnedData> {
+        let index = self.position_of_owner(frame_id, owner)?;
+        self.frames[index].body().as_owned_data()
+    }
+
+    /// Adds a `UFID` or `PRIV` frame, depending on `frame_id`. If there's already one with the
+    /// same owner, it's replaced.
+    pub fn set_owned_data(&mut self, frame_id: &str, owned_data: OwnedData) {
+        let position = self.position_of_owner(frame_id, &owned_data.owner);
+        self.upsert_frame(
+            position,
+            frame_id,
+            FrameBody::owned_data(frame_id, owned_data),
+        );
+    }
+
+    /// Removes the `UFID` or `PRIV` frame, depending on `frame_id`, written by `owner`.
+    pub fn remove_owned_data(&mut self, frame_id: &str, owner: &str) -> Result<()> {
+        let index =
+            self.position_of_owner(frame_id, owner)
+                .ok_or_else(|| Error::KeyedFrameNotFound {
+                    frame_id: frame_id.to_string(),
+                    key: format!("owner \"{}\"", owner),
+                })?;
+        self.frames.remove(index);
+        Ok(())
+    }
+
+    fn position_of_owner(&self, frame_id: &str, owner: &str) -> Option<usize> {
+        self.frames.iter().position(|frame| {
+            frame.id == frame_id
+                && frame
+                    .body()
+                    .as_owned_data()
+                    .is_some_and(|owned_data| owned_data.owner == owner)
+        })
+    }
+
+    /// The `RVA2` frame with the given identification.
+    pub fn relative_volume(&self, identification: &str) -> Option<&RelativeVolume> {
+        let index = self.position_of_identification(identification)?;
+        match self.frames[index].body() {
+            FrameBody::RelativeVolume(relative_volume) => Some(relative_volume),
+            _ => None,
+        }
+    }
+
+    /// Adds an `RVA2` frame. If there's already one with the same identification, it's
+    /// replaced.
+    pub fn set_relative_volume(&mut self, relative_volume: RelativeVolume) {
+        let position = self.position_of_identification(&relative_volume.identification);
+        self.upsert_frame(position, "RVA2", FrameBody::RelativeVolume(relative_volume));
+    }
+
+    /// Removes the `RVA2` frame with the given identification.
+    pub fn remove_relative_volume(&mut self, identification: &str) -> Result<()> {
+        let index = self
+            .position_of_identification(identification)
+            .ok_or_else(|| Error::KeyedFrameNotFound {
+                frame_id: "RVA2".to_string(),
+                key: format!("identification \"{}\"", identification),
+            })?;
+        self.frames.remove(index);
+        Ok(())
+    }
+
+    /// Identifications are compared ignoring case.
+    fn position_of_identification(&self, identification: &str) -> Option<usize> {
+        self.frames.iter().position(|frame| {
+            matches!(frame.body(), FrameBody::RelativeVolume(relative_volume)
+                if relative_volume.identification.eq_ignore_ascii_case(identification))
+        })
+    }
+
+    /// The ReplayGain of `scope`, read from its `TXXX:REPLAYGAIN_*` frames or, if there's no
+    /// valid gain there, from the master volume of its `RVA2` frame.
+    pub fn replay_gain(&self, scope: GainScope) -> Option<ReplayGain> {
+        let user_text = |description: String| {
+            self.user_text(&description)
+                .and_then(|user_text| parse_gain(user_text.text()))
+        };
+        if let Some(gain) = user_text(scope.gain_description()) {
+            return Some(ReplayGain {
+                gain,
+                peak: user_text(scope.peak_description()),
+            });
+        }
+        let master = self.relative_volume(scope.name())?.master()?;
+        Some(ReplayGain {
+            gain: master.gain(),
+            peak: master.peak_ratio(),
+        })
+    }
+
+    /// Writes the ReplayGain of `scope` to its `TXXX:REPLAYGAIN_*` frames and, on ID3v2.4
+    /// tags, to the master volume of its `RVA2` frame. ID3v2.3 has no `RVA2` frame, and its
+    /// `RVAD` frame can't tell tracks and albums apart. The previous peak is removed if
+    /// `replay_gain` has none.
+    pub fn set_replay_gain(&mut self, scope: GainScope, replay_gain: ReplayGain) -> Result<()> {
+        let major_version = self.major_version();
+        self.set_user_text(UserText::new(
+            scope.gain_description(),
+            vec![format_gain(replay_gain.gain)],
+            major_version,
+        ))?;
+        match replay_gain.peak {
+            Some(peak) => self.set_user_text(UserText::new(
+                scope.peak_description(),
+                vec![format!("{:.6}", peak)],
+                major_version,
+            ))?,
+            None => {
+                let _ = self.remove_user_defined_frame("TXXX", &scope.peak_description());
+            }
+        }
+        if major_version >= 4 {
+            self.set_relative_volume(RelativeVolume {
+                identification: scope.name().to_string(),
+                channels: vec![ChannelAdjustment::from_db(
+                    ChannelType::MasterVolume,
+                    replay_gain.gain,
+                    replay_gain.peak,
+                )],
+            });
+        }
+        Ok(())
+    }
+
+    /// Removes the `TXXX:REPLAYGAIN_*` and `RVA2` frames of `scope`. Returns whether there was
+    /// any.
+    pub fn remove_replay_gain(&mut self, scope: GainScope) -> bool {
+        let removed = [
+            self.remove_user_defined_frame("TXXX", &scope.gain_description()),
+            self.remove_user_defined_frame("TXXX", &scope.peak_description()),
+            self.remove_relative_volume(scope.name()),
+        ];
+        removed.iter().any(Result::is_ok)
+    }
+
+    /// The value of the `PCNT` frame: how many times the file was played.
+    pub fn play_count(&self) -> Option<&PlayCounter> {
+        self.frames_with_id("PCNT")
+            .find_map(|frame| match frame.body() {
+                FrameBody::PlayCounter(counter) => Some(counter),
+                _ => None,
+            })
+    }
+
+    /// Replaces the value of the `PCNT` frame, or adds one.
+    pub fn set_play_count(&mut self, counter: PlayCounter) {
+        let position = self.frames.iter().position(|frame| frame.id == "PCNT");
+        self.upsert_frame(position, "PCNT", FrameBody::PlayCounter(counter));
+    }
+
+    /// Counts `plays` more plays in the `PCNT` frame, adding one if there's none, and in the
+    /// counter of the `POPM` frame of `email`, if there's one. Counters grow by a byte instead
+    /// of overflowing.
+    pub fn increment_play_count(&mut self, email: &str, plays: u64) {
+        let mut counter = self.play_count().cloned().unwrap_or_default();
+        counter.add(plays);
+        self.set_play_count(counter);
+        if let Some(index) = self.position_of_email(email) {
+            if let FrameBody::Popularimeter(popularimeter) = self.frames[index].body_mut() {
+                popularimeter
+                    .counter
+                    .get_or_insert_with(PlayCounter::default)
+                    .add(plays);
+            }
+        }
+    }
+
+    /// Replaces the body of the frame at `position`, or appends a new frame if there's none.
+    fn upsert_frame(&mut self, position: Option<usize>, frame_id: &str, body: FrameBody) {
+        match position {
+            Some(index) => self.frames[index].set_body(body),
+            None => self
+                .frames
+                .push(ID3v2Frame::new(frame_id.to_string(), body)),
+        }
+    }
+
+    /// Every chapter in the tag, sorted by start time. `CHAP` frames that couldn't be
+    /// interpreted are skipped.
+    pub fn chapters(&self) -> Vec<&Chapter> {
+        let mut chapters: Vec<&Chapter> = self
+            .frames_with_id("CHAP")
+            .filter_map(|frame| match frame.body() {
+                FrameBody::Chapter(chapter) => Some(chapter),
+                _ => None,
+            })
+            .collect();
+        chapters.sort_by_key(|chapter| chapter.start_time);
+        chapters
+    }
+
+    /// The top level table of contents, which lists the tag's chapters or other tables of
+    /// contents.
+    pub fn table_of_contents(&self) -> Option<&TableOfContents> {
+        self.frames_with_id("CTOC")
+            .find_map(|frame| match frame.body() {
+                FrameBody::TableOfContents(table) if table.top_level => Some(table),
+                _ => None,
+            })
+    }
+
+    /// Replaces every `CHAP` and `CTOC` frame with `chapters`, listed in that order by a
+    /// top level, ordered table of contents with element ID `toc`, or `toc1`, `toc2`... if a
+    /// chapter already uses it.
+    pub fn set_chapters(&mut self, chapters: Vec<Chapter>) {
+        self.remove_chapters();
+        let is_used = |element_id: &str| {
+            chapters
+                .iter()
+                .any(|chapter| chapter.element_id == element_id)
+        };
+        let element_id = std::iter::once("toc".to_string())
+            .chain((1..).map(|index| format!("toc{}", index)))
+            .find(|element_id| !is_used(element_id))
+            .unwrap();
+        let table = TableOfContents {
+            element_id,
+            top_level: true,
+            ordered: true,
+            child_element_ids: chapters
+                .iter()
+                .map(|chapter| chapter.element_id.clone())
+                .collect(),
+            sub_frames: Vec::new(),
+        };
+        self.frames.push(ID3v2Frame::new(
+            "CTOC".to_string(),
+            FrameBody::TableOfContents(table),
+        ));
+        for chapter in chapters {
+            self.frames.push(ID3v2Frame::new(
+                "CHAP".to_string(),
+                FrameBody::Chapter(chapter),
+            ));
+        }
+    }
+
+    /// Removes every `CHAP` and `CTOC` frame. Returns how many were removed.
+    pub fn remove_chapters(&mut self) -> usize {
+        let count = self.frames.len();
+        self.frames
+            .retain(|frame| frame.id != "CHAP" && frame.id != "CTOC");
+        count - self.frames.len()
+    }
+
+    /// Removes the `user_frame_index`-th (zero indexed) frame with ID `frame_id`. If there's
+    /// no such frame, returns [`Error::FrameNotFound`].
+    pub fn remove_frame(&mut self, frame_id: &str, user_frame_index: u32) -> Result<()> {
+        self.find_index_of_frame_with_id(frame_id, user_frame_index)
+            .map(|index| {
+                self.frames.remove(index);
+            })
+    }
+
+    /// Replaces the data of the `user_frame_index`-th (zero indexed) frame with ID `frame_id`.
+    /// Frames identified by a language or a description keep them. If there's no such frame,
+    /// returns [`Error::FrameNotFound`].
+    pub fn edit_frame(
+        &mut self,
+        frame_id: &str,
+        new_data: String,
+        user_frame_index: u32,
+    ) -> Result<()> {
+        let major_version = self.major_version();
+        let index = self.find_index_of_frame_with_id(frame_id, user_frame_index)?;
+        self.frames[index]
+            .body_mut()
+            .set_user_input(frame_id, new_data, major_version);
+        Ok(())
+    }
+
+    fn find_index_of_frame_with_id(&self, frame_id: &str, frame_index: u32) -> Result<usize> {
+        let mut frames_found_counter = 0u32;
+
+        self.frames
+            .iter()
+            .position(|item| {
+                if item.id == frame_id {
+                    if frames_found_counter < frame_index {
+                        frames_found_counter += 1;
+                        false
+                    } else {
+                        true
+                    }
+                } else {
+                    false
+                }
+            })
+            .ok_or_else(|| Error::FrameNotFound {
+                frame_id: frame_id.to_string(),
+                index: frame_index,
+                found: frames_found_counter,
+            })
+    }
+
+    fn calculate_id3v2_size(&self, frames_size: u32) -> u32 {
+        let extended_header_size = self.header.extended_header_size();
+        extended_header_size + frames_size
+    }
+
+    /// Formats every frame as one `ID data` line. The data of `UFID` and `PRIV` frames is shown
+    /// in `binary_format`. When `human_readable` is set, genres are shown by name.
+    pub fn format_frames(
+        &self,
+        frame_flags: bool,
+        human_readable: bool,
+        binary_format: BinaryFormat,
+    ) -> String {
+        self.frames
+            .iter()
+            .map(|frame| -> String {
+                let data = match frame.text() {
+                    Some(text_frame) if human_readable && frame.id == "TCON" => {
+                        let genres = Genre::parse_values(&text_frame.values);
+                        let names: Vec<String> = genres.iter().map(Genre::name).collect();
+                        names.join("; ")
+                    }
+                    _ => frame.body().format_with(binary_format),
+                };
+                if frame_flags {
+                    let mut flags_str = frame.format_flags(human_readable);
+                    if !flags_str.is_empty() {
+                        flags_str.push(' ');
+                    };
+                    format!("{} {}{}", frame.format_id(), flags_str, data)
+                } else {
+                    format!("{} {}", frame.format_id(), data)
+                }
+            })
+            .reduce(|a, b| format!("{}\n{}", a, b))
+            .unwrap_or_else(|| "No frames in file.".to_string())
+    }
+}
+
+/// Reads up to `length` bytes, stopping right after the zero bytes at their start. Returns how
+/// many zero bytes there were.
+fn count_zeros<R: Read + Seek>(reader: &mut R, length: u64) -> Result<u64> {
+    let mut zeros = 0;
+    let mut buffer = [0u8; 4096];
+    while zeros < length {
+        let chunk = &mut buffer[..(length - zeros).min(4096) as usize];
+        let read = reader.read(chunk)?;
+        if read == 0 {
+            break;
+        }
+        match chunk[..read].iter().position(|&byte| byte != 0) {
+            Some(position) => {
+                zeros += position as u64;
+                reader.seek(SeekFrom::Current(position as i64 - read as i64))?;
+                break;
+            }
+            None => zeros += read as u64,
+        }
+    }
+    Ok(zeros)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp3_file::extended_header::TagRestrictions;
+
+    /// A tag with no frames and no padding.
+    fn empty_tag(major_version: u8) -> Tag {
+        Tag::from_bytes(&[b'I', b'D', b'3', major_version, 0, 0, 0, 0, 0, 0]).unwrap()
+    }
+
+    /// The bytes of a tag holding `frames`, already serialized, followed by `padding` zeros.
+    fn tag_bytes(major_version: u8, frames: &[&[u8]], padding: usize) -> Vec<u8> {
+        let body = [frames.concat(), vec![0; padding]].concat();
+        let mut bytes = vec![b'I', b'D', b'3', major_version, 0, 0];
+        bytes.extend(crate::utils::write_syncsafe_integer(body.len() as u32));
+        bytes.extend(body);
+        bytes
+    }
+
+    fn text(tag: &Tag, frame_id: &str) -> String {
+        let text_frame = tag.frames_with_id(frame_id).find_map(ID3v2Frame::text);
+        text_frame.unwrap().text().to_string()
+    }
+
+    #[test]
+    fn padding_is_written_after_the_frames_and_counted_in_the_size() {
+        let title = b"TIT2\0\0\0\x05\0\0\x00Song";
+        let tag = Tag::from_bytes(&tag_bytes(4, &[title], 0)).unwrap();
+
+        let bytes = tag.to_bytes_with_padding(16).unwrap();
+        assert_eq!(bytes, tag_bytes(4, &[title], 16));
+        let padded = Tag::from_bytes(&bytes).unwrap();
+        assert_eq!(padded.frames().len(), 1);
+        assert_eq!(padded.to_bytes().unwrap(), tag.to_bytes().unwrap());
+    }
+
+    #[test]
+    fn reads_and_writes_an_id3v24_tag() {
+        let title = [b"TIT2\0\0\0\x07\0\0\x03Song".as_slice(), "é".as_bytes()].concat();
+        let artists = b"TPE1\0\0\0\x04\0\0\0A\0B";
+        let bytes = tag_bytes(4, &[&title, artists], 0);
+
+        let tag = Tag::from_bytes(&bytes).unwrap();
+        assert_eq!(tag.major_version(), 4);
+        assert_eq!(tag.frames().len(), 2);
+        assert_eq!(text(&tag, "TIT2"), "Songé");
+        let artists = tag.frames_with_id("TPE1").find_map(ID3v2Frame::text);
+        assert_eq!(artists.unwrap().values, ["A", "B"]);
+        assert_eq!(tag.to_bytes().unwrap(), bytes);
+    }
+
+    #[test]
+    fn reads_and_writes_an_id3v23_tag() {
+        let title = b"TIT2\0\0\0\x05\0\0\0Song";
+        let counter = b"PCNT\0\0\0\x04\0\0\0\0\x01\x00";
+        let bytes = tag_bytes(3, &[title, counter], 0);
+
+        let tag = Tag::from_bytes(&bytes).unwrap();
+        assert_eq!(tag.major_version(), 3);
+        assert_eq!(text(&tag, "TIT2"), "Song");
+        assert_eq!(tag.play_count(), Some(&PlayCounter::from(256)));
+        assert_eq!(tag.to_bytes().unwrap(), bytes);
+    }
+
+    #[test]
+    fn reads_and_writes_an_id3v22_tag() {
+        let title = b"TT2\0\0\x05\0Song";
+        let album = b"TAL\0\0\x06\0Album";
+        let bytes = tag_bytes(2, &[title, album], 0);
+
+        let tag = Tag::from_bytes(&bytes).unwrap();
+        assert_eq!(tag.major_version(), 2);
+        assert_eq!(text(&tag, "TIT2"), "Song");
+        assert_eq!(text(&tag, "TALB"), "Album");
+        assert_eq!(tag.to_bytes().unwrap(), bytes);
+    }
+
+    #[test]
+    fn untouched_id3v23_frames_are_written_back_unchanged() {
+        // UTF-16 with a big endian byte order mark, and terminators the encoder wouldn't add
+        let title = b"TIT2\0\0\0\x09\0\0\x01\xFE\xFF\0A\0B\0\0";
+        let album = b"TALB\0\0\0\x04\0\0\0AB\0";
+        let comment = b"COMM\0\0\0\x08\0\0\0engd\0c\0";
+        let bytes = tag_bytes(3, &[title, album, comment], 0);
+
+        let mut tag = Tag::from_bytes(&bytes).unwrap();
+        assert_eq!(text(&tag, "TIT2"), "AB");
+        assert_eq!(text(&tag, "TALB"), "AB");
+        assert_eq!(tag.to_bytes_with_padding(0).unwrap(), bytes);
+
+        // Only the edited frame is encoded again
+        let title = TextFrame::new(TextEncoding::Utf16, vec!["AC".to_string()]);
+        tag.edit_text_frame("TIT2", &title, 0).unwrap();
+        let edited_title = b"TIT2\0\0\0\x07\0\0\x01\xFF\xFEA\0C\0";
+        let edited = tag_bytes(3, &[edited_title, album, comment], 0);
+        assert_eq!(tag.to_bytes().unwrap(), edited);
+    }
+
+    #[test]
+    fn untouched_genres_are_written_back_unchanged() {
+        let genre = b"TCON\0\0\0\x0A\0\0\0(17)Rock\0";
+        let title = b"TIT2\0\0\0\x02\0\0\0A";
+        let bytes = tag_bytes(3, &[genre, title], 0);
+
+        let mut tag = Tag::from_bytes(&bytes).unwrap();
+        assert_eq!(tag.genres(), [Genre::Id3v1(17)]);
+        let title = TextFrame::new(TextEncoding::Latin1, vec!["B".to_string()]);
+        tag.edit_text_frame("TIT2", &title, 0).unwrap();
+        let edited = tag_bytes(3, &[genre, b"TIT2\0\0\0\x02\0\0\0B"], 0);
+        assert_eq!(tag.to_bytes().unwrap(), edited);
+    }
+
+    #[test]
+    fn genres_are_set_the_way_the_version_expects_them() {
+        let genres = [Genre::Id3v1(17), Genre::Custom("(Live)".to_string())];
+        let mut tag = empty_tag(3);
+        tag.set_genres(&genres);
+        let genre = b"TCON\0\0\0\x0B\0\0\0(17)(Live)";
+        assert_eq!(
+            tag.to_bytes_with_padding(0).unwrap(),
+            tag_bytes(3, &[genre], 0)
+        );
+        assert_eq!(tag.genres(), genres);
+
+        let mut tag = empty_tag(4);
+        tag.set_genres(&genres);
+        let genre = b"TCON\0\0\0\x0A\0\0\x0317\0(Live)";
+        assert_eq!(
+            tag.to_bytes_with_padding(0).unwrap(),
+            tag_bytes(4, &[genre], 0)
+        );
+        assert_eq!(tag.genres(), genres);
+
+        // Refinements that would be read as references are escaped
+        let genres = [Genre::Custom("(17) Live".to_string())];
+        let mut tag = empty_tag(3);
+        tag.set_genres(&genres);
+        let tag = Tag::from_bytes(&tag.to_bytes().unwrap()).unwrap();
+        assert_eq!(text(&tag, "TCON"), "((17) Live");
+        assert_eq!(tag.genres(), genres);
+    }
+
+    #[test]
+    fn converted_genres_keep_their_refinement() {
+        let genre = b"TCON\0\0\0\x12\0\0\0(17)(RX)Eurodisco";
+        let mut tag = Tag::from_bytes(&tag_bytes(3, &[genre], 0)).unwrap();
+        let genres = [
+            Genre::Id3v1(17),
+            Genre::Remix,
+            Genre::Custom("Eurodisco".to_string()),
+        ];
+        assert_eq!(tag.genres(), genres);
+
+        tag.convert(4).unwrap();
+        let mut tag = Tag::from_bytes(&tag.to_bytes().unwrap()).unwrap();
+        let text_frame = tag.frames_with_id("TCON").find_map(ID3v2Frame::text);
+        assert_eq!(text_frame.unwrap().values, ["17", "RX", "Eurodisco"]);
+        tag.convert(3).unwrap();
+        let tag = Tag::from_bytes(&tag.to_bytes().unwrap()).unwrap();
+        assert_eq!(text(&tag, "TCON"), "(17)(RX)Eurodisco");
+    }
+
+    #[test]
+    fn reading_stops_at_the_end_of_the_tag() {
+        let mut bytes = tag_bytes(4, &[b"TIT2\0\0\0\x02\0\0\0A"], 7);
+        let tag_length = bytes.len() as u64;
+        bytes.extend([0xFF, 0xFB, 0x90, 0x00]);
+
+        let mut reader = Cursor::new(bytes.as_slice());
+        let tag = Tag::from_reader(&mut reader).unwrap();
+        assert_eq!(reader.position(), tag_length);
+        assert_eq!(tag.frames().len(), 1);
+        assert_eq!(tag.padding(), 7);
+        assert!(tag.garbage().is_none());
+    }
+
+    #[test]
+    fn a_frame_overrunning_the_tag_is_garbage() {
+        let title = b"TIT2\0\0\0\x02\0\0\0A";
+        let album = b"TALB\0\0\0\x20\0\0\0B";
+        let mut bytes = tag_bytes(4, &[title, album], 0);
+        let tag_length = bytes.len() as u64;
+        bytes.extend([0xFF, 0xFB, 0x90, 0x00]);
+
+        let mut reader = Cursor::new(bytes.as_slice());
+        let tag = Tag::from_reader(&mut reader).unwrap();
+        assert_eq!(reader.position(), tag_length);
+        assert_eq!(tag.frames().len(), 1);
+        assert_eq!(tag.padding(), 0);
+        let garbage = tag.garbage().unwrap();
+        assert_eq!((garbage.offset, garbage.length), (22, 12));
+        assert_eq!(
+            garbage.reason,
+            "frame \"TALB\" claims 32 bytes, but only 2 are left in the tag"
+        );
+        // Garbage is dropped when the tag is written
+        let written = tag.to_bytes_with_padding(0).unwrap();
+        assert_eq!(written, tag_bytes(4, &[title], 0));
+    }
+
+    #[test]
+    fn bytes_that_are_not_a_frame_are_garbage() {
+        let title = b"TIT2\0\0\0\x02\0\0\0A";
+        let bytes = tag_bytes(3, &[title, b"\xFF\xFB\x90\x00tag\0\0\0\0\0"], 4);
+
+        let tag = Tag::from_bytes(&bytes).unwrap();
+        assert_eq!(tag.frames().len(), 1);
+        assert_eq!(text(&tag, "TIT2"), "A");
+        assert_eq!(tag.padding(), 0);
+        let garbage = tag.garbage().unwrap();
+        assert_eq!((garbage.offset, garbage.length), (22, 16));
+        assert_eq!(garbage.reason, "[FF, FB, 90, 00] is not a frame ID");
+
+        // So are a few bytes too short for a frame header
+        let bytes = tag_bytes(3, &[title, b"TALB\0\0"], 0);
+        let garbage = Tag::from_bytes(&bytes).unwrap().garbage().cloned().unwrap();
+        assert_eq!((garbage.offset, garbage.length), (22, 6));
+        assert_eq!(garbage.reason, "6 bytes are too few for a frame");
+    }
+
+    #[test]
+    fn padding_that_is_not_all_zeros_ends_in_garbage() {
+        let title = b"TIT2\0\0\0\x02\0\0\0A";
+        let bytes = tag_bytes(4, &[title, b"\0\0\0\x01\0"], 0);
+
+        let tag = Tag::from_bytes(&bytes).unwrap();
+        assert_eq!(tag.frames().len(), 1);
+        assert_eq!(tag.padding(), 3);
+        let garbage = tag.garbage().unwrap();
+        assert_eq!((garbage.offset, garbage.length), (25, 2));
+        assert_eq!(garbage.reason, "the padding is not made of zeros");
+        let written = tag.to_bytes_with_padding(5).unwrap();
+        assert_eq!(written, tag_bytes(4, &[title], 5));
+    }
+
+    #[test]
+    fn reads_and_writes_an_id3v23_extended_header() {
+        let title = b"TIT2\0\0\0\x02\0\0\0A";
+        let mut tag = Tag::from_bytes(&tag_bytes(3, &[title], 0)).unwrap();
+        let extended_header = ExtendedHeader {
+            is_update: true,
+            has_crc: true,
+            restrictions: Some(TagRestrictions::default()),
+        };
+        tag.set_extended_header(Some(extended_header)).unwrap();
+        let bytes = tag.to_bytes_with_padding(4).unwrap();
+        assert_eq!(bytes[5], 0b0100_0000);
+        assert_eq!(bytes[10..20], [0, 0, 0, 10, 0x80, 0, 0, 0, 0, 4]);
+        assert_eq!(bytes[20..24], crc32(title).to_be_bytes());
+
+        // ID3v2.3 tags can't hold the update flag and the restrictions
+        let tag = Tag::from_bytes(&bytes).unwrap();
+        let expected = ExtendedHeader {
+            has_crc: true,
+            ..ExtendedHeader::default()
+        };
+        assert_eq!(tag.extended_header(), Some(&expected));
+        assert_eq!(tag.header.crc(), Some(crc32(title)));
+        assert_eq!(text(&tag, "TIT2"), "A");
+        assert_eq!(tag.padding(), 4);
+        assert_eq!(tag.to_bytes_with_padding(4).unwrap(), bytes);
+    }
+
+    #[test]
+    fn reads_and_writes_an_id3v24_extended_header() {
+        let title = b"TIT2\0\0\0\x02\0\0\0A";
+        let mut tag = Tag::from_bytes(&tag_bytes(4, &[title], 0)).unwrap();
+        let extended_header = ExtendedHeader {
+            is_update: true,
+            has_crc: true,
+            restrictions: Some(TagRestrictions {
+                tag_size: 2,
+                text_encoding: true,
+                text_field_size: 3,
+                image_encoding: false,
+                image_size: 1,
+            }),
+        };
+        tag.set_extended_header(Some(extended_header)).unwrap();
+        let bytes = tag.to_bytes_with_padding(4).unwrap();
+        assert_eq!(bytes[10..16], [0, 0, 0, 15, 1, 0b0111_0000]);
+        assert_eq!(bytes[23..25], [1, 0b1011_1001]);
+
+        let tag = Tag::from_bytes(&bytes).unwrap();
+        assert_eq!(tag.extended_header(), Some(&extended_header));
+        // ID3v2.4 CRCs cover the padding too
+        let crc = crc32(&[title.as_slice(), &[0; 4]].concat());
+        assert_eq!(tag.header.crc(), Some(crc));
+        assert_eq!(text(&tag, "TIT2"), "A");
+        assert_eq!(tag.padding(), 4);
+        assert_eq!(tag.to_bytes_with_padding(4).unwrap(), bytes);
+    }
+
+    #[test]
+    fn a_crc_mismatch_does_not_stop_reading() {
+        let title = b"TIT2\0\0\0\x02\0\0\0A";
+        let mut tag = Tag::from_bytes(&tag_bytes(3, &[title], 0)).unwrap();
+        let extended_header = ExtendedHeader {
+            has_crc: true,
+            ..ExtendedHeader::default()
+        };
+        tag.set_extended_header(Some(extended_header)).unwrap();
+        let mut bytes = tag.to_bytes().unwrap();
+        *bytes.last_mut().unwrap() = b'B';
+
+        let tag = Tag::from_bytes(&bytes).unwrap();
+        assert_eq!(tag.extended_header(), Some(&extended_header));
+        assert_eq!(tag.header.crc(), Some(crc32(title)));
+        assert_eq!(text(&tag, "TIT2"), "B");
+        // The CRC is calculated again when the tag is written
+        let written = tag.to_bytes().unwrap();
+        let title = b"TIT2\0\0\0\x02\0\0\0B";
+        assert_eq!(written[20..24], crc32(title).to_be_bytes());
+        assert_eq!(
+            Tag::from_bytes(&written).unwrap().header.crc(),
+            Some(crc32(title))
+        );
+    }
+
+    #[test]
+    fn a_truncated_extended_header_reports_where_it_starts() {
+        let mut bytes = b"junk".to_vec();
+        bytes.extend(tag_bytes(3, &[b"\0\0\0\x06\0"], 0));
+        bytes[9] = 0b0100_0000;
+        let mut reader = Cursor::new(bytes);
+        reader.set_position(4);
+        assert!(matches!(
+            Tag::from_reader(&mut reader),
+            Err(Error::TruncatedExtendedHeader { offset: 14 })
+        ));
+    }
+
+    #[test]
+    fn an_extended_header_larger_than_the_tag_is_truncated() {
+        let mut bytes = tag_bytes(4, &[b"\x7F\x7F\x7F\x7F\x01\0"], 4);
+        bytes[5] = 0b0100_0000;
+        // What follows the tag isn't part of the extended header, however big it says it is
+        bytes.extend([0; 100]);
+        assert!(matches!(
+            Tag::from_bytes(&bytes),
+            Err(Error::TruncatedExtendedHeader { offset: 10 })
+        ));
+    }
+
+    #[test]
+    fn the_footer_is_skipped_and_not_written_back() {
+        let title = b"TIT2\0\0\0\x02\0\0\0A";
+        let mut bytes = tag_bytes(4, &[title], 0);
+        bytes[5] = 0b0001_0000;
+        let footer = [b"3DI".as_slice(), &bytes[3..10]].concat();
+        bytes.extend(footer);
+        bytes.extend([0xFF, 0xFB]);
+
+        let mut reader = Cursor::new(&bytes);
+        let tag = Tag::from_reader(&mut reader).unwrap();
+        assert_eq!(reader.position(), bytes.len() as u64 - 2);
+        assert_eq!(tag.footer_size(), 10);
+        assert_eq!(text(&tag, "TIT2"), "A");
+        assert_eq!(tag.to_bytes().unwrap(), tag_bytes(4, &[title], 0));
+    }
+
+    #[test]
+    fn the_table_of_contents_does_not_reuse_a_chapter_element_id() {
+        let mut tag = empty_tag(4);
+        let chapters = vec![
+            Chapter::new("toc".to_string(), 0, 1000),
+            Chapter::new("toc1".to_string(), 1000, 2000),
+        ];
+        tag.set_chapters(chapters.clone());
+        let tag = Tag::from_bytes(&tag.to_bytes().unwrap()).unwrap();
+        let table = tag
+            .frames_with_id("CTOC")
+            .find_map(|frame| match frame.body() {
+                FrameBody::TableOfContents(table) => Some(table),
+                _ => None,
+            });
+        let table = table.unwrap();
+        assert_eq!(table.element_id, "toc2");
+        assert_eq!(table.child_element_ids, ["toc", "toc1"]);
+        assert_eq!(tag.chapters(), chapters.iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn plays_are_counted_in_the_play_counter_and_the_popularimeter() {
+        let popularimeter = b"POPM\0\0\0\x03\0\0a\0\xFF";
+        let counter = b"PCNT\0\0\0\x04\0\0\xFF\xFF\xFF\xFF";
+        let mut tag = Tag::from_bytes(&tag_bytes(4, &[popularimeter, counter], 0)).unwrap();
+        tag.increment_play_count("a", 2);
+        tag.increment_play_count("b", 1);
+        let tag = Tag::from_bytes(&tag.to_bytes().unwrap()).unwrap();
+        assert_eq!(tag.play_count().unwrap().to_u64(), Some(0x1_0000_0002));
+        let popularimeter = tag.popularimeter("a").unwrap();
+        assert_eq!(popularimeter.counter, Some(PlayCounter::from(2)));
+        assert!(tag.popularimeter("b").is_none());
+    }
+
+    #[test]
+    fn comments_are_addressed_by_language_and_description() {
+        let mut tag = empty_tag(3);
+        let comment = |language: &str, description: &str, text: &str| {
+            Comment::new(
+                language.to_string(),
+                description.to_string(),
+                text.to_string(),
+                3,
+            )
+        };
+        tag.set_comment("COMM", comment("eng", "", "a")).unwrap();
+        tag.set_comment("COMM", comment("fra", "", "b")).unwrap();
+        tag.set_comment("COMM", comment("eng", "d", "c")).unwrap();
+        tag.set_comment("USLT", comment("eng", "", "lyrics"))
+            .unwrap();
+        tag.set_comment("COMM", comment("eng", "", "replaced"))
+            .unwrap();
+        assert_eq!(tag.frames().len(), 4);
+        assert_eq!(tag.comment("COMM", "eng", "").unwrap().text, "replaced");
+        assert_eq!(tag.comment("COMM", "fra", "").unwrap().text, "b");
+        assert_eq!(tag.comment("COMM", "eng", "d").unwrap().text, "c");
+        assert_eq!(tag.comment("USLT", "eng", "").unwrap().text, "lyrics");
+        assert_eq!(tag.comment_at("COMM", 1).unwrap().text, "b");
+
+        tag.remove_comment("COMM", "eng", "").unwrap();
+        assert!(tag.comment("COMM", "eng", "").is_none());
+        assert!(matches!(
+            tag.remove_comment("COMM", "eng", ""),
+            Err(Error::KeyedFrameNotFound { .. })
+        ));
+        // ID3v2.3 tags can't hold UTF-8
+        let utf8 = Comment {
+            encoding: TextEncoding::Utf8,
+            ..comment("eng", "", "a")
+        };
+        assert!(matches!(
+            tag.set_comment("COMM", utf8),
+            Err(Error::UnsupportedEncoding { version: 3, .. })
+        ));
+    }
+
+    #[test]
+    fn user_defined_frames_are_addressed_by_description() {
+        let mut tag = empty_tag(4);
+        let user_text = |description: &str, value: &str| {
+            UserText::new(description.to_string(), vec![value.to_string()], 4)
+        };
+        tag.set_user_text(user_text("MOOD", "calm")).unwrap();
+        tag.set_user_text(user_text("Tempo", "slow")).unwrap();
+        let user_url = UserUrl::new("MOOD".to_string(), "https://example.com".to_string(), 4);
+        tag.set_user_url(user_url).unwrap();
+        // Descriptions are compared ignoring case
+        tag.set_user_text(user_text("mood", "happy")).unwrap();
+        assert_eq!(tag.frames().len(), 3);
+        assert_eq!(tag.user_text("Mood").unwrap().text(), "happy");
+        assert_eq!(tag.user_text("TEMPO").unwrap().text(), "slow");
+        assert_eq!(tag.user_url("mood").unwrap().url, "https://example.com");
+
+        tag.remove_user_defined_frame("TXXX", "MOOD").unwrap();
+        assert!(tag.user_text("mood").is_none());
+        assert!(tag.user_url("mood").is_some());
+        assert!(tag.remove_user_defined_frame("TXXX", "mood").is_err());
+    }
+
+    #[test]
+    fn owned_data_is_addressed_by_frame_id_and_owner() {
+        let mut tag = empty_tag(4);
+        let owned_data = |owner: &str, data: &[u8]| OwnedData {
+            owner: owner.to_string(),
+            data: data.to_vec(),
+        };
+        tag.set_owned_data("UFID", owned_data("a", b"1"));
+        tag.set_owned_data("PRIV", owned_data("a", b"2"));
+        tag.set_owned_data("UFID", owned_data("b", b"3"));
+        tag.set_owned_data("UFID", owned_data("a", b"4"));
+        let tag = Tag::from_bytes(&tag.to_bytes().unwrap()).unwrap();
+        assert_eq!(tag.frames().len(), 3);
+        assert_eq!(tag.owned_data("UFID", "a").unwrap().data, b"4");
+        assert_eq!(tag.owned_data("PRIV", "a").unwrap().data, b"2");
+        assert_eq!(tag.owned_data("UFID", "b").unwrap().data, b"3");
+        // Owners are compared exactly
+        assert!(tag.owned_data("UFID", "A").is_none());
+
+        let mut tag = tag;
+        tag.remove_owned_data("UFID", "a").unwrap();
+        assert!(tag.owned_data("UFID", "a").is_none());
+        assert!(tag.owned_data("PRIV", "a").is_some());
+        assert!(matches!(
+            tag.remove_owned_data("UFID", "a"),
+            Err(Error::KeyedFrameNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn encapsulated_objects_are_addressed_by_description() {
+        let mut tag = empty_tag(3);
+        let object = |description: &str, data: &[u8]| {
+            EncapsulatedObject::new(
+                "text/plain".to_string(),
+                "notes.txt".to_string(),
+                description.to_string(),
+                data.to_vec(),
+                3,
+            )
+        };
+        tag.set_encapsulated_object(object("Notes", b"1")).unwrap();
+        tag.set_encapsulated_object(object("Other", b"2")).unwrap();
+        tag.set_encapsulated_object(object("notes", b"3")).unwrap();
+        let mut tag = Tag::from_bytes(&tag.to_bytes().unwrap()).unwrap();
+        assert_eq!(tag.frames().len(), 2);
+        assert_eq!(tag.encapsulated_object("NOTES").unwrap().data, b"3");
+        assert_eq!(tag.encapsulated_object("other").unwrap().data, b"2");
+        tag.remove_encapsulated_object("Other").unwrap();
+        assert!(tag.encapsulated_object("other").is_none());
+        assert!(tag.remove_encapsulated_object("Other").is_err());
+    }
+
+    #[test]
+    fn id3v23_tags_hold_timestamps_in_tyer_tdat_and_time() {
+        let mut tag = empty_tag(3);
+        let timestamp = Timestamp::parse("2004-07-12T18:30:05").unwrap();
+        tag.set_timestamp("TDRC", &timestamp);
+        tag.set_timestamp("TDOR", &Timestamp::parse("2001-05").unwrap());
+        assert_eq!(text(&tag, "TYER"), "2004");
+        assert_eq!(text(&tag, "TDAT"), "1207");
+        assert_eq!(text(&tag, "TIME"), "1830");
+        assert_eq!(text(&tag, "TORY"), "2001");
+        assert_eq!(tag.timestamp("TDRC"), Some(timestamp.truncated_for_v23()));
+        assert_eq!(tag.timestamp("TDOR"), Some(Timestamp::from_year(2001)));
+
+        // A timestamp without a date removes the old one
+        tag.set_timestamp("TDRC", &Timestamp::from_year(2005));
+        assert!(tag.frames_with_id("TDAT").next().is_none());
+        assert!(tag.frames_with_id("TIME").next().is_none());
+        assert_eq!(tag.timestamp("TDRC"), Some(Timestamp::from_year(2005)));
+
+        let mut tag = empty_tag(4);
+        tag.set_timestamp("TDRC", &timestamp);
+        assert_eq!(text(&tag, "TDRC"), "2004-07-12T18:30:05");
+        assert_eq!(tag.timestamp("TDRC"), Some(timestamp));
+    }
+
+    #[test]
+    fn writing_a_frame_with_a_short_id_fails() {
+        let mut tag = empty_tag(4);
+        tag.push_frame(ID3v2Frame::new(
+            "AB".to_string(),
+            FrameBody::Unknown(vec![1]),
+        ));
+        assert!(matches!(
+            tag.to_bytes(),
+            Err(Error::UnwritableFrameId { frame_id, version: 4 }) if frame_id == "AB"
+        ));
+    }
+
+    #[test]
+    fn writing_a_chapter_with_a_short_sub_frame_id_fails() {
+        let mut tag = empty_tag(3);
+        let mut chapter = Chapter::new("ch0".to_string(), 0, 1000);
+        chapter.sub_frames.push(ID3v2Frame::new(
+            "TIT".to_string(),
+            FrameBody::Unknown(vec![]),
+        ));
+        tag.push_frame(ID3v2Frame::new(
+            "CHAP".to_string(),
+            FrameBody::Chapter(chapter),
+        ));
+        assert!(matches!(
+            tag.to_bytes(),
+            Err(Error::UnwritableFrameId { version: 3, .. })
+        ));
+    }
+}
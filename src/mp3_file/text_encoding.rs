@@ -0,0 +1,149 @@
+use std::fmt;
+
+/// The encodings text can be stored in, as indicated by the first byte of text frames.
+/// ID3v2.2 and ID3v2.3 only define [`TextEncoding::Latin1`] and [`TextEncoding::Utf16`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextEncoding {
+    /// ISO-8859-1. Characters outside of it are replaced by `?` when encoding.
+    Latin1 = 0,
+    /// UTF-16 starting with a byte order mark. Written as little endian.
+    Utf16 = 1,
+    /// UTF-16 big endian without a byte order mark (ID3v2.4 only).
+    Utf16BE = 2,
+    /// UTF-8 (ID3v2.4 only).
+    Utf8 = 3,
+}
+
+impl TextEncoding {
+    pub fn from_byte(byte: u8) -> Option<TextEncoding> {
+        match byte {
+            0 => Some(TextEncoding::Latin1),
+            1 => Some(TextEncoding::Utf16),
+            2 => Some(TextEncoding::Utf16BE),
+            3 => Some(TextEncoding::Utf8),
+            _ => None,
+        }
+    }
+
+    /// Whether tags of the given major version can use this encoding.
+    pub fn is_supported_by(&self, major_version: u8) -> bool {
+        major_version >= 4 || matches!(self, TextEncoding::Latin1 | TextEncoding::Utf16)
+    }
+
+    /// The best encoding for `values` on a tag of the given version: UTF-8 for ID3v2.4, and
+    /// ISO-8859-1 or UTF-16 if some character needs it for older versions.
+    pub fn default_for<S: AsRef<str>>(values: &[S], major_version: u8) -> TextEncoding {
+        if major_version >= 4 {
+            TextEncoding::Utf8
+        } else if values
+            .iter()
+            .all(|value| TextEncoding::Latin1.can_encode(value.as_ref()))
+        {
+            TextEncoding::Latin1
+        } else {
+            TextEncoding::Utf16
+        }
+    }
+
+    /// This encoding if tags of the given major version support it, or UTF-16 otherwise.
+    pub fn or_supported_by(self, major_version: u8) -> TextEncoding {
+        if self.is_supported_by(major_version) {
+            self
+        } else {
+            TextEncoding::Utf16
+        }
+    }
+
+    /// The string terminator, which is one null character in this encoding.
+    pub fn terminator(&self) -> &'static [u8] {
+        match self {
+            TextEncoding::Latin1 | TextEncoding::Utf8 => &[0],
+            TextEncoding::Utf16 | TextEncoding::Utf16BE => &[0, 0],
+        }
+    }
+
+    /// Decodes `bytes`, which must not include a terminator. Invalid sequences are replaced
+    /// by U+FFFD.
+    pub fn decode(&self, bytes: &[u8]) -> String {
+        match self {
+            TextEncoding::Latin1 => bytes.iter().map(|byte| *byte as char).collect(),
+            TextEncoding::Utf8 => String::from_utf8_lossy(bytes).to_string(),
+            TextEncoding::Utf16BE => decode_utf16(bytes, u16::from_be_bytes),
+            TextEncoding::Utf16 => match bytes {
+                [0xFF, 0xFE, rest @ ..] => decode_utf16(rest, u16::from_le_bytes),
+                [0xFE, 0xFF, rest @ ..] => decode_utf16(rest, u16::from_be_bytes),
+                // No byte order mark. Big endian is the default according to the unicode spec
+                _ => decode_utf16(bytes, u16::from_be_bytes),
+            },
+        }
+    }
+
+    /// Encodes `text`, without a terminator.
+    pub fn encode(&self, text: &str) -> Vec<u8> {
+        match self {
+            TextEncoding::Latin1 => text
+                .chars()
+                .map(|char| {
+                    if (char as u32) < 0x100 {
+                        char as u8
+                    } else {
+                        b'?'
+                    }
+                })
+                .collect(),
+            TextEncoding::Utf8 => text.as_bytes().to_vec(),
+            TextEncoding::Utf16BE => text.encode_utf16().flat_map(u16::to_be_bytes).collect(),
+            TextEncoding::Utf16 => [0xFF, 0xFE]
+                .into_iter()
+                .chain(text.encode_utf16().flat_map(u16::to_le_bytes))
+                .collect(),
+        }
+    }
+
+    /// Whether `text` can be encoded without losing characters.
+    pub fn can_encode(&self, text: &str) -> bool {
+        match self {
+            TextEncoding::Latin1 => text.chars().all(|char| (char as u32) < 0x100),
+            _ => true,
+        }
+    }
+
+    /// Splits `bytes` at the first terminator. Returns the bytes before it and the bytes after
+    /// it. If there's no terminator, all bytes are returned as the first element.
+    pub fn split_terminated<'a>(&self, bytes: &'a [u8]) -> (&'a [u8], &'a [u8]) {
+        let position = match self {
+            TextEncoding::Latin1 | TextEncoding::Utf8 => bytes.iter().position(|byte| *byte == 0),
+            // UTF-16 terminators are aligned to 2 bytes
+            TextEncoding::Utf16 | TextEncoding::Utf16BE => bytes
+                .chunks_exact(2)
+                .position(|unit| unit == [0, 0])
+                .map(|position| position * 2),
+        };
+        match position {
+            Some(position) => (
+                &bytes[..position],
+                &bytes[position + self.terminator().len()..],
+            ),
+            None => (bytes, &[]),
+        }
+    }
+}
+
+impl fmt::Display for TextEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TextEncoding::Latin1 => "ISO-8859-1",
+            TextEncoding::Utf16 => "UTF-16",
+            TextEncoding::Utf16BE => "UTF-16BE",
+            TextEncoding::Utf8 => "UTF-8",
+        })
+    }
+}
+
+fn decode_utf16(bytes: &[u8], from_bytes: fn([u8; 2]) -> u16) -> String {
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|unit| from_bytes([unit[0], unit[1]]))
+        .collect();
+    String::from_utf16_lossy(&units)
+}
@@ -1,5 +1,6 @@
-pub mod log;
+//! Low level helpers shared by the parser, the writer and the command line tool.
 
+/// Returns whether the bit at `bit_index` (0 being the least significant bit) is set.
 pub fn check_bit(byte: u8, bit_index: u8) -> bool {
     (byte >> bit_index) & 1 == 1
 }
@@ -9,16 +10,114 @@ pub fn read_syncsafe_integer(bytes: &[u8; 4]) -> u32 {
     (bytes[3] as u32) | (bytes[2] as u32) << 7 | (bytes[1] as u32) << 14 | (bytes[0] as u32) << 21
 }
 
+/// Encodes `number` as a syncsafe integer. Only the lower 28 bits of `number` are kept.
 pub fn write_syncsafe_integer(number: u32) -> [u8; 4] {
     [
         (((number << 3) & 0b01111111_00000000_00000000_00000000) >> 24) as u8,
         (((number << 2) & 0b00000000_01111111_00000000_00000000) >> 16) as u8,
         (((number << 1) & 0b00000000_00000000_01111111_00000000) >> 8) as u8,
-        (((number << 0) & 0b00000000_00000000_00000000_01111111) >> 0) as u8,
+        (number & 0b00000000_00000000_00000000_01111111) as u8,
     ]
 }
 
+/// Returns whether none of the bytes have their MSB set.
 pub fn is_valid_syncsafe_integer(bytes: &[u8; 4]) -> bool {
     // Check if any byte has the last bit set
     (bytes[0] | bytes[1] | bytes[2] | bytes[3]) >> 7 == 0
 }
+
+/// The CRC-32 used by ID3v2 (ISO-3309, the same one used by zlib and PNG).
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xFFFFFFFFu32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB88320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// How binary data is shown as text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryFormat {
+    /// Two lowercase hexadecimal digits per byte.
+    Hex,
+    /// Standard base64, with padding.
+    Base64,
+}
+
+impl BinaryFormat {
+    pub fn encode(&self, bytes: &[u8]) -> String {
+        match self {
+            BinaryFormat::Hex => bytes.iter().map(|byte| format!("{:02x}", byte)).collect(),
+            BinaryFormat::Base64 => encode_base64(bytes),
+        }
+    }
+
+    /// Returns `None` if `text` is not valid in this format. Whitespace is ignored.
+    pub fn decode(&self, text: &str) -> Option<Vec<u8>> {
+        let text: Vec<u8> = text
+            .bytes()
+            .filter(|byte| !byte.is_ascii_whitespace())
+            .collect();
+        match self {
+            BinaryFormat::Hex => decode_hex(&text),
+            BinaryFormat::Base64 => decode_base64(&text),
+        }
+    }
+}
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn encode_base64(bytes: &[u8]) -> String {
+    let mut result = String::new();
+    for chunk in bytes.chunks(3) {
+        let group = (chunk[0] as u32) << 16
+            | (*chunk.get(1).unwrap_or(&0) as u32) << 8
+            | *chunk.get(2).unwrap_or(&0) as u32;
+        for index in 0..4 {
+            if index <= chunk.len() {
+                let sextet = (group >> (18 - 6 * index)) & 0b11_1111;
+                result.push(BASE64_ALPHABET[sextet as usize] as char);
+            } else {
+                result.push('=');
+            }
+        }
+    }
+    result
+}
+
+fn decode_base64(text: &[u8]) -> Option<Vec<u8>> {
+    let text = text
+        .strip_suffix(b"==")
+        .or(text.strip_suffix(b"="))
+        .unwrap_or(text);
+    let mut result = Vec::new();
+    for chunk in text.chunks(4) {
+        if chunk.len() == 1 {
+            return None;
+        }
+        let mut group = 0u32;
+        for (index, char) in chunk.iter().enumerate() {
+            let sextet = BASE64_ALPHABET.iter().position(|item| item == char)? as u32;
+            group |= sextet << (18 - 6 * index);
+        }
+        result.extend(&group.to_be_bytes()[1..chunk.len()]);
+    }
+    Some(result)
+}
+
+fn decode_hex(text: &[u8]) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) || !text.iter().all(u8::is_ascii_hexdigit) {
+        return None;
+    }
+    text.chunks(2)
+        .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok())
+        .collect()
+}
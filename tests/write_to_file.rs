@@ -0,0 +1,185 @@
+//! Round trips through `Mp3File::write_to_file`, checking that the audio that follows the tag
+//! comes out byte for byte the same whether the tag is rewritten in place or the whole file is.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use id3v2::utils::read_syncsafe_integer;
+use id3v2::{Mp3File, Tag, TextEncoding, TextFrame, WriteOptions};
+
+/// A file in the temporary directory that is removed when dropped.
+struct TestFile {
+    path: PathBuf,
+}
+
+impl TestFile {
+    fn new(name: &str, contents: &[u8]) -> TestFile {
+        let path = std::env::temp_dir().join(format!("id3v2-{}-{}.mp3", name, std::process::id()));
+        fs::write(&path, contents).unwrap();
+        TestFile { path }
+    }
+
+    fn bytes(&self) -> Vec<u8> {
+        fs::read(&self.path).unwrap()
+    }
+
+    /// Everything after the tag, as declared by its header.
+    fn audio(&self) -> Vec<u8> {
+        let bytes = self.bytes();
+        let size = read_syncsafe_integer(bytes[6..10].try_into().unwrap()) as usize;
+        bytes[10 + size..].to_vec()
+    }
+}
+
+impl Drop for TestFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Bytes that look nothing like a frame, starting with an MPEG frame sync. The length is not a
+/// multiple of any buffer size a copy loop could use.
+fn audio(length: usize) -> Vec<u8> {
+    let mut audio: Vec<u8> = (0..length).map(|i| (i * 31 % 251) as u8).collect();
+    audio[..2].copy_from_slice(&[0xFF, 0xFB]);
+    audio
+}
+
+fn text_frame(text: &str) -> TextFrame {
+    TextFrame::new(TextEncoding::Latin1, vec![text.to_string()])
+}
+
+/// An ID3v2.4 tag with a title, followed by `padding` bytes of padding and `audio`.
+fn mp3(title: &str, padding: u32, audio: &[u8]) -> Vec<u8> {
+    let mut tag = Tag::from_bytes(&[b'I', b'D', b'3', 4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]).unwrap();
+    tag.add_text_frame("TIT2".to_string(), &text_frame(title))
+        .unwrap();
+    let mut bytes = tag.to_bytes_with_padding(padding).unwrap();
+    bytes.extend(audio);
+    bytes
+}
+
+fn title(path: &Path) -> String {
+    let mp3_file = Mp3File::from_path(path).unwrap();
+    let title = mp3_file
+        .frames_with_id("TIT2")
+        .find_map(|frame| frame.text());
+    title.unwrap().text().to_string()
+}
+
+#[test]
+fn rewriting_the_file_keeps_the_audio() {
+    let audio = audio(100_003);
+    let file = TestFile::new("rewrite", &mp3("Title", 16, &audio));
+
+    let mut mp3_file = Mp3File::from_path(&file.path).unwrap();
+    mp3_file
+        .add_text_frame("TIT3".to_string(), &text_frame(&"long ".repeat(20)))
+        .unwrap();
+    mp3_file.write_to_file(&file.path).unwrap();
+
+    assert_eq!(file.audio(), audio);
+    assert_eq!(title(&file.path), "Title");
+}
+
+#[test]
+fn rewriting_in_place_keeps_the_audio_and_the_size() {
+    let audio = audio(40_000);
+    let file = TestFile::new("in-place", &mp3("Title", 512, &audio));
+    let size = file.bytes().len();
+
+    let mut mp3_file = Mp3File::from_path(&file.path).unwrap();
+    mp3_file
+        .edit_text_frame("TIT2", &text_frame("A longer title"), 0)
+        .unwrap();
+    mp3_file.write_to_file(&file.path).unwrap();
+
+    assert_eq!(file.bytes().len(), size);
+    assert_eq!(file.audio(), audio);
+    assert_eq!(title(&file.path), "A longer title");
+}
+
+#[test]
+fn writing_the_same_file_repeatedly_keeps_the_audio() {
+    let audio = audio(16_385);
+    let file = TestFile::new("repeated", &mp3("Title", 0, &audio));
+    let options = WriteOptions {
+        padding: 0,
+        ..WriteOptions::default()
+    };
+
+    let mut mp3_file = Mp3File::from_path(&file.path).unwrap();
+    for index in 0..3 {
+        mp3_file
+            .add_text_frame("TIT3".to_string(), &text_frame(&index.to_string()))
+            .unwrap();
+        mp3_file
+            .write_to_file_with_options(&file.path, &options)
+            .unwrap();
+        assert_eq!(file.audio(), audio);
+    }
+    let subtitles = Mp3File::from_path(&file.path)
+        .unwrap()
+        .frames_with_id("TIT3")
+        .count();
+    assert_eq!(subtitles, 3);
+}
+
+#[test]
+fn rewriting_a_file_without_audio_adds_none() {
+    let file = TestFile::new("no-audio", &mp3("Title", 4, &[]));
+
+    let mut mp3_file = Mp3File::from_path(&file.path).unwrap();
+    mp3_file
+        .add_text_frame("TIT3".to_string(), &text_frame("Subtitle"))
+        .unwrap();
+    mp3_file.write_to_file(&file.path).unwrap();
+
+    assert_eq!(file.audio(), Vec::<u8>::new());
+}
+
+#[test]
+fn rewriting_leaves_no_temporary_file_behind() {
+    let file = TestFile::new("temporary", &mp3("Title", 0, &audio(1_000)));
+
+    let mut mp3_file = Mp3File::from_path(&file.path).unwrap();
+    mp3_file
+        .add_text_frame("TIT3".to_string(), &text_frame("Subtitle"))
+        .unwrap();
+    mp3_file.write_to_file(&file.path).unwrap();
+
+    let file_name = file.path.file_name().unwrap().to_string_lossy().to_string();
+    let leftovers = fs::read_dir(file.path.parent().unwrap())
+        .unwrap()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            name.starts_with(&format!(".{}", file_name))
+        })
+        .count();
+    assert_eq!(leftovers, 0);
+}
+
+#[cfg(unix)]
+#[test]
+fn rewriting_keeps_the_permissions_and_owner() {
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+    let file = TestFile::new("permissions", &mp3("Title", 0, &audio(1_000)));
+    fs::set_permissions(&file.path, fs::Permissions::from_mode(0o640)).unwrap();
+    let original = fs::metadata(&file.path).unwrap();
+
+    let mut mp3_file = Mp3File::from_path(&file.path).unwrap();
+    mp3_file
+        .add_text_frame("TIT3".to_string(), &text_frame("Subtitle"))
+        .unwrap();
+    mp3_file.write_to_file(&file.path).unwrap();
+
+    let rewritten = fs::metadata(&file.path).unwrap();
+    assert_ne!(rewritten.ino(), original.ino());
+    assert_eq!(rewritten.permissions().mode() & 0o7777, 0o640);
+    assert_eq!(
+        (rewritten.uid(), rewritten.gid()),
+        (original.uid(), original.gid())
+    );
+}